@@ -1,16 +1,88 @@
+use crate::error_boundary::{CaughtError, ResetHandle, catch};
+use crate::lifecycle::collect_hooks;
 use crate::mutations::Mutation;
-use crate::vdom::{Element, NodeId, VDomArena, VirtualNode};
+use crate::node_ref::RefUpdate;
+use crate::profiler::ProfilePhase;
+use crate::template::{Template, TemplateNode};
+use crate::vdom::{
+    AttributeValue, Caught, Component, Element, ErrorBoundary, NodeId, VDomArena, VirtualNode,
+    attribute_namespace, tag_namespace,
+};
 use slotmap::Key; // Import Key trait for .data()
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
-use crate::runtime::{Scope, ScopeId};
+use crate::runtime::{DirtyScopes, Scope, ScopeId};
+use crate::suspense::collect_pending;
+use nexa_signals::NodeType;
+use nexa_signals::dependency::{GRAPH, allocate_node, remove_node, set_update_fn, with_observer};
 use slotmap::SlotMap;
+use smallvec::SmallVec;
+use std::rc::Rc;
 
 pub struct Differ<'a> {
     pub arena: &'a mut VDomArena,
     pub mutation_buffer: &'a mut Vec<Mutation>,
     pub profiling: &'a mut crate::runtime::Profiling,
     pub scopes: &'a mut SlotMap<ScopeId, Scope>,
+    pub dirty_scopes: &'a DirtyScopes,
+    /// Names of the templates already sent to the renderer.
+    pub templates: &'a mut HashSet<String>,
+    /// Scope whose subtree is currently being created or diffed.
+    /// New component scopes are parented to it.
+    pub current_scope: Option<ScopeId>,
+    /// Scopes created by this diff, waiting for their mount hooks.
+    pub mounted: Vec<ScopeId>,
+    /// Scopes re-rendered by this diff, waiting for their update hooks.
+    pub updated: Vec<ScopeId>,
+    /// Arena nodes no longer reachable from the committed tree.
+    /// The runtime frees them once the commit is done.
+    pub garbage: Vec<NodeId>,
+    /// Failures caught during this diff, with the node they came from.
+    /// Failures inside a boundary being created are handled by it; the
+    /// rest wait for `show_errors` once the diff is done.
+    pub errors: Vec<(NodeId, CaughtError)>,
+    /// Node refs to fill in or clear once the diff is committed.
+    pub(crate) refs: Vec<RefUpdate>,
+}
+
+/// Where the next server-rendered node is among its parent's children.
+#[derive(Debug, Default, Clone, Copy)]
+struct HydrationCursor {
+    index: usize,
+    /// The last node was text, so the server put a comment between it and
+    /// any text that follows, to keep the two from merging.
+    after_text: bool,
+}
+
+impl HydrationCursor {
+    fn path(&self, parent_path: &[u8]) -> Option<Vec<u8>> {
+        let Ok(index) = u8::try_from(self.index) else {
+            tracing::error!(
+                "Cannot hydrate child {} of {:?}: paths only reach 256 children",
+                self.index,
+                parent_path
+            );
+            return None;
+        };
+        let mut path = parent_path.to_vec();
+        path.push(index);
+        Some(path)
+    }
+
+    fn advance(&mut self, text: bool) {
+        self.index += 1;
+        self.after_text = text;
+    }
+}
+
+/// Where the differ's output stood before creating a boundary's content.
+struct Checkpoint {
+    mutations: usize,
+    mutation_count: u64,
+    templates: HashSet<String>,
+    mounted: usize,
+    errors: usize,
+    refs: usize,
 }
 
 impl<'a> Differ<'a> {
@@ -19,23 +91,158 @@ impl<'a> Differ<'a> {
         mutation_buffer: &'a mut Vec<Mutation>,
         profiling: &'a mut crate::runtime::Profiling,
         scopes: &'a mut SlotMap<ScopeId, Scope>,
+        dirty_scopes: &'a DirtyScopes,
+        templates: &'a mut HashSet<String>,
     ) -> Self {
         Self {
             arena,
             mutation_buffer,
             profiling,
             scopes,
+            dirty_scopes,
+            templates,
+            current_scope: None,
+            mounted: Vec::new(),
+            updated: Vec::new(),
+            garbage: Vec::new(),
+            errors: Vec::new(),
+            refs: Vec::new(),
         }
     }
 
-    pub fn diff_nodes(&mut self, old_id: NodeId, new_id: NodeId, parent: Option<NodeId>) {
+    /// Creates a scope for a component, along with the reactive effect that
+    /// tracks the signals its render function reads.
+    pub fn create_scope(&mut self, node: NodeId, component: &Component) -> ScopeId {
+        let parent = self.current_scope;
+        let height = parent
+            .and_then(|p| self.scopes.get(p))
+            .map(|p| p.height + 1)
+            .unwrap_or(0);
+        let effect = allocate_node(NodeType::Effect);
+
+        let scope_id = self.scopes.insert_with_key(|id| Scope {
+            id,
+            name: component.name.to_string(),
+            lifecycle: Default::default(),
+            root_node: None,
+            node,
+            parent,
+            height,
+            effect,
+            render_fn: component.render_fn.clone(),
+            props: component.props.clone(),
+        });
+
+        // Signal propagation runs the effect eagerly, but rendering needs the
+        // runtime, so the effect only queues its scope for the next update.
+        let dirty_scopes = Rc::clone(self.dirty_scopes);
+        set_update_fn(
+            effect,
+            Rc::new(move || {
+                let mut dirty = dirty_scopes.borrow_mut();
+                if !dirty.contains(&scope_id) {
+                    dirty.push(scope_id);
+                }
+            }),
+        );
+
+        scope_id
+    }
+
+    /// Runs a scope's render function with its effect as the observer,
+    /// re-tracking its dependencies, and returns the new root.
+    /// The scope's `root_node` is left untouched. A panicking render returns
+    /// `None`, with the error queued in `errors`.
+    pub fn render_scope(&mut self, scope_id: ScopeId) -> Option<NodeId> {
+        let scope = self.scopes.get(scope_id)?;
+        let (render_fn, props, effect) =
+            (scope.render_fn.clone(), scope.props.clone(), scope.effect);
+        let first_render = scope.root_node.is_none();
+
+        // Rendering now satisfies any pending re-render of this scope.
+        self.dirty_scopes.borrow_mut().retain(|&s| s != scope_id);
+
+        self.profiling.count_render(&scope.name);
+        let span = self.open_span(ProfilePhase::Render, scope_id);
+        let arena = &mut *self.arena;
+        let (root_id, hooks) = collect_hooks(|| {
+            with_observer(effect, || unsafe {
+                crate::vdom::set_active_arena(arena, || catch(|| render_fn(&*props)))
+            })
+        });
+        self.close_span(span);
+
+        let scope = self.scopes.get_mut(scope_id)?;
+        match root_id {
+            Ok(root_id) => {
+                scope.lifecycle.replace_with(hooks, first_render);
+                Some(root_id)
+            }
+            Err(error) => {
+                self.errors.push((scope.node, error));
+                None
+            }
+        }
+    }
+
+    fn open_span(&mut self, phase: ProfilePhase, scope_id: ScopeId) -> Option<usize> {
+        self.profiling
+            .open_span(phase, self.scopes, scope_id, self.mutation_buffer.len())
+    }
+
+    fn close_span(&mut self, span: Option<usize>) {
+        self.profiling.close_span(span, self.mutation_buffer.len());
+    }
+
+    /// Re-renders a mounted scope and diffs the result against its current root.
+    pub fn diff_scope(&mut self, scope_id: ScopeId) {
+        let old_root_id = match self.scopes.get(scope_id) {
+            Some(scope) => scope.root_node,
+            None => return,
+        };
+        let Some(new_root_id) = self.render_scope(scope_id) else {
+            return;
+        };
+
+        let span = self.open_span(ProfilePhase::Diff, scope_id);
+        let prev_scope = self.current_scope.replace(scope_id);
+        let root_id = if let Some(old_root_id) = old_root_id {
+            self.diff_nodes(old_root_id, new_root_id)
+        } else {
+            // Should not happen if mounted correctly, but treat as new
+            self.create_tree(new_root_id);
+            self.mutation_buffer.push(Mutation::AppendChildren {
+                id: 0,
+                m: self.flatten_node(new_root_id),
+            });
+            self.profiling.mutation_count += 1;
+            new_root_id
+        };
+        self.current_scope = prev_scope;
+        self.close_span(span);
+
+        if let Some(scope) = self.scopes.get_mut(scope_id) {
+            scope.root_node = Some(root_id);
+        }
+        self.updated.push(scope_id);
+    }
+
+    /// Diffs `new_id` against the committed node `old_id`, emitting mutations.
+    ///
+    /// When the old node can be reused, its DOM node keeps its id: the new
+    /// contents are written into the old arena slot and `old_id` is returned.
+    /// Otherwise the old subtree is replaced and `new_id` is returned.
+    /// Callers store the returned id in place of the old one.
+    pub fn diff_nodes(&mut self, old_id: NodeId, new_id: NodeId) -> NodeId {
         let (is_static, old_count) = {
             let meta = self.arena.metadata.get(new_id).cloned().unwrap_or_default();
             (meta.is_static, meta.render_count)
         };
 
         if is_static && old_count > 0 {
-            return; // Skip diffing static subtree
+            // Skip diffing static subtree
+            self.discard_subtree(new_id);
+            return old_id;
         }
 
         self.profiling.diff_count += 1;
@@ -44,15 +251,28 @@ impl<'a> Differ<'a> {
             meta.render_count += 1;
         }
 
+        let committed = self.patch_node(old_id, new_id);
+        if committed == old_id {
+            // The new contents now live in the old slot; the new one is garbage.
+            let new_meta = self.arena.metadata.get(new_id).copied().unwrap_or_default();
+            if let Some(meta) = self.arena.metadata.get_mut(old_id) {
+                meta.is_static = new_meta.is_static;
+                meta.template = new_meta.template;
+                meta.render_count += 1;
+            }
+            self.garbage.push(new_id);
+        }
+        committed
+    }
+
+    /// Diffs two nodes of any type; returns the committed id like `diff_nodes`.
+    fn patch_node(&mut self, old_id: NodeId, new_id: NodeId) -> NodeId {
         let old_node_type_disc = self.arena.nodes.get(old_id).map(std::mem::discriminant);
         let new_node_type_disc = self.arena.nodes.get(new_id).map(std::mem::discriminant);
 
         if old_node_type_disc != new_node_type_disc {
             // Types differ, replace node
-            if let Some(p) = parent {
-                self.replace_node(old_id, new_id, p);
-            }
-            return;
+            return self.replace_node(old_id, new_id);
         }
 
         // Clone nodes to avoid holding immutable borrow of arena while calling specific diff methods
@@ -60,133 +280,536 @@ impl<'a> Differ<'a> {
         let new_node = self.arena.nodes.get(new_id).cloned();
 
         match (old_node, new_node) {
-            (Some(VirtualNode::Text(old_t)), Some(VirtualNode::Text(new_t))) => {
+            (Some(VirtualNode::Text(old_t)), Some(VirtualNode::Text(mut new_t))) => {
                 if old_t.text != new_t.text {
-                    let text = new_t.text.clone();
                     self.mutation_buffer.push(Mutation::SetText {
-                        id: new_id.data().as_ffi(),
-                        value: text,
+                        id: old_id.data().as_ffi(),
+                        value: new_t.text.clone(),
                     });
                     self.profiling.mutation_count += 1;
                 }
+                new_t.parent = old_t.parent;
+                self.store(old_id, VirtualNode::Text(new_t));
+                old_id
             }
-            (Some(VirtualNode::Element(old_el)), Some(VirtualNode::Element(new_el))) => {
-                if old_el.tag != new_el.tag {
-                    if let Some(p) = parent {
-                        self.replace_node(old_id, new_id, p);
+            (Some(VirtualNode::Element(old_el)), Some(VirtualNode::Element(mut new_el))) => {
+                // Nodes from different templates may not have ids in the same places.
+                if old_el.tag != new_el.tag
+                    || old_el.namespace != new_el.namespace
+                    || self.template_of(old_id) != self.template_of(new_id)
+                {
+                    return self.replace_node(old_id, new_id);
+                }
+
+                if old_el.node_ref != new_el.node_ref {
+                    let ffi_id = old_id.data().as_ffi();
+                    if let Some(node_ref) = old_el.node_ref.clone() {
+                        self.refs.push(RefUpdate::Clear(node_ref, ffi_id));
                     }
-                } else {
-                    // Diff Attributes
-                    self.diff_attributes(new_id, &old_el.clone(), &new_el.clone());
-
-                    // Diff Children
-                    let old_c = old_el.children.clone();
-                    let new_c = new_el.children.clone();
-                    self.diff_children(new_id, &old_c, &new_c);
-                }
-            }
-            (Some(VirtualNode::Fragment(old_f)), Some(VirtualNode::Fragment(new_f))) => {
-                let old_c = old_f.children.clone();
-                let new_c = new_f.children.clone();
-                self.diff_children(new_id, &old_c, &new_c);
-            }
-            (Some(VirtualNode::Component(old_comp)), Some(VirtualNode::Component(new_comp))) => {
-                // Check if same component type (same function pointer)
-                // Cast to usize to avoid predictable function pointer comparison lint
-                if (old_comp.render_fn as usize) == (new_comp.render_fn as usize) {
-                    if let Some(scope_id) = old_comp.scope {
-                        // Reuse scope
-                        if let Some(VirtualNode::Component(c)) = self.arena.nodes.get_mut(new_id) {
-                            c.scope = Some(scope_id);
-                        }
+                    if let Some(node_ref) = new_el.node_ref.clone() {
+                        self.refs.push(RefUpdate::Set(node_ref, ffi_id));
+                    }
+                }
 
-                        // Re-render
-                        let render_fn = new_comp.render_fn;
-                        let new_root_id =
-                            unsafe { crate::vdom::set_active_arena(self.arena, || (render_fn)()) };
+                // Diff Attributes
+                self.diff_attributes(old_id, &old_el, &new_el);
+                self.diff_listeners(old_id, &old_el, &new_el);
 
-                        // Get old root and update scope
-                        let mut old_root_id_opt = None;
-                        if let Some(scope) = self.scopes.get_mut(scope_id) {
-                            old_root_id_opt = scope.root_node;
-                            scope.root_node = Some(new_root_id);
-                        }
+                // Diff Children
+                new_el.children = self.diff_children(old_id, &old_el.children, &new_el.children);
+                new_el.parent = old_el.parent;
+                self.store(old_id, VirtualNode::Element(new_el));
+                old_id
+            }
+            (Some(VirtualNode::Fragment(old_f)), Some(VirtualNode::Fragment(mut new_f))) => {
+                new_f.children = self.diff_children(old_id, &old_f.children, &new_f.children);
+                new_f.parent = old_f.parent;
+                self.store(old_id, VirtualNode::Fragment(new_f));
+                old_id
+            }
+            (
+                Some(VirtualNode::Component(old_comp)),
+                Some(VirtualNode::Component(mut new_comp)),
+            ) => {
+                // Check if same component type (same render function)
+                if old_comp.type_id != new_comp.type_id {
+                    // Different component, replace
+                    return self.replace_node(old_id, new_id);
+                }
+                let Some(scope_id) = old_comp.scope else {
+                    // Old component had no scope? Treat as new.
+                    return self.replace_node(old_id, new_id);
+                };
 
-                        if let Some(old_root_id) = old_root_id_opt {
-                            self.diff_nodes(old_root_id, new_root_id, parent);
-                        } else {
-                            // Should not happen if mounted correctly, but treat as new
-                            self.create_tree(new_root_id);
-                            // Append? Component has no parent DOM node to append ONLY to?
-                            // It relies on parent passed from diff_nodes.
-                            // But diff_nodes(parent) is the PARENT of the component (e.g. div).
-                            // We need to insert new_root_id into parent.
-                            // But since it's "update", likely the old nodes are gone or we are just replacing?
-                            // If old_root was None, we append.
-                            self.mutation_buffer.push(Mutation::AppendChildren {
-                                id: parent.map(|p| p.data().as_ffi()).unwrap_or(0),
-                                m: self.flatten_node(new_root_id),
-                            });
-                            self.profiling.mutation_count += 1;
-                        }
-                    } else {
-                        // Old component had no scope? Treat as new.
-                        if let Some(p) = parent {
-                            self.replace_node(old_id, new_id, p);
+                // Reuse scope, and re-render only if the props changed
+                let props_changed = match &new_comp.props_eq {
+                    Some(eq) => !eq(&*old_comp.props, &*new_comp.props),
+                    // `Component::eager`: the props can't be compared.
+                    None => true,
+                };
+                if let Some(scope) = self.scopes.get_mut(scope_id) {
+                    scope.render_fn = new_comp.render_fn.clone();
+                    scope.props = new_comp.props.clone();
+                }
+                new_comp.scope = Some(scope_id);
+                new_comp.parent = old_comp.parent;
+                self.store(old_id, VirtualNode::Component(new_comp));
+                if props_changed {
+                    self.diff_scope(scope_id);
+                } else {
+                    self.profiling.skipped_renders += 1;
+                }
+                old_id
+            }
+            (Some(VirtualNode::Suspense(old_s)), Some(VirtualNode::Suspense(mut new_s))) => {
+                if !old_s.suspended {
+                    // Revealed content stays up while later async work is pending.
+                    let (actual, _) =
+                        collect_pending(|| self.diff_nodes(old_s.actual, new_s.actual));
+                    new_s.actual = actual;
+                    self.discard_subtree(new_s.fallback);
+                } else if self.try_create_content(old_id, new_s.actual) {
+                    self.reveal(old_s.fallback, new_s.actual);
+                    self.discard_subtree(new_s.fallback);
+                } else {
+                    new_s.fallback = self.diff_nodes(old_s.fallback, new_s.fallback);
+                    new_s.suspended = true;
+                }
+                new_s.parent = old_s.parent;
+                self.store(old_id, VirtualNode::Suspense(new_s));
+                old_id
+            }
+            (
+                Some(VirtualNode::ErrorBoundary(old_b)),
+                Some(VirtualNode::ErrorBoundary(mut new_b)),
+            ) => {
+                new_b.caught = match old_b.caught {
+                    None => {
+                        new_b.content = self.diff_nodes(old_b.content, new_b.content);
+                        None
+                    }
+                    Some(caught) if caught.reset.requested() => {
+                        match self.try_create_boundary_content(old_id, new_b.content) {
+                            Ok(()) => {
+                                self.reveal(caught.fallback, new_b.content);
+                                None
+                            }
+                            Err(error) => {
+                                let reset = ResetHandle::new(
+                                    Rc::clone(self.dirty_scopes),
+                                    self.current_scope,
+                                );
+                                let fallback =
+                                    self.fallback_node(&new_b, error.clone(), reset.clone());
+                                let fallback = self.diff_nodes(caught.fallback, fallback);
+                                Some(Box::new(Caught {
+                                    error,
+                                    reset,
+                                    fallback,
+                                }))
+                            }
                         }
                     }
-                } else {
-                    // Different component, replace
-                    if let Some(p) = parent {
-                        self.replace_node(old_id, new_id, p);
+                    Some(caught) => {
+                        // The fallback stays up until it is reset.
+                        self.discard_subtree(new_b.content);
+                        let fallback =
+                            self.fallback_node(&new_b, caught.error.clone(), caught.reset.clone());
+                        let fallback = self.diff_nodes(caught.fallback, fallback);
+                        Some(Box::new(Caught {
+                            fallback,
+                            ..*caught
+                        }))
                     }
-                }
+                };
+                new_b.parent = old_b.parent;
+                self.store(old_id, VirtualNode::ErrorBoundary(new_b));
+                old_id
             }
-            (Some(VirtualNode::Suspense(old_s)), Some(VirtualNode::Suspense(new_s))) => {
-                // Diff actual
-                self.diff_nodes(old_s.actual, new_s.actual, parent);
+            (Some(VirtualNode::Portal(old_p)), Some(VirtualNode::Portal(mut new_p))) => {
+                if old_p.target != new_p.target {
+                    return self.replace_node(old_id, new_id);
+                }
+                new_p.content = self.diff_nodes(old_p.content, new_p.content);
+                new_p.parent = old_p.parent;
+                self.store(old_id, VirtualNode::Portal(new_p));
+                old_id
             }
-            // Add component/suspense diffing here
+            (Some(VirtualNode::Placeholder), Some(VirtualNode::Placeholder)) => old_id,
             _ => {
                 // Should be covered by discriminant check, but just in case
-                if let Some(p) = parent {
-                    self.replace_node(old_id, new_id, p);
-                }
+                self.replace_node(old_id, new_id)
             }
         }
     }
 
-    fn replace_node(&mut self, old_id: NodeId, new_id: NodeId, parent: NodeId) {
-        // 1. Create new tree
+    fn replace_node(&mut self, old_id: NodeId, new_id: NodeId) -> NodeId {
+        // 1. Create new tree in the old node's place
+        let parent = self.parent_of(old_id);
+        self.set_parent(new_id, parent);
         self.create_tree(new_id);
 
-        // 2. Insert new node before old node (to keep position) or just append?
-        // Logic: Insert new, Remove old.
-        // We need a reference sibling for InsertBefore.
-        // If we just use Append, it goes to end.
-        // But for replacement, we want exact spot.
-        // We can use InsertBefore old_id.
-
-        self.mutation_buffer.push(Mutation::InsertBefore {
-            id: parent.data().as_ffi(),
-            m: vec![new_id.data().as_ffi()],
-            // We need 'before_id' logic in Mutation?
-            // Mutation::InsertBefore usually takes (parentId, newId, refId).
-            // Check Mutation definition.
-            // Wait, Mutation::InsertBefore definition in mutations.rs might be different.
-            // Let's assume InsertBefore { id: parent, m: [new_id], before: old_id } ??
-            // Checking runtime.rs logic:
-            // self.mutation_buffer.push(Mutation::InsertBefore { id: parent.., m: vec![..] })
-            // It seems missing 'reference'.
-            // Let's check mutations.rs definition later.
-            // For now, assume a standard Replace operation or Insert+Remove.
-        });
+        // 2. Insert the new DOM nodes before the first old one to keep position,
+        //    falling back to appending when the old node had nothing in the DOM.
+        let new_nodes = self.flatten_node(new_id);
+        if !new_nodes.is_empty() {
+            if let Some(first_old) = self.first_dom_node(old_id) {
+                self.mutation_buffer.push(Mutation::InsertBefore {
+                    id: first_old,
+                    m: new_nodes,
+                });
+                self.profiling.mutation_count += 1;
+            } else {
+                self.mutation_buffer.push(Mutation::AppendChildren {
+                    id: self.dom_container(parent),
+                    m: new_nodes,
+                });
+                self.profiling.mutation_count += 1;
+            }
+        }
 
-        self.mutation_buffer.push(Mutation::Remove {
-            id: old_id.data().as_ffi(),
-        });
+        // 3. Remove old
+        self.remove_subtree(old_id);
 
-        self.profiling.mutation_count += 2;
+        new_id
+    }
+
+    /// Removes a committed subtree from the DOM and tears down the scopes inside it.
+    pub fn remove_subtree(&mut self, id: NodeId) {
+        let mut dom_ids = self.flatten_node(id);
+        self.portal_dom_nodes(id, &mut dom_ids);
+        for dom_id in dom_ids {
+            self.mutation_buffer.push(Mutation::Remove { id: dom_id });
+            self.profiling.mutation_count += 1;
+        }
+        self.release_subtree(id);
+    }
+
+    /// Tears down the scopes in a committed subtree and marks its nodes as garbage.
+    fn release_subtree(&mut self, id: NodeId) {
+        self.garbage.push(id);
+        let children: Vec<NodeId> = match self.arena.nodes.get(id) {
+            Some(VirtualNode::Element(el)) => {
+                if let Some(node_ref) = &el.node_ref {
+                    self.refs
+                        .push(RefUpdate::Clear(node_ref.clone(), id.data().as_ffi()));
+                }
+                el.children.to_vec()
+            }
+            Some(VirtualNode::Fragment(frag)) => frag.children.to_vec(),
+            Some(VirtualNode::Suspense(susp)) => vec![susp.fallback, susp.actual],
+            Some(VirtualNode::ErrorBoundary(eb)) => eb
+                .caught
+                .iter()
+                .map(|c| c.fallback)
+                .chain([eb.content])
+                .collect(),
+            Some(VirtualNode::Portal(portal)) => vec![portal.content],
+            Some(VirtualNode::Component(comp)) => {
+                if let Some(scope_id) = comp.scope {
+                    self.drop_scope(scope_id);
+                }
+                vec![]
+            }
+            _ => vec![],
+        };
+        for child in children {
+            self.release_subtree(child);
+        }
+    }
+
+    /// Collects the top-level DOM nodes of the portals in a committed
+    /// subtree. They live in other containers, so removing the subtree's own
+    /// DOM nodes doesn't take them along.
+    fn portal_dom_nodes(&self, id: NodeId, out: &mut Vec<u64>) {
+        let children: Vec<NodeId> = match self.arena.nodes.get(id) {
+            Some(VirtualNode::Element(el)) => el.children.to_vec(),
+            Some(VirtualNode::Fragment(frag)) => frag.children.to_vec(),
+            Some(VirtualNode::Component(comp)) => comp
+                .scope
+                .and_then(|s| self.scopes.get(s))
+                .and_then(|s| s.root_node)
+                .into_iter()
+                .collect(),
+            Some(VirtualNode::Suspense(susp)) if susp.suspended => vec![susp.fallback],
+            Some(VirtualNode::Suspense(susp)) => vec![susp.actual],
+            Some(VirtualNode::ErrorBoundary(eb)) => {
+                vec![eb.caught.as_ref().map_or(eb.content, |c| c.fallback)]
+            }
+            Some(VirtualNode::Portal(portal)) => {
+                out.extend(self.flatten_node(portal.content));
+                vec![portal.content]
+            }
+            _ => vec![],
+        };
+        for child in children {
+            self.portal_dom_nodes(child, out);
+        }
+    }
+
+    /// Marks a subtree that was never committed as garbage. Scopes created
+    /// for it are dropped without unmount hooks, as they never mounted.
+    fn discard_subtree(&mut self, id: NodeId) {
+        self.garbage.push(id);
+        let children: Vec<NodeId> = match self.arena.nodes.get(id) {
+            Some(VirtualNode::Element(el)) => el.children.to_vec(),
+            Some(VirtualNode::Fragment(frag)) => frag.children.to_vec(),
+            Some(VirtualNode::Suspense(susp)) => vec![susp.fallback, susp.actual],
+            Some(VirtualNode::ErrorBoundary(eb)) => eb
+                .caught
+                .iter()
+                .map(|c| c.fallback)
+                .chain([eb.content])
+                .collect(),
+            Some(VirtualNode::Portal(portal)) => vec![portal.content],
+            Some(VirtualNode::Component(comp)) => comp
+                .scope
+                .and_then(|scope_id| self.scopes.remove(scope_id))
+                .map(|scope| {
+                    remove_node(scope.effect);
+                    self.dirty_scopes.borrow_mut().retain(|&s| s != scope.id);
+                    scope.root_node.into_iter().collect()
+                })
+                .unwrap_or_default(),
+            _ => vec![],
+        };
+        for child in children {
+            self.discard_subtree(child);
+        }
+    }
+
+    /// Creates the content of the Suspense boundary `boundary`, keeping it
+    /// only if nothing in it read a pending resource. Otherwise the content is
+    /// rolled back, and the scope that rendered the boundary subscribes to the
+    /// pending resources so it re-renders, and retries, once they resolve.
+    fn try_create_content(&mut self, boundary: NodeId, content: NodeId) -> bool {
+        let checkpoint = self.checkpoint();
+        self.set_parent(content, Some(boundary));
+        let ((), pending) = collect_pending(|| self.create_tree(content));
+        if pending.is_empty() {
+            return true;
+        }
+        self.rollback(checkpoint, content);
+
+        if let Some(effect) = self
+            .current_scope
+            .and_then(|s| self.scopes.get(s))
+            .map(|s| s.effect)
+        {
+            GRAPH.with(|g| {
+                let mut graph = g.borrow_mut();
+                for resource in pending {
+                    graph.add_dependency(effect, resource);
+                }
+            });
+        }
+        false
+    }
+
+    /// Creates the content of the error boundary `boundary`. If anything in
+    /// it failed, the content is rolled back and the first error returned.
+    fn try_create_boundary_content(
+        &mut self,
+        boundary: NodeId,
+        content: NodeId,
+    ) -> Result<(), CaughtError> {
+        let checkpoint = self.checkpoint();
+        self.set_parent(content, Some(boundary));
+        self.create_tree(content);
+        let Some((_, error)) = self.errors.get(checkpoint.errors).cloned() else {
+            return Ok(());
+        };
+        self.rollback(checkpoint, content);
+        Err(error)
+    }
+
+    fn checkpoint(&self) -> Checkpoint {
+        Checkpoint {
+            mutations: self.mutation_buffer.len(),
+            mutation_count: self.profiling.mutation_count,
+            templates: self.templates.clone(),
+            mounted: self.mounted.len(),
+            errors: self.errors.len(),
+            refs: self.refs.len(),
+        }
+    }
+
+    /// Undoes the creation of `content` since `checkpoint`.
+    fn rollback(&mut self, checkpoint: Checkpoint, content: NodeId) {
+        self.mutation_buffer.truncate(checkpoint.mutations);
+        self.profiling.mutation_count = checkpoint.mutation_count;
+        *self.templates = checkpoint.templates;
+        self.mounted.truncate(checkpoint.mounted);
+        self.errors.truncate(checkpoint.errors);
+        self.refs.truncate(checkpoint.refs);
+        self.discard_subtree(content);
+    }
+
+    /// Inserts the component that renders `boundary`'s fallback for `error`.
+    fn fallback_node(
+        &mut self,
+        boundary: &ErrorBoundary,
+        error: CaughtError,
+        reset: ResetHandle,
+    ) -> NodeId {
+        let fallback = Rc::clone(&boundary.fallback);
+        self.arena.insert(VirtualNode::Component(Component::new(
+            "ErrorFallback",
+            move |(error, reset): (CaughtError, ResetHandle)| fallback(error, reset),
+            (error, reset),
+        )))
+    }
+
+    /// Creates the fallback of the boundary `id` for `error`. The reset
+    /// handle re-renders `owner`, the scope that rendered the boundary.
+    fn create_fallback(
+        &mut self,
+        id: NodeId,
+        error: CaughtError,
+        owner: Option<ScopeId>,
+    ) -> NodeId {
+        self.build_fallback(id, error, owner, Self::create_tree)
+    }
+
+    /// Inserts the fallback of the boundary `id` for `error` and builds it
+    /// with `build`, which creates or hydrates it.
+    fn build_fallback(
+        &mut self,
+        id: NodeId,
+        error: CaughtError,
+        owner: Option<ScopeId>,
+        build: impl FnOnce(&mut Self, NodeId),
+    ) -> NodeId {
+        let Some(VirtualNode::ErrorBoundary(boundary)) = self.arena.nodes.get(id).cloned() else {
+            unreachable!("fallback created for a node that is not an error boundary");
+        };
+        let reset = ResetHandle::new(Rc::clone(self.dirty_scopes), owner);
+        let fallback = self.fallback_node(&boundary, error.clone(), reset.clone());
+        self.set_parent(fallback, Some(id));
+        let prev_scope = std::mem::replace(&mut self.current_scope, owner);
+        build(self, fallback);
+        self.current_scope = prev_scope;
+        if let Some(VirtualNode::ErrorBoundary(b)) = self.arena.nodes.get_mut(id) {
+            b.caught = Some(Box::new(Caught {
+                error,
+                reset,
+                fallback,
+            }));
+        }
+        fallback
+    }
+
+    /// Shows the fallback of the nearest boundary above each failed node.
+    /// Runs once a diff is done, so the boundaries it swaps are committed.
+    ///
+    /// # Panics
+    /// If a failure has no boundary above it.
+    pub fn show_errors(&mut self, errors: Vec<(NodeId, CaughtError)>) {
+        for (node, error) in errors {
+            if !self.arena.nodes.contains(node) {
+                // Removed along with an earlier failure's content.
+                continue;
+            }
+            let Some(boundary) = self.boundary_of(node) else {
+                panic!("uncaught error in component: {error}");
+            };
+            let Some(VirtualNode::ErrorBoundary(b)) = self.arena.nodes.get(boundary).cloned()
+            else {
+                continue;
+            };
+            if b.caught.is_some() {
+                continue;
+            }
+            let owner = self.owner_scope(boundary);
+            let fallback = self.create_fallback(boundary, error, owner);
+            self.reveal(b.content, fallback);
+        }
+    }
+
+    /// The nearest error boundary whose content holds `id`. Fallbacks don't
+    /// catch their own failures; those go to the next boundary up.
+    fn boundary_of(&self, id: NodeId) -> Option<NodeId> {
+        let mut child = id;
+        let mut current = self.parent_of(id);
+        while let Some(node_id) = current {
+            if let Some(VirtualNode::ErrorBoundary(b)) = self.arena.nodes.get(node_id)
+                && b.content == child
+            {
+                return Some(node_id);
+            }
+            child = node_id;
+            current = self.parent_of(node_id);
+        }
+        None
+    }
+
+    /// The scope of the nearest component above `id`.
+    fn owner_scope(&self, id: NodeId) -> Option<ScopeId> {
+        let mut current = self.parent_of(id);
+        while let Some(node_id) = current {
+            if let Some(VirtualNode::Component(comp)) = self.arena.nodes.get(node_id) {
+                return comp.scope;
+            }
+            current = self.parent_of(node_id);
+        }
+        None
+    }
+
+    /// Swaps a boundary's committed child `old` for the newly created `new`:
+    /// a fallback for content, or content for a fallback.
+    fn reveal(&mut self, old: NodeId, new: NodeId) {
+        let new_nodes = self.flatten_node(new);
+        if !new_nodes.is_empty() {
+            if let Some(first) = self.first_dom_node(old) {
+                self.mutation_buffer.push(Mutation::InsertBefore {
+                    id: first,
+                    m: new_nodes,
+                });
+            } else {
+                let container = self.dom_container(self.parent_of(new));
+                self.mutation_buffer.push(Mutation::AppendChildren {
+                    id: container,
+                    m: new_nodes,
+                });
+            }
+            self.profiling.mutation_count += 1;
+        }
+        self.remove_subtree(old);
+    }
+
+    fn drop_scope(&mut self, scope_id: ScopeId) {
+        if let Some(scope) = self.scopes.remove(scope_id) {
+            remove_node(scope.effect);
+            self.dirty_scopes.borrow_mut().retain(|&s| s != scope_id);
+            if let Some(root) = scope.root_node {
+                self.release_subtree(root);
+            }
+            // Children are dropped first, so unmount hooks run bottom-up.
+            for hook in scope.lifecycle.on_unmount {
+                if let Err(error) = catch(hook) {
+                    self.unmount_failed(scope.node, error);
+                }
+            }
+        }
+    }
+
+    /// Hands a failed unmount hook to `show_errors` through the nearest node
+    /// above `node` that outlives the removal; the scope's own nodes are
+    /// garbage by the time it runs.
+    ///
+    /// # Panics
+    /// If the whole tree above `node` is being removed, as there can be no
+    /// boundary left to catch the failure.
+    fn unmount_failed(&mut self, node: NodeId, error: CaughtError) {
+        let mut current = self.parent_of(node);
+        while let Some(id) = current {
+            if !self.garbage.contains(&id) {
+                self.errors.push((id, error));
+                return;
+            }
+            current = self.parent_of(id);
+        }
+        panic!("uncaught error in component: {error}");
     }
 
     pub fn create_tree(&mut self, id: NodeId) {
@@ -200,18 +823,39 @@ impl<'a> Differ<'a> {
 
         match node {
             VirtualNode::Element(el) => {
-                self.mutation_buffer.push(Mutation::CreateElement {
-                    tag: el.tag.to_string(),
-                    id: ffi_id,
+                if let Some(node_ref) = &el.node_ref {
+                    self.refs.push(RefUpdate::Set(node_ref.clone(), ffi_id));
+                }
+
+                let namespace = self.namespace_of(id);
+                // A skeleton built for another namespace would clone the wrong
+                // kind of element, e.g. an SVG icon rendered from its own component.
+                if let Some(template) = self.template_of(id)
+                    && template_namespace(template) == namespace
+                {
+                    self.create_from_template(id, template);
+                    return;
+                }
+
+                self.mutation_buffer.push(match namespace {
+                    Some(ns) => Mutation::CreateElementNs {
+                        tag: el.tag.to_string(),
+                        ns: ns.to_string(),
+                        id: ffi_id,
+                    },
+                    None => Mutation::CreateElement {
+                        tag: el.tag.to_string(),
+                        id: ffi_id,
+                    },
                 });
                 self.profiling.mutation_count += 1;
 
-                for prop in &el.props {
+                for prop in el.props.iter().filter(|p| p.value != AttributeValue::None) {
                     self.mutation_buffer.push(Mutation::SetAttribute {
                         name: prop.name.to_string(),
                         value: prop.value.clone(),
                         id: ffi_id,
-                        ns: None,
+                        ns: attribute_namespace(prop.name).map(str::to_string),
                     });
                     self.profiling.mutation_count += 1;
                 }
@@ -226,6 +870,7 @@ impl<'a> Differ<'a> {
 
                 let mut child_ids = Vec::new();
                 for &child_id in &el.children {
+                    self.set_parent(child_id, Some(id));
                     self.create_tree(child_id);
                     child_ids.extend(self.flatten_node(child_id));
                 }
@@ -247,76 +892,456 @@ impl<'a> Differ<'a> {
             }
             VirtualNode::Fragment(frag) => {
                 for &child in &frag.children {
+                    self.set_parent(child, Some(id));
                     self.create_tree(child);
                 }
             }
             VirtualNode::Component(comp) => {
-                let render_fn = comp.render_fn;
-                let name = comp.name;
-
-                // Create Scope
-                let scope_id = self.scopes.insert(Scope {
-                    id: Default::default(),
-                    name: name.to_string(),
-                    lifecycle: Default::default(),
-                    root_node: None,
+                let Some((scope_id, root_id)) = self.render_new_scope(id, &comp) else {
+                    return;
+                };
+
+                // Recurse
+                let span = self.open_span(ProfilePhase::Diff, scope_id);
+                let prev_scope = self.current_scope.replace(scope_id);
+                self.create_tree(root_id);
+                self.current_scope = prev_scope;
+                self.close_span(span);
+
+                // Children were pushed first, so mount hooks run bottom-up.
+                self.mounted.push(scope_id);
+            }
+            VirtualNode::Portal(portal) => {
+                self.mutation_buffer.push(Mutation::LoadContainer {
+                    target: portal.target.clone(),
+                    id: ffi_id,
                 });
+                self.profiling.mutation_count += 1;
 
-                // Run render
-                let root_id =
-                    unsafe { crate::vdom::set_active_arena(self.arena, || (render_fn)()) };
+                self.set_parent(portal.content, Some(id));
+                self.create_tree(portal.content);
+                let content = self.flatten_node(portal.content);
+                if !content.is_empty() {
+                    self.mutation_buffer.push(Mutation::AppendChildren {
+                        id: ffi_id,
+                        m: content,
+                    });
+                    self.profiling.mutation_count += 1;
+                }
+            }
+            VirtualNode::ErrorBoundary(boundary) => {
+                if let Err(error) = self.try_create_boundary_content(id, boundary.content) {
+                    self.create_fallback(id, error, self.current_scope);
+                }
+            }
+            VirtualNode::Suspense(susp) => {
+                if self.try_create_content(id, susp.actual) {
+                    self.discard_subtree(susp.fallback);
+                } else {
+                    self.set_parent(susp.fallback, Some(id));
+                    self.create_tree(susp.fallback);
+                    if let Some(VirtualNode::Suspense(s)) = self.arena.nodes.get_mut(id) {
+                        s.suspended = true;
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
 
-                // Update Scope with root
-                if let Some(scope) = self.scopes.get_mut(scope_id) {
-                    scope.root_node = Some(root_id);
+    /// Gives the component node `id` a scope and renders it for the first
+    /// time, returning the scope and the root it rendered. A failed render
+    /// returns `None`, with the error queued in `errors`.
+    fn render_new_scope(&mut self, id: NodeId, comp: &Component) -> Option<(ScopeId, NodeId)> {
+        let scope_id = self.create_scope(id, comp);
+        if let Some(VirtualNode::Component(c)) = self.arena.nodes.get_mut(id) {
+            c.scope = Some(scope_id);
+        }
+
+        let root_id = self.render_scope(scope_id)?;
+        if let Some(scope) = self.scopes.get_mut(scope_id) {
+            scope.root_node = Some(root_id);
+        }
+        self.set_parent(root_id, Some(id));
+        Some((scope_id, root_id))
+    }
+
+    /// The namespace element `id` is created in: its own, or else that of
+    /// the nearest element above it. `None` is HTML.
+    fn namespace_of(&self, id: NodeId) -> Option<&'static str> {
+        let mut current = id;
+        loop {
+            match self.arena.nodes.get(current)? {
+                VirtualNode::Element(el) => {
+                    if current != id && el.tag == "foreignObject" {
+                        return None;
+                    }
+                    if let Some(ns) = el.namespace.or_else(|| tag_namespace(el.tag)) {
+                        return Some(ns);
+                    }
+                    current = el.parent?;
                 }
+                // Portal content lands in a host container, which is HTML.
+                VirtualNode::Portal(_) => return None,
+                node => current = node.parent()?,
+            }
+        }
+    }
+
+    fn template_of(&self, id: NodeId) -> Option<&'static Template> {
+        self.arena.metadata.get(id).and_then(|meta| meta.template)
+    }
 
-                // Update Component node in Arena with ScopeId
-                if let Some(VirtualNode::Component(c)) = self.arena.nodes.get_mut(id) {
-                    c.scope = Some(scope_id);
+    /// Creates an element tree by cloning its template, then fills in the
+    /// dynamic parts: ids for nodes that will be patched later, dynamic
+    /// attributes and text, listeners, components and dynamic children.
+    fn create_from_template(&mut self, id: NodeId, template: &'static Template) {
+        if self.templates.insert(template.name.to_string()) {
+            self.mutation_buffer.push(Mutation::RegisterTemplate {
+                template: template.clone(),
+            });
+            self.profiling.mutation_count += 1;
+        }
+        self.mutation_buffer.push(Mutation::LoadTemplate {
+            name: template.name.to_string(),
+            index: 0,
+            id: id.data().as_ffi(),
+        });
+        self.profiling.mutation_count += 1;
+
+        let Some(root) = template.roots.first() else {
+            return;
+        };
+
+        // Paths are relative to the last loaded template, so everything that
+        // may load other templates waits until this one has been hydrated.
+        let mut deferred = Vec::new();
+        self.hydrate_template_node(root, id, &mut Vec::new(), &mut deferred);
+
+        for node_id in deferred {
+            match self.arena.nodes.get(node_id).cloned() {
+                Some(VirtualNode::Element(el)) => {
+                    let mut child_ids = Vec::new();
+                    for &child_id in &el.children {
+                        self.set_parent(child_id, Some(node_id));
+                        self.create_tree(child_id);
+                        child_ids.extend(self.flatten_node(child_id));
+                    }
+                    if !child_ids.is_empty() {
+                        self.mutation_buffer.push(Mutation::AppendChildren {
+                            id: node_id.data().as_ffi(),
+                            m: child_ids,
+                        });
+                        self.profiling.mutation_count += 1;
+                    }
+                }
+                Some(VirtualNode::Portal(_)) => {
+                    // The portal's id moves to its container, so the placeholder
+                    // goes first; portals take no room in their parent.
+                    self.mutation_buffer.push(Mutation::Remove {
+                        id: node_id.data().as_ffi(),
+                    });
+                    self.profiling.mutation_count += 1;
+                    self.create_tree(node_id);
+                }
+                Some(_) => {
+                    // A component slot: its placeholder was given the node's id.
+                    self.create_tree(node_id);
+                    self.mutation_buffer.push(Mutation::ReplaceWith {
+                        id: node_id.data().as_ffi(),
+                        m: self.flatten_node(node_id),
+                    });
+                    self.profiling.mutation_count += 1;
                 }
+                None => {}
+            }
+        }
+    }
 
-                // Recurse
-                self.create_tree(root_id);
+    /// Walks a template node alongside the arena node created for it.
+    /// Elements with dynamic children and component slots are pushed to `deferred`.
+    fn hydrate_template_node(
+        &mut self,
+        template: &TemplateNode,
+        id: NodeId,
+        path: &mut Vec<u8>,
+        deferred: &mut Vec<NodeId>,
+    ) {
+        let ffi_id = id.data().as_ffi();
+        match (template, self.arena.nodes.get(id).cloned()) {
+            (
+                TemplateNode::Element {
+                    attrs,
+                    children,
+                    dynamic_children,
+                    ..
+                },
+                Some(VirtualNode::Element(el)),
+            ) => {
+                let dynamic_attrs: Vec<_> = el
+                    .props
+                    .iter()
+                    .filter(|prop| !attrs.iter().any(|attr| attr.name == prop.name))
+                    .collect();
+                let needs_id = !dynamic_attrs.is_empty()
+                    || !el.listeners.is_empty()
+                    || el.node_ref.is_some()
+                    || *dynamic_children
+                    || children.iter().any(|c| matches!(c, TemplateNode::Dynamic));
+                // The root already got its id from `LoadTemplate`.
+                if needs_id && !path.is_empty() {
+                    self.mutation_buffer.push(Mutation::AssignId {
+                        path: path.clone(),
+                        id: ffi_id,
+                    });
+                    self.profiling.mutation_count += 1;
+                }
+                if let Some(node_ref) = &el.node_ref
+                    && !path.is_empty()
+                {
+                    self.refs.push(RefUpdate::Set(node_ref.clone(), ffi_id));
+                }
+
+                for prop in dynamic_attrs
+                    .into_iter()
+                    .filter(|p| p.value != AttributeValue::None)
+                {
+                    self.mutation_buffer.push(Mutation::SetAttribute {
+                        name: prop.name.to_string(),
+                        value: prop.value.clone(),
+                        id: ffi_id,
+                        ns: attribute_namespace(prop.name).map(str::to_string),
+                    });
+                    self.profiling.mutation_count += 1;
+                }
+                for listener in &el.listeners {
+                    self.mutation_buffer.push(Mutation::NewEventListener {
+                        name: listener.name.to_lowercase(),
+                        id: ffi_id,
+                    });
+                    self.profiling.mutation_count += 1;
+                }
+
+                if *dynamic_children {
+                    deferred.push(id);
+                    return;
+                }
+                for (idx, (child_template, &child_id)) in
+                    children.iter().zip(el.children.iter()).enumerate()
+                {
+                    self.set_parent(child_id, Some(id));
+                    path.push(idx as u8);
+                    self.hydrate_template_node(child_template, child_id, path, deferred);
+                    path.pop();
+                }
             }
-            VirtualNode::Suspense(susp) => {
-                // For now just render actual? Or fallback?
-                // Logic: check strict mode or something?
-                // Default to actual.
-                self.create_tree(susp.actual);
+            (TemplateNode::DynamicText, Some(VirtualNode::Text(txt))) => {
+                self.mutation_buffer.push(Mutation::HydrateText {
+                    path: path.clone(),
+                    value: txt.text.clone(),
+                    id: ffi_id,
+                });
+                self.profiling.mutation_count += 1;
+            }
+            (TemplateNode::Dynamic, Some(_)) => {
+                self.mutation_buffer.push(Mutation::AssignId {
+                    path: path.clone(),
+                    id: ffi_id,
+                });
+                self.profiling.mutation_count += 1;
+                deferred.push(id);
             }
+            // Static text is part of the clone.
             _ => {}
         }
     }
 
-    pub fn diff_attributes(&mut self, id: NodeId, old_el: &Element, new_el: &Element) {
+    /// Builds the tree under `id` like `create_tree`, but adopts the markup
+    /// `nexa-ssr` rendered for it into the root container instead of creating
+    /// it: elements get their ids through `AssignId`, text through
+    /// `HydrateText`, and listeners are attached. Paths start at the
+    /// container, so the markup must be exactly what the server rendered for
+    /// the same tree, hydration markers included.
+    ///
+    /// Portal content and Suspense boundaries, which the server doesn't
+    /// render in place, are created once everything else is adopted.
+    pub fn hydrate_tree(&mut self, id: NodeId) {
+        self.mutation_buffer.push(Mutation::LoadExisting { id: 0 });
+        self.profiling.mutation_count += 1;
+
+        let mut deferred = Vec::new();
+        self.hydrate_node(id, &[], &mut HydrationCursor::default(), &mut deferred);
+
+        for node_id in deferred {
+            let suspense = matches!(
+                self.arena.nodes.get(node_id),
+                Some(VirtualNode::Suspense(_))
+            );
+            self.create_tree(node_id);
+            // The server rendered the boundary as one element, which was
+            // given the boundary's id.
+            if suspense {
+                self.mutation_buffer.push(Mutation::ReplaceWith {
+                    id: node_id.data().as_ffi(),
+                    m: self.flatten_node(node_id),
+                });
+                self.profiling.mutation_count += 1;
+            }
+        }
+    }
+
+    /// Adopts the server-rendered node(s) for `id`, the next of which is at
+    /// `cursor` among the children of the node at `parent_path`.
+    fn hydrate_node(
+        &mut self,
+        id: NodeId,
+        parent_path: &[u8],
+        cursor: &mut HydrationCursor,
+        deferred: &mut Vec<NodeId>,
+    ) {
+        let Some(node) = self.arena.nodes.get(id).cloned() else {
+            return;
+        };
         let ffi_id = id.data().as_ffi();
 
-        for new_attr in &new_el.props {
-            let old_attr = old_el.props.iter().find(|a| a.name == new_attr.name);
-            if let Some(old) = old_attr {
-                if old.value != new_attr.value {
-                    self.mutation_buffer.push(Mutation::SetAttribute {
+        match node {
+            VirtualNode::Element(el) => {
+                let Some(path) = cursor.path(parent_path) else {
+                    return;
+                };
+                self.mutation_buffer.push(Mutation::AssignId {
+                    path: path.clone(),
+                    id: ffi_id,
+                });
+                self.profiling.mutation_count += 1;
+                if let Some(node_ref) = &el.node_ref {
+                    self.refs.push(RefUpdate::Set(node_ref.clone(), ffi_id));
+                }
+                for listener in &el.listeners {
+                    self.mutation_buffer.push(Mutation::NewEventListener {
+                        name: listener.name.to_lowercase(),
                         id: ffi_id,
-                        name: new_attr.name.to_string(),
-                        value: new_attr.value.clone(),
-                        ns: None,
                     });
                     self.profiling.mutation_count += 1;
                 }
-            } else {
+
+                let mut children = HydrationCursor::default();
+                for &child_id in &el.children {
+                    self.set_parent(child_id, Some(id));
+                    self.hydrate_node(child_id, &path, &mut children, deferred);
+                }
+                cursor.advance(false);
+            }
+            VirtualNode::Text(txt) => {
+                if cursor.after_text {
+                    cursor.advance(false);
+                }
+                let Some(path) = cursor.path(parent_path) else {
+                    return;
+                };
+                if txt.text.is_empty() {
+                    // Empty text has no markup, so the server left a comment.
+                    self.mutation_buffer.push(Mutation::CreateTextNode {
+                        text: String::new(),
+                        id: ffi_id,
+                    });
+                    self.mutation_buffer.push(Mutation::ReplacePlaceholder {
+                        path,
+                        m: vec![ffi_id],
+                    });
+                    self.profiling.mutation_count += 2;
+                    cursor.advance(false);
+                } else {
+                    self.mutation_buffer.push(Mutation::HydrateText {
+                        path,
+                        value: txt.text.clone(),
+                        id: ffi_id,
+                    });
+                    self.profiling.mutation_count += 1;
+                    cursor.advance(true);
+                }
+            }
+            VirtualNode::Fragment(frag) => {
+                for &child in &frag.children {
+                    self.set_parent(child, Some(id));
+                    self.hydrate_node(child, parent_path, cursor, deferred);
+                }
+            }
+            VirtualNode::Component(comp) => {
+                let Some((scope_id, root_id)) = self.render_new_scope(id, &comp) else {
+                    return;
+                };
+                let span = self.open_span(ProfilePhase::Diff, scope_id);
+                let prev_scope = self.current_scope.replace(scope_id);
+                self.hydrate_node(root_id, parent_path, cursor, deferred);
+                self.current_scope = prev_scope;
+                self.close_span(span);
+                self.mounted.push(scope_id);
+            }
+            VirtualNode::ErrorBoundary(boundary) => {
+                let checkpoint = self.checkpoint();
+                let (start, pending) = (*cursor, deferred.len());
+                self.set_parent(boundary.content, Some(id));
+                self.hydrate_node(boundary.content, parent_path, cursor, deferred);
+                let Some((_, error)) = self.errors.get(checkpoint.errors).cloned() else {
+                    return;
+                };
+                self.rollback(checkpoint, boundary.content);
+                deferred.truncate(pending);
+                *cursor = start;
+                // The server rendered the same tree, so it showed the fallback too.
+                let owner = self.current_scope;
+                self.build_fallback(id, error, owner, |differ, fallback| {
+                    differ.hydrate_node(fallback, parent_path, cursor, deferred)
+                });
+            }
+            VirtualNode::Suspense(_) => {
+                if let Some(path) = cursor.path(parent_path) {
+                    self.mutation_buffer
+                        .push(Mutation::AssignId { path, id: ffi_id });
+                    self.profiling.mutation_count += 1;
+                    deferred.push(id);
+                }
+                cursor.advance(false);
+            }
+            VirtualNode::Portal(portal) => {
+                // The server left a comment where the portal is.
+                self.set_parent(portal.content, Some(id));
+                deferred.push(id);
+                cursor.advance(false);
+            }
+            // Placeholders render nothing, but the server left a comment.
+            VirtualNode::Placeholder => cursor.advance(false),
+        }
+    }
+
+    /// Attributes set to `AttributeValue::None` count as absent.
+    pub fn diff_attributes(&mut self, id: NodeId, old_el: &Element, new_el: &Element) {
+        let ffi_id = id.data().as_ffi();
+        let present = |el: &Element, name: &str| {
+            el.props
+                .iter()
+                .find(|a| a.name == name && a.value != AttributeValue::None)
+                .map(|a| a.value.clone())
+        };
+
+        for new_attr in &new_el.props {
+            if new_attr.value == AttributeValue::None {
+                continue;
+            }
+            if present(old_el, new_attr.name).as_ref() != Some(&new_attr.value) {
                 self.mutation_buffer.push(Mutation::SetAttribute {
                     id: ffi_id,
                     name: new_attr.name.to_string(),
                     value: new_attr.value.clone(),
-                    ns: None,
+                    ns: attribute_namespace(new_attr.name).map(str::to_string),
                 });
                 self.profiling.mutation_count += 1;
             }
         }
 
         for old_attr in &old_el.props {
-            if !new_el.props.iter().any(|a| a.name == old_attr.name) {
+            if old_attr.value != AttributeValue::None && present(new_el, old_attr.name).is_none() {
                 self.mutation_buffer.push(Mutation::RemoveAttribute {
                     id: ffi_id, // Assuming Element ID
                     name: old_attr.name.to_string(),
@@ -326,144 +1351,146 @@ impl<'a> Differ<'a> {
         }
     }
 
+    /// Tells the renderer about event names the element starts or stops
+    /// listening to. Callbacks themselves live in the vdom and are swapped
+    /// without mutations.
+    pub fn diff_listeners(&mut self, id: NodeId, old_el: &Element, new_el: &Element) {
+        let ffi_id = id.data().as_ffi();
+
+        for (i, new_l) in new_el.listeners.iter().enumerate() {
+            let seen = new_el.listeners[..i].iter().any(|l| l.name == new_l.name);
+            if !seen && !old_el.listeners.iter().any(|l| l.name == new_l.name) {
+                self.mutation_buffer.push(Mutation::NewEventListener {
+                    name: new_l.name.to_lowercase(),
+                    id: ffi_id,
+                });
+                self.profiling.mutation_count += 1;
+            }
+        }
+
+        for (i, old_l) in old_el.listeners.iter().enumerate() {
+            let seen = old_el.listeners[..i].iter().any(|l| l.name == old_l.name);
+            if !seen && !new_el.listeners.iter().any(|l| l.name == old_l.name) {
+                self.mutation_buffer.push(Mutation::RemoveEventListener {
+                    name: old_l.name.to_lowercase(),
+                    id: ffi_id,
+                });
+                self.profiling.mutation_count += 1;
+            }
+        }
+    }
+
+    /// Diffs the children of `owner` and returns the committed child list.
     pub fn diff_children(
         &mut self,
-        parent: NodeId,
+        owner: NodeId,
         old_children: &[NodeId],
         new_children: &[NodeId],
-    ) {
+    ) -> SmallVec<[NodeId; 4]> {
         self.profiling.diff_count += 1;
 
+        for &new_id in new_children {
+            self.set_parent(new_id, Some(owner));
+        }
+        let container = self.dom_container(Some(owner));
+
         // Fast paths
         if old_children.is_empty() && new_children.is_empty() {
-            return;
+            return SmallVec::new();
         }
         if old_children.is_empty() {
             // All new
+            let mut ids = Vec::new();
             for &new_id in new_children {
                 self.create_tree(new_id);
+                ids.extend(self.flatten_node(new_id));
             }
-            let ids = new_children.iter().map(|&n| n.data().as_ffi()).collect();
-            self.mutation_buffer.push(Mutation::AppendChildren {
-                id: parent.data().as_ffi(),
-                m: ids,
-            });
-            self.profiling.mutation_count += 1;
-            return;
+            if !ids.is_empty() {
+                self.mutation_buffer.push(Mutation::AppendChildren {
+                    id: container,
+                    m: ids,
+                });
+                self.profiling.mutation_count += 1;
+            }
+            return new_children.iter().copied().collect();
         }
         if new_children.is_empty() {
             // Remove all
             for &old_id in old_children {
-                self.mutation_buffer.push(Mutation::Remove {
-                    id: old_id.data().as_ffi(),
-                });
-                self.profiling.mutation_count += 1;
+                self.remove_subtree(old_id);
             }
-            return;
+            return SmallVec::new();
         }
 
-        // Keyed diffing logic (simplified)
+        // Keyed children are matched by key, unkeyed children by their
+        // position among the unkeyed children of each list.
         let mut old_map = HashMap::new();
+        let mut old_unkeyed = Vec::new();
         for (idx, &id) in old_children.iter().enumerate() {
-            if let Some(VirtualNode::Element(el)) = self.arena.nodes.get(id) {
-                if let Some(key) = &el.key {
-                    old_map.insert(key.clone(), (id, idx));
+            match self.arena.nodes.get(id).and_then(VirtualNode::key) {
+                Some(key) => {
+                    old_map.insert(key.to_string(), (id, idx));
                 }
+                None => old_unkeyed.push((id, idx)),
             }
         }
+        let mut old_unkeyed = old_unkeyed.into_iter();
 
         let mut source = vec![-1_isize; new_children.len()];
+        let mut committed: SmallVec<[NodeId; 4]> = new_children.iter().copied().collect();
 
         for (idx, &id) in new_children.iter().enumerate() {
-            let mut matched = false;
-            // Check key
-            if let Some(VirtualNode::Element(el)) = self.arena.nodes.get(id) {
-                if let Some(key) = &el.key {
-                    if let Some(&(old_id, old_idx)) = old_map.get(key) {
-                        source[idx] = old_idx as isize;
-                        self.diff_nodes(old_id, id, Some(parent));
-                        matched = true;
-                    }
-                }
-            }
-            if !matched {
-                // Try unkeyed match by index if possible, or just treat as new?
-                // For now treat as new if not keyed match.
-                // If unkeyed, we might map by index 0->0, 1->1.
+            let matched = match self.arena.nodes.get(id).and_then(VirtualNode::key) {
+                Some(key) => old_map.remove(key),
+                None => old_unkeyed.next(),
+            };
+            if let Some((old_id, old_idx)) = matched {
+                source[idx] = old_idx as isize;
+                committed[idx] = self.diff_nodes(old_id, id);
             }
+            // Unmatched children are created below.
         }
 
-        // Handling unkeyed items (basic index based)
-        // Only if map is empty? Or mixed?
-        // Let's assume purely keyed for now, unkeyed falls back to creation.
-        // TODO: Improve unkeyed support.
-
         let lis = self.calculate_lis(&source);
         let mut lis_idx = lis.len() as isize - 1;
 
         for i in (0..new_children.len()).rev() {
-            let new_child_id = new_children[i];
-
-            // Should verify new_child_id is valid?
-            // let ffi_id = new_child_id.data().as_ffi(); // Don't use this directly
+            let child_id = committed[i];
 
             // Find next sibling (reference node)
-            let next_sibling_id = if i + 1 < new_children.len() {
-                self.first_dom_node(new_children[i + 1])
-            } else {
-                None
-            };
+            let next_sibling_id = committed[i + 1..]
+                .iter()
+                .find_map(|&sibling| self.first_dom_node(sibling));
 
-            if source[i] == -1 {
+            let needs_insert = if source[i] == -1 {
                 // New node
-                self.create_tree(new_child_id);
-
-                let flattened_ids = self.flatten_node(new_child_id);
+                self.create_tree(child_id);
+                true
+            } else if lis_idx < 0 || i != lis[lis_idx as usize] {
+                // Node needs to move.
+                // Its DOM nodes already exist, so we only reposition them.
+                true
+            } else {
+                lis_idx -= 1;
+                false
+            };
 
+            if needs_insert {
+                let flattened_ids = self.flatten_node(child_id);
                 if !flattened_ids.is_empty() {
                     if let Some(ref_id) = next_sibling_id {
                         self.mutation_buffer.push(Mutation::InsertBefore {
                             id: ref_id,
                             m: flattened_ids,
                         });
-                        self.profiling.mutation_count += 1;
                     } else {
+                        // Move to end (Append)
                         self.mutation_buffer.push(Mutation::AppendChildren {
-                            id: parent.data().as_ffi(),
+                            id: container,
                             m: flattened_ids,
                         });
-                        self.profiling.mutation_count += 1;
                     }
-                }
-            } else {
-                // Move node logic
-                if lis_idx < 0 || i != lis[lis_idx as usize] {
-                    // Node needs to move
-                    let flattened_ids = self.flatten_node(new_child_id);
-                    // Usually moving a node that already exists means we don't need to create it.
-                    // But we need to move its DOM nodes.
-                    // Issue: flatten_node returns IDs.
-                    // If component, it returns current roots.
-                    // If they are already in DOM, we just move them.
-
-                    if !flattened_ids.is_empty() {
-                        if let Some(ref_id) = next_sibling_id {
-                            self.mutation_buffer.push(Mutation::InsertBefore {
-                                id: ref_id,
-                                m: flattened_ids,
-                            });
-                            self.profiling.mutation_count += 1;
-                        } else {
-                            // Move to end (Append)
-                            self.mutation_buffer.push(Mutation::AppendChildren {
-                                id: parent.data().as_ffi(),
-                                m: flattened_ids,
-                            });
-                            self.profiling.mutation_count += 1;
-                        }
-                    }
-                } else {
-                    lis_idx -= 1;
+                    self.profiling.mutation_count += 1;
                 }
             }
         }
@@ -477,15 +1504,52 @@ impl<'a> Differ<'a> {
             .collect();
         for (i, &old_id) in old_children.iter().enumerate() {
             if !present_indices.contains(&i) {
-                self.mutation_buffer.push(Mutation::Remove {
-                    id: old_id.data().as_ffi(),
-                });
-                self.profiling.mutation_count += 1;
+                self.remove_subtree(old_id);
             }
         }
+
+        committed
+    }
+
+    fn store(&mut self, id: NodeId, node: VirtualNode) {
+        if let Some(slot) = self.arena.nodes.get_mut(id) {
+            *slot = node;
+        }
     }
 
-    fn first_dom_node(&self, id: NodeId) -> Option<u64> {
+    fn parent_of(&self, id: NodeId) -> Option<NodeId> {
+        self.arena.nodes.get(id)?.parent()
+    }
+
+    fn set_parent(&mut self, id: NodeId, parent: Option<NodeId>) {
+        match self.arena.nodes.get_mut(id) {
+            Some(VirtualNode::Element(el)) => el.parent = parent,
+            Some(VirtualNode::Text(txt)) => txt.parent = parent,
+            Some(VirtualNode::Fragment(frag)) => frag.parent = parent,
+            Some(VirtualNode::Component(comp)) => comp.parent = parent,
+            Some(VirtualNode::Suspense(susp)) => susp.parent = parent,
+            Some(VirtualNode::ErrorBoundary(eb)) => eb.parent = parent,
+            Some(VirtualNode::Portal(portal)) => portal.parent = parent,
+            _ => {}
+        }
+    }
+
+    /// The DOM node that children of `id` live in: `id` itself for elements
+    /// and portals, otherwise the nearest such ancestor, or the root container (0).
+    pub fn dom_container(&self, id: Option<NodeId>) -> u64 {
+        let mut current = id;
+        while let Some(node_id) = current {
+            if let Some(VirtualNode::Element(_) | VirtualNode::Portal(_)) =
+                self.arena.nodes.get(node_id)
+            {
+                return node_id.data().as_ffi();
+            }
+            current = self.parent_of(node_id);
+        }
+        0
+    }
+
+    pub fn first_dom_node(&self, id: NodeId) -> Option<u64> {
         if let Some(node) = self.arena.nodes.get(id) {
             match node {
                 VirtualNode::Element(_) => Some(id.data().as_ffi()),
@@ -508,7 +1572,12 @@ impl<'a> Differ<'a> {
                     }
                     None
                 }
-                VirtualNode::Suspense(susp) => self.first_dom_node(susp.actual), // Or fallback?
+                VirtualNode::Suspense(susp) if susp.suspended => self.first_dom_node(susp.fallback),
+                VirtualNode::Suspense(susp) => self.first_dom_node(susp.actual),
+                VirtualNode::ErrorBoundary(eb) => match &eb.caught {
+                    Some(caught) => self.first_dom_node(caught.fallback),
+                    None => self.first_dom_node(eb.content),
+                },
                 _ => None,
             }
         } else {
@@ -516,7 +1585,7 @@ impl<'a> Differ<'a> {
         }
     }
 
-    fn flatten_node(&self, id: NodeId) -> Vec<u64> {
+    pub fn flatten_node(&self, id: NodeId) -> Vec<u64> {
         if let Some(node) = self.arena.nodes.get(id) {
             match node {
                 VirtualNode::Element(_) | VirtualNode::Text(_) => vec![id.data().as_ffi()],
@@ -537,7 +1606,12 @@ impl<'a> Differ<'a> {
                     }
                     vec![]
                 }
+                VirtualNode::Suspense(susp) if susp.suspended => self.flatten_node(susp.fallback),
                 VirtualNode::Suspense(susp) => self.flatten_node(susp.actual),
+                VirtualNode::ErrorBoundary(eb) => match &eb.caught {
+                    Some(caught) => self.flatten_node(caught.fallback),
+                    None => self.flatten_node(eb.content),
+                },
                 _ => vec![],
             }
         } else {
@@ -584,3 +1658,11 @@ impl<'a> Differ<'a> {
         res
     }
 }
+
+/// The namespace of the template's root element.
+fn template_namespace(template: &Template) -> Option<&str> {
+    match template.roots.first() {
+        Some(TemplateNode::Element { namespace, .. }) => namespace.as_deref(),
+        _ => None,
+    }
+}
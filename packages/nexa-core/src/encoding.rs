@@ -0,0 +1,602 @@
+//! Compact binary wire format for mutation streams.
+//!
+//! A batch is a varint mutation count followed by the mutations, each an
+//! opcode byte and its fields. Indices and lengths are LEB128 varints. A
+//! node id is two: the slot index, then the version, which sits in the high
+//! 32 bits and would make a single varint at least five bytes long.
+//! Names (tags, attribute and event names, namespaces, template names) are
+//! interned: a reference is `0` followed by the string the first time it is
+//! sent, and `index + 1` afterwards. Text is sent as length-prefixed UTF-8.
+//! Attribute values are a kind byte followed by the value: UTF-8 text, a
+//! bool byte, a zigzag varint or the little-endian bits of an `f64`.
+//!
+//! The intern table lives for the whole stream, so an encoder and its decoder
+//! must see the same batches in the same order. A batch that fails to decode
+//! leaves the decoder's table as it was, so it can still decode the batch
+//! once it is sent again intact.
+
+use crate::mutations::Mutation;
+use crate::template::{Template, TemplateAttribute, TemplateNode};
+use crate::vdom::AttributeValue;
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+mod op {
+    pub const APPEND_CHILDREN: u8 = 0;
+    pub const ASSIGN_ID: u8 = 1;
+    pub const CREATE_ELEMENT: u8 = 2;
+    pub const CREATE_PLACEHOLDER: u8 = 3;
+    pub const CREATE_TEXT_NODE: u8 = 4;
+    pub const HYDRATE_TEXT: u8 = 5;
+    pub const LOAD_TEMPLATE: u8 = 6;
+    pub const REGISTER_TEMPLATE: u8 = 7;
+    pub const REPLACE_WITH: u8 = 8;
+    pub const REPLACE_PLACEHOLDER: u8 = 9;
+    pub const INSERT_AFTER: u8 = 10;
+    pub const INSERT_BEFORE: u8 = 11;
+    pub const SET_ATTRIBUTE: u8 = 12;
+    pub const REMOVE_ATTRIBUTE: u8 = 13;
+    pub const SET_TEXT: u8 = 14;
+    pub const NEW_EVENT_LISTENER: u8 = 15;
+    pub const REMOVE_EVENT_LISTENER: u8 = 16;
+    pub const REMOVE: u8 = 17;
+    pub const PUSH_ROOT: u8 = 18;
+    pub const LOAD_CONTAINER: u8 = 19;
+    pub const CREATE_ELEMENT_NS: u8 = 20;
+    pub const LOAD_EXISTING: u8 = 21;
+}
+
+mod value_kind {
+    pub const TEXT: u8 = 0;
+    pub const BOOL: u8 = 1;
+    pub const INT: u8 = 2;
+    pub const FLOAT: u8 = 3;
+    pub const NONE: u8 = 4;
+    pub const LISTENER: u8 = 5;
+}
+
+mod template_op {
+    pub const ELEMENT: u8 = 0;
+    pub const TEXT: u8 = 1;
+    pub const DYNAMIC_TEXT: u8 = 2;
+    pub const DYNAMIC: u8 = 3;
+}
+
+#[derive(Debug, thiserror::Error, PartialEq, Eq)]
+pub enum DecodeError {
+    #[error("unexpected end of input")]
+    UnexpectedEof,
+    #[error("unknown opcode {0}")]
+    UnknownOpcode(u8),
+    #[error("varint overflows u64")]
+    VarintOverflow,
+    #[error("node id index or version overflows u32")]
+    IdOverflow,
+    #[error("invalid UTF-8 in string")]
+    InvalidUtf8,
+    #[error("reference to unknown interned string {0}")]
+    UnknownString(u64),
+    #[error("unknown attribute value kind {0}")]
+    UnknownValueKind(u8),
+}
+
+#[derive(Default)]
+pub struct MutationEncoder {
+    strings: HashMap<String, u64>,
+}
+
+impl MutationEncoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn encode_batch(&mut self, mutations: &[Mutation]) -> Vec<u8> {
+        let mut out = Vec::new();
+        write_varint(&mut out, mutations.len() as u64);
+        for mutation in mutations {
+            self.encode(&mut out, mutation);
+        }
+        out
+    }
+
+    fn encode(&mut self, out: &mut Vec<u8>, mutation: &Mutation) {
+        match mutation {
+            Mutation::AppendChildren { id, m } => {
+                out.push(op::APPEND_CHILDREN);
+                write_id(out, *id);
+                write_ids(out, m);
+            }
+            Mutation::AssignId { path, id } => {
+                out.push(op::ASSIGN_ID);
+                write_bytes(out, path);
+                write_id(out, *id);
+            }
+            Mutation::CreateElement { tag, id } => {
+                out.push(op::CREATE_ELEMENT);
+                self.write_name(out, tag);
+                write_id(out, *id);
+            }
+            Mutation::CreateElementNs { tag, ns, id } => {
+                out.push(op::CREATE_ELEMENT_NS);
+                self.write_name(out, tag);
+                self.write_name(out, ns);
+                write_id(out, *id);
+            }
+            Mutation::CreatePlaceholder { id } => {
+                out.push(op::CREATE_PLACEHOLDER);
+                write_id(out, *id);
+            }
+            Mutation::CreateTextNode { text, id } => {
+                out.push(op::CREATE_TEXT_NODE);
+                write_bytes(out, text.as_bytes());
+                write_id(out, *id);
+            }
+            Mutation::HydrateText { path, value, id } => {
+                out.push(op::HYDRATE_TEXT);
+                write_bytes(out, path);
+                write_bytes(out, value.as_bytes());
+                write_id(out, *id);
+            }
+            Mutation::LoadTemplate { name, index, id } => {
+                out.push(op::LOAD_TEMPLATE);
+                self.write_name(out, name);
+                write_varint(out, *index as u64);
+                write_id(out, *id);
+            }
+            Mutation::RegisterTemplate { template } => {
+                out.push(op::REGISTER_TEMPLATE);
+                self.write_name(out, &template.name);
+                write_varint(out, template.roots.len() as u64);
+                for root in template.roots.iter() {
+                    self.write_template_node(out, root);
+                }
+            }
+            Mutation::ReplaceWith { id, m } => {
+                out.push(op::REPLACE_WITH);
+                write_id(out, *id);
+                write_ids(out, m);
+            }
+            Mutation::ReplacePlaceholder { path, m } => {
+                out.push(op::REPLACE_PLACEHOLDER);
+                write_bytes(out, path);
+                write_ids(out, m);
+            }
+            Mutation::InsertAfter { id, m } => {
+                out.push(op::INSERT_AFTER);
+                write_id(out, *id);
+                write_ids(out, m);
+            }
+            Mutation::InsertBefore { id, m } => {
+                out.push(op::INSERT_BEFORE);
+                write_id(out, *id);
+                write_ids(out, m);
+            }
+            Mutation::SetAttribute {
+                name,
+                value,
+                id,
+                ns,
+            } => {
+                out.push(op::SET_ATTRIBUTE);
+                self.write_name(out, name);
+                write_value(out, value);
+                write_id(out, *id);
+                self.write_optional_name(out, ns.as_deref());
+            }
+            Mutation::RemoveAttribute { name, id } => {
+                out.push(op::REMOVE_ATTRIBUTE);
+                self.write_name(out, name);
+                write_id(out, *id);
+            }
+            Mutation::SetText { value, id } => {
+                out.push(op::SET_TEXT);
+                write_bytes(out, value.as_bytes());
+                write_id(out, *id);
+            }
+            Mutation::NewEventListener { name, id } => {
+                out.push(op::NEW_EVENT_LISTENER);
+                self.write_name(out, name);
+                write_id(out, *id);
+            }
+            Mutation::RemoveEventListener { name, id } => {
+                out.push(op::REMOVE_EVENT_LISTENER);
+                self.write_name(out, name);
+                write_id(out, *id);
+            }
+            Mutation::Remove { id } => {
+                out.push(op::REMOVE);
+                write_id(out, *id);
+            }
+            Mutation::PushRoot { id } => {
+                out.push(op::PUSH_ROOT);
+                write_id(out, *id);
+            }
+            Mutation::LoadContainer { target, id } => {
+                out.push(op::LOAD_CONTAINER);
+                self.write_name(out, target);
+                write_id(out, *id);
+            }
+            Mutation::LoadExisting { id } => {
+                out.push(op::LOAD_EXISTING);
+                write_id(out, *id);
+            }
+        }
+    }
+
+    fn write_template_node(&mut self, out: &mut Vec<u8>, node: &TemplateNode) {
+        match node {
+            TemplateNode::Element {
+                tag,
+                namespace,
+                attrs,
+                children,
+                dynamic_children,
+            } => {
+                out.push(template_op::ELEMENT);
+                self.write_name(out, tag);
+                self.write_optional_name(out, namespace.as_deref());
+                write_varint(out, attrs.len() as u64);
+                for attr in attrs.iter() {
+                    self.write_name(out, &attr.name);
+                    write_bytes(out, attr.value.as_bytes());
+                }
+                write_varint(out, children.len() as u64);
+                for child in children.iter() {
+                    self.write_template_node(out, child);
+                }
+                out.push(*dynamic_children as u8);
+            }
+            TemplateNode::Text { text } => {
+                out.push(template_op::TEXT);
+                write_bytes(out, text.as_bytes());
+            }
+            TemplateNode::DynamicText => out.push(template_op::DYNAMIC_TEXT),
+            TemplateNode::Dynamic => out.push(template_op::DYNAMIC),
+        }
+    }
+
+    fn write_optional_name(&mut self, out: &mut Vec<u8>, name: Option<&str>) {
+        match name {
+            Some(name) => {
+                out.push(1);
+                self.write_name(out, name);
+            }
+            None => out.push(0),
+        }
+    }
+
+    fn write_name(&mut self, out: &mut Vec<u8>, name: &str) {
+        if let Some(&idx) = self.strings.get(name) {
+            write_varint(out, idx + 1);
+        } else {
+            write_varint(out, 0);
+            write_bytes(out, name.as_bytes());
+            self.strings
+                .insert(name.to_string(), self.strings.len() as u64);
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct MutationDecoder {
+    strings: Vec<String>,
+    /// Names first sent in the batch being decoded, added to `strings` once
+    /// the whole batch decodes.
+    pending: Vec<String>,
+}
+
+impl MutationDecoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn decode_batch(&mut self, bytes: &[u8]) -> Result<Vec<Mutation>, DecodeError> {
+        let decoded = self.decode_mutations(bytes);
+        let pending = std::mem::take(&mut self.pending);
+        if decoded.is_ok() {
+            self.strings.extend(pending);
+        }
+        decoded
+    }
+
+    fn decode_mutations(&mut self, bytes: &[u8]) -> Result<Vec<Mutation>, DecodeError> {
+        let mut reader = Reader { bytes, pos: 0 };
+        let count = reader.varint()?;
+        // Every mutation takes at least two bytes, so don't trust larger counts.
+        let mut mutations = Vec::with_capacity((count as usize).min(bytes.len() / 2));
+        for _ in 0..count {
+            mutations.push(self.decode(&mut reader)?);
+        }
+        Ok(mutations)
+    }
+
+    fn decode(&mut self, r: &mut Reader<'_>) -> Result<Mutation, DecodeError> {
+        let opcode = r.byte()?;
+        Ok(match opcode {
+            op::APPEND_CHILDREN => Mutation::AppendChildren {
+                id: r.id()?,
+                m: r.ids()?,
+            },
+            op::ASSIGN_ID => Mutation::AssignId {
+                path: r.bytes()?.to_vec(),
+                id: r.id()?,
+            },
+            op::CREATE_ELEMENT => Mutation::CreateElement {
+                tag: self.read_name(r)?,
+                id: r.id()?,
+            },
+            op::CREATE_ELEMENT_NS => Mutation::CreateElementNs {
+                tag: self.read_name(r)?,
+                ns: self.read_name(r)?,
+                id: r.id()?,
+            },
+            op::CREATE_PLACEHOLDER => Mutation::CreatePlaceholder { id: r.id()? },
+            op::CREATE_TEXT_NODE => Mutation::CreateTextNode {
+                text: r.string()?,
+                id: r.id()?,
+            },
+            op::HYDRATE_TEXT => Mutation::HydrateText {
+                path: r.bytes()?.to_vec(),
+                value: r.string()?,
+                id: r.id()?,
+            },
+            op::LOAD_TEMPLATE => Mutation::LoadTemplate {
+                name: self.read_name(r)?,
+                index: r.varint()? as usize,
+                id: r.id()?,
+            },
+            op::REGISTER_TEMPLATE => {
+                let name = self.read_name(r)?;
+                let count = r.varint()?;
+                let mut roots = Vec::new();
+                for _ in 0..count {
+                    roots.push(self.read_template_node(r)?);
+                }
+                Mutation::RegisterTemplate {
+                    template: Template {
+                        name: Cow::Owned(name),
+                        roots: Cow::Owned(roots),
+                    },
+                }
+            }
+            op::REPLACE_WITH => Mutation::ReplaceWith {
+                id: r.id()?,
+                m: r.ids()?,
+            },
+            op::REPLACE_PLACEHOLDER => Mutation::ReplacePlaceholder {
+                path: r.bytes()?.to_vec(),
+                m: r.ids()?,
+            },
+            op::INSERT_AFTER => Mutation::InsertAfter {
+                id: r.id()?,
+                m: r.ids()?,
+            },
+            op::INSERT_BEFORE => Mutation::InsertBefore {
+                id: r.id()?,
+                m: r.ids()?,
+            },
+            op::SET_ATTRIBUTE => Mutation::SetAttribute {
+                name: self.read_name(r)?,
+                value: r.value()?,
+                id: r.id()?,
+                ns: self.read_optional_name(r)?,
+            },
+            op::REMOVE_ATTRIBUTE => Mutation::RemoveAttribute {
+                name: self.read_name(r)?,
+                id: r.id()?,
+            },
+            op::SET_TEXT => Mutation::SetText {
+                value: r.string()?,
+                id: r.id()?,
+            },
+            op::NEW_EVENT_LISTENER => Mutation::NewEventListener {
+                name: self.read_name(r)?,
+                id: r.id()?,
+            },
+            op::REMOVE_EVENT_LISTENER => Mutation::RemoveEventListener {
+                name: self.read_name(r)?,
+                id: r.id()?,
+            },
+            op::REMOVE => Mutation::Remove { id: r.id()? },
+            op::PUSH_ROOT => Mutation::PushRoot { id: r.id()? },
+            op::LOAD_CONTAINER => Mutation::LoadContainer {
+                target: self.read_name(r)?,
+                id: r.id()?,
+            },
+            op::LOAD_EXISTING => Mutation::LoadExisting { id: r.id()? },
+            other => return Err(DecodeError::UnknownOpcode(other)),
+        })
+    }
+
+    fn read_template_node(&mut self, r: &mut Reader<'_>) -> Result<TemplateNode, DecodeError> {
+        Ok(match r.byte()? {
+            template_op::ELEMENT => {
+                let tag = self.read_name(r)?;
+                let namespace = self.read_optional_name(r)?;
+                let mut attrs = Vec::new();
+                for _ in 0..r.varint()? {
+                    attrs.push(TemplateAttribute {
+                        name: Cow::Owned(self.read_name(r)?),
+                        value: Cow::Owned(r.string()?),
+                    });
+                }
+                let mut children = Vec::new();
+                for _ in 0..r.varint()? {
+                    children.push(self.read_template_node(r)?);
+                }
+                TemplateNode::Element {
+                    tag: Cow::Owned(tag),
+                    namespace: namespace.map(Cow::Owned),
+                    attrs: Cow::Owned(attrs),
+                    children: Cow::Owned(children),
+                    dynamic_children: r.byte()? != 0,
+                }
+            }
+            template_op::TEXT => TemplateNode::Text {
+                text: Cow::Owned(r.string()?),
+            },
+            template_op::DYNAMIC_TEXT => TemplateNode::DynamicText,
+            template_op::DYNAMIC => TemplateNode::Dynamic,
+            other => return Err(DecodeError::UnknownOpcode(other)),
+        })
+    }
+
+    fn read_optional_name(&mut self, r: &mut Reader<'_>) -> Result<Option<String>, DecodeError> {
+        Ok(match r.byte()? {
+            0 => None,
+            _ => Some(self.read_name(r)?),
+        })
+    }
+
+    fn read_name(&mut self, r: &mut Reader<'_>) -> Result<String, DecodeError> {
+        match r.varint()? {
+            0 => {
+                let name = r.string()?;
+                self.pending.push(name.clone());
+                Ok(name)
+            }
+            idx => {
+                let index = (idx - 1) as usize;
+                match index.checked_sub(self.strings.len()) {
+                    None => self.strings.get(index),
+                    Some(pending) => self.pending.get(pending),
+                }
+                .cloned()
+                .ok_or(DecodeError::UnknownString(idx - 1))
+            }
+        }
+    }
+}
+
+fn write_varint(out: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        out.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+fn write_id(out: &mut Vec<u8>, id: u64) {
+    write_varint(out, id & 0xffff_ffff);
+    write_varint(out, id >> 32);
+}
+
+fn write_bytes(out: &mut Vec<u8>, bytes: &[u8]) {
+    write_varint(out, bytes.len() as u64);
+    out.extend_from_slice(bytes);
+}
+
+fn write_value(out: &mut Vec<u8>, value: &AttributeValue) {
+    match value {
+        AttributeValue::Text(text) => {
+            out.push(value_kind::TEXT);
+            write_bytes(out, text.as_bytes());
+        }
+        AttributeValue::Bool(b) => {
+            out.push(value_kind::BOOL);
+            out.push(*b as u8);
+        }
+        AttributeValue::Int(i) => {
+            out.push(value_kind::INT);
+            write_varint(out, ((i << 1) ^ (i >> 63)) as u64);
+        }
+        AttributeValue::Float(f) => {
+            out.push(value_kind::FLOAT);
+            out.extend_from_slice(&f.to_bits().to_le_bytes());
+        }
+        AttributeValue::None => out.push(value_kind::NONE),
+        AttributeValue::Listener => out.push(value_kind::LISTENER),
+    }
+}
+
+fn write_ids(out: &mut Vec<u8>, ids: &[u64]) {
+    write_varint(out, ids.len() as u64);
+    for &id in ids {
+        write_id(out, id);
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn byte(&mut self) -> Result<u8, DecodeError> {
+        let byte = *self.bytes.get(self.pos).ok_or(DecodeError::UnexpectedEof)?;
+        self.pos += 1;
+        Ok(byte)
+    }
+
+    fn varint(&mut self) -> Result<u64, DecodeError> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = self.byte()?;
+            let bits = (byte & 0x7f) as u64;
+            if shift == 63 && bits > 1 {
+                return Err(DecodeError::VarintOverflow);
+            }
+            value |= bits << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(DecodeError::VarintOverflow)
+    }
+
+    fn id(&mut self) -> Result<u64, DecodeError> {
+        let index = self.varint()?;
+        let version = self.varint()?;
+        if index > u32::MAX as u64 || version > u32::MAX as u64 {
+            return Err(DecodeError::IdOverflow);
+        }
+        Ok(version << 32 | index)
+    }
+
+    fn bytes(&mut self) -> Result<&'a [u8], DecodeError> {
+        let len = self.varint()? as usize;
+        let end = self
+            .pos
+            .checked_add(len)
+            .filter(|&end| end <= self.bytes.len())
+            .ok_or(DecodeError::UnexpectedEof)?;
+        let bytes = &self.bytes[self.pos..end];
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    fn string(&mut self) -> Result<String, DecodeError> {
+        let bytes = self.bytes()?;
+        String::from_utf8(bytes.to_vec()).map_err(|_| DecodeError::InvalidUtf8)
+    }
+
+    fn value(&mut self) -> Result<AttributeValue, DecodeError> {
+        Ok(match self.byte()? {
+            value_kind::TEXT => AttributeValue::Text(self.string()?),
+            value_kind::BOOL => AttributeValue::Bool(self.byte()? != 0),
+            value_kind::INT => {
+                let zigzag = self.varint()?;
+                AttributeValue::Int((zigzag >> 1) as i64 ^ -((zigzag & 1) as i64))
+            }
+            value_kind::FLOAT => {
+                let mut bits = [0; 8];
+                for byte in &mut bits {
+                    *byte = self.byte()?;
+                }
+                AttributeValue::Float(f64::from_bits(u64::from_le_bytes(bits)))
+            }
+            value_kind::NONE => AttributeValue::None,
+            value_kind::LISTENER => AttributeValue::Listener,
+            other => return Err(DecodeError::UnknownValueKind(other)),
+        })
+    }
+
+    fn ids(&mut self) -> Result<Vec<u64>, DecodeError> {
+        let count = self.varint()?;
+        let mut ids = Vec::new();
+        for _ in 0..count {
+            ids.push(self.id()?);
+        }
+        Ok(ids)
+    }
+}
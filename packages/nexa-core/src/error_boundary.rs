@@ -0,0 +1,105 @@
+//! Failures caught by `ErrorBoundary` nodes.
+//!
+//! A panic in a render function, a mount or update hook, or an event
+//! listener is caught and routed to the nearest `ErrorBoundary` above the
+//! component or element it came from. The boundary swaps its content for a
+//! fallback built from the error. Errors with no boundary above them still
+//! panic, once the commit they happened in is done.
+//!
+//! Catching relies on unwinding: on targets built with `panic = "abort"`,
+//! such as the default `wasm32-unknown-unknown` profile, a panic still aborts.
+
+use crate::runtime::{DirtyScopes, ScopeId};
+use crate::vdom::NodeId;
+use std::any::Any;
+use std::cell::Cell;
+use std::fmt;
+use std::panic::{AssertUnwindSafe, catch_unwind};
+use std::rc::Rc;
+
+/// An error caught by an error boundary.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CaughtError {
+    /// The panic message.
+    pub message: String,
+}
+
+impl CaughtError {
+    fn from_panic(payload: Box<dyn Any + Send>) -> Self {
+        let message = if let Some(s) = payload.downcast_ref::<&str>() {
+            s.to_string()
+        } else if let Some(s) = payload.downcast_ref::<String>() {
+            s.clone()
+        } else {
+            "component panicked".to_string()
+        };
+        Self { message }
+    }
+}
+
+impl fmt::Display for CaughtError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+/// Runs `f`, turning a panic into a `CaughtError`.
+pub(crate) fn catch<R>(f: impl FnOnce() -> R) -> Result<R, CaughtError> {
+    catch_unwind(AssertUnwindSafe(f)).map_err(CaughtError::from_panic)
+}
+
+/// Retries a failed boundary's content.
+///
+/// `reset` marks the component that rendered the boundary dirty; on its next
+/// render the boundary creates its content again, and shows a fresh fallback
+/// if that fails too.
+#[derive(Clone)]
+pub struct ResetHandle {
+    requested: Rc<Cell<bool>>,
+    dirty_scopes: DirtyScopes,
+    owner: Option<ScopeId>,
+}
+
+impl ResetHandle {
+    pub(crate) fn new(dirty_scopes: DirtyScopes, owner: Option<ScopeId>) -> Self {
+        Self {
+            requested: Rc::default(),
+            dirty_scopes,
+            owner,
+        }
+    }
+
+    pub fn reset(&self) {
+        self.requested.set(true);
+        if let Some(owner) = self.owner {
+            let mut dirty = self.dirty_scopes.borrow_mut();
+            if !dirty.contains(&owner) {
+                dirty.push(owner);
+            }
+        }
+    }
+
+    pub(crate) fn requested(&self) -> bool {
+        self.requested.get()
+    }
+}
+
+/// Handles are compared by identity, so they can be passed as props.
+impl PartialEq for ResetHandle {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.requested, &other.requested)
+    }
+}
+
+impl fmt::Debug for ResetHandle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ResetHandle")
+            .field("requested", &self.requested.get())
+            .field("owner", &self.owner)
+            .finish()
+    }
+}
+
+/// Builds a boundary's fallback. It runs as a component of its own, so it
+/// can read signals like any other.
+pub type ErrorFallback = Rc<dyn Fn(CaughtError, ResetHandle) -> NodeId>;
@@ -1,6 +1,192 @@
+use serde::{Deserialize, Serialize};
+use std::cell::Cell;
+use std::rc::Rc;
+
+/// A DOM-style event, dispatched by `Runtime::handle_event`.
+///
+/// Events run capture listeners from the root down to the target, then
+/// regular listeners from the target back up the `parent` chain. Clones share
+/// their propagation state, so a renderer can keep a clone and check
+/// `default_prevented` once dispatch returns.
 #[derive(Debug, Clone)]
-pub enum Event {
-    Click,
-    Input(String),
+pub struct Event {
+    data: Rc<EventData>,
+    state: Rc<PropagationState>,
+    target: u64,
+    current_target: u64,
+    phase: EventPhase,
+}
+
+#[derive(Debug, Default)]
+struct PropagationState {
+    propagation_stopped: Cell<bool>,
+    default_prevented: Cell<bool>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventPhase {
+    Capturing,
+    AtTarget,
+    Bubbling,
+}
+
+impl Event {
+    pub fn new(data: EventData) -> Self {
+        Self {
+            data: Rc::new(data),
+            state: Rc::default(),
+            target: 0,
+            current_target: 0,
+            phase: EventPhase::AtTarget,
+        }
+    }
+
+    pub fn data(&self) -> &EventData {
+        &self.data
+    }
+
+    /// The node the event was dispatched to.
+    pub fn target(&self) -> u64 {
+        self.target
+    }
+
+    /// The node whose listener is running.
+    pub fn current_target(&self) -> u64 {
+        self.current_target
+    }
+
+    pub fn phase(&self) -> EventPhase {
+        self.phase
+    }
+
+    /// Stops the event from reaching further nodes. The remaining listeners
+    /// on the current node still run.
+    pub fn stop_propagation(&self) {
+        self.state.propagation_stopped.set(true);
+    }
+
+    pub fn propagation_stopped(&self) -> bool {
+        self.state.propagation_stopped.get()
+    }
+
+    /// Asks the renderer to skip the platform's default action.
+    pub fn prevent_default(&self) {
+        self.state.default_prevented.set(true);
+    }
+
+    pub fn default_prevented(&self) -> bool {
+        self.state.default_prevented.get()
+    }
+
+    pub(crate) fn at(&self, target: u64, current_target: u64, phase: EventPhase) -> Self {
+        Self {
+            target,
+            current_target,
+            phase,
+            ..self.clone()
+        }
+    }
+}
+
+/// Whether an event of this name bubbles. Capture listeners see every event.
+pub fn bubbles(name: &str) -> bool {
+    !matches!(
+        name,
+        "focus"
+            | "blur"
+            | "mouseenter"
+            | "mouseleave"
+            | "pointerenter"
+            | "pointerleave"
+            | "load"
+            | "error"
+            | "scroll"
+    )
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum EventData {
+    Mouse(MouseData),
+    Keyboard(KeyboardData),
+    Focus(FocusData),
+    Form(FormData),
+    Pointer(PointerData),
+    Wheel(WheelData),
+    Drag(DragData),
     Unknown,
 }
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Modifiers {
+    pub alt: bool,
+    pub ctrl: bool,
+    pub meta: bool,
+    pub shift: bool,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct MouseData {
+    pub client_x: f64,
+    pub client_y: f64,
+    pub screen_x: f64,
+    pub screen_y: f64,
+    /// The button that changed state: 0 main, 1 auxiliary, 2 secondary.
+    pub button: i16,
+    /// Bitmask of the buttons held down.
+    pub buttons: u16,
+    pub modifiers: Modifiers,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct KeyboardData {
+    /// The key value, e.g. `"a"`, `"Enter"` or `"ArrowUp"`.
+    pub key: String,
+    /// The physical key, e.g. `"KeyA"`.
+    pub code: String,
+    pub modifiers: Modifiers,
+    pub repeat: bool,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct FocusData {
+    /// The node losing focus for `focus`, or gaining it for `blur`, if known.
+    pub related_target: Option<u64>,
+}
+
+/// Payload of `input`, `change` and `submit` events.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct FormData {
+    /// The value of the input, or empty for a submitted form.
+    pub value: String,
+    /// Named fields of the submitted form, in document order.
+    pub values: Vec<(String, String)>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PointerData {
+    pub mouse: MouseData,
+    pub pointer_id: i32,
+    /// `"mouse"`, `"pen"` or `"touch"`.
+    pub pointer_type: String,
+    pub pressure: f32,
+    pub width: f64,
+    pub height: f64,
+    pub is_primary: bool,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct WheelData {
+    pub mouse: MouseData,
+    pub delta_x: f64,
+    pub delta_y: f64,
+    pub delta_z: f64,
+    /// 0 pixels, 1 lines, 2 pages.
+    pub delta_mode: u32,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DragData {
+    pub mouse: MouseData,
+    /// Names of the dragged files, if any.
+    pub files: Vec<String>,
+}
@@ -1,14 +1,37 @@
 pub mod diff;
+pub mod encoding;
+pub mod error_boundary;
 pub mod events;
+pub mod lifecycle;
 pub mod mutations;
+pub mod node_ref;
+pub mod optimize;
+pub mod profiler;
+pub mod renderer;
 pub mod runtime;
+pub mod session;
+pub mod snapshot;
+pub mod suspense;
+pub mod template;
+pub mod testing;
 pub mod vdom;
 
-pub use events::Event;
+pub use encoding::{DecodeError, MutationDecoder, MutationEncoder};
+pub use error_boundary::{CaughtError, ErrorFallback, ResetHandle};
+pub use events::{
+    DragData, Event, EventData, EventPhase, FocusData, FormData, KeyboardData, Modifiers,
+    MouseData, PointerData, WheelData,
+};
+pub use lifecycle::{on_mount, on_unmount, on_update};
 pub use mutations::Mutation;
 pub use nexa_signals::Scheduler;
+pub use node_ref::NodeRef;
+pub use renderer::MutationSink;
 pub use runtime::{Runtime, ScopeId};
+pub use suspense::Resource;
+pub use template::{Template, TemplateAttribute, TemplateNode};
 pub use vdom::{
-    Attribute, Component, Element, EventListener, Fragment, NodeId, NodeMetadata, Text, VDomArena,
-    VirtualNode, get_active_arena, set_active_arena,
+    AnyProps, Attribute, AttributeValue, Caught, Component, Element, ErrorBoundary, EventListener,
+    Fragment, MATHML_NAMESPACE, NodeId, NodeMetadata, Portal, SVG_NAMESPACE, Suspense, Text,
+    VDomArena, VirtualNode, XLINK_NAMESPACE, get_active_arena, set_active_arena,
 };
@@ -0,0 +1,61 @@
+use std::cell::RefCell;
+
+/// Hooks registered by a component during its most recent render.
+#[derive(Default)]
+pub struct ComponentLifecycle {
+    pub on_mount: Vec<Box<dyn FnOnce()>>,
+    pub on_update: Vec<Box<dyn FnMut()>>,
+    pub on_unmount: Vec<Box<dyn FnOnce()>>,
+}
+
+impl ComponentLifecycle {
+    /// Replaces the hooks with the ones registered by a new render, so they
+    /// capture the latest state. Mount hooks are only kept from the first render.
+    pub fn replace_with(&mut self, next: ComponentLifecycle, first_render: bool) {
+        if first_render {
+            self.on_mount = next.on_mount;
+        }
+        self.on_update = next.on_update;
+        self.on_unmount = next.on_unmount;
+    }
+}
+
+thread_local! {
+    static ACTIVE_HOOKS: RefCell<Option<ComponentLifecycle>> = const { RefCell::new(None) };
+}
+
+/// Runs a component render, collecting the lifecycle hooks it registers.
+pub fn collect_hooks<F, R>(f: F) -> (R, ComponentLifecycle)
+where
+    F: FnOnce() -> R,
+{
+    let old = ACTIVE_HOOKS.with(|h| h.borrow_mut().replace(ComponentLifecycle::default()));
+    let res = f();
+    let hooks = ACTIVE_HOOKS.with(|h| std::mem::replace(&mut *h.borrow_mut(), old));
+    (res, hooks.unwrap_or_default())
+}
+
+fn register(f: impl FnOnce(&mut ComponentLifecycle)) {
+    ACTIVE_HOOKS.with(|h| {
+        if let Some(hooks) = h.borrow_mut().as_mut() {
+            f(hooks);
+        } else {
+            panic!("No active component! Lifecycle hooks must be registered during a render.");
+        }
+    })
+}
+
+/// Runs once, after the component's first render has been committed.
+pub fn on_mount(f: impl FnOnce() + 'static) {
+    register(|hooks| hooks.on_mount.push(Box::new(f)));
+}
+
+/// Runs after each commit in which the component re-rendered.
+pub fn on_update(f: impl FnMut() + 'static) {
+    register(|hooks| hooks.on_update.push(Box::new(f)));
+}
+
+/// Runs when the component is removed from the tree.
+pub fn on_unmount(f: impl FnOnce() + 'static) {
+    register(|hooks| hooks.on_unmount.push(Box::new(f)));
+}
@@ -1,11 +1,15 @@
+use crate::template::Template;
+use crate::vdom::AttributeValue;
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum Mutation {
     AppendChildren {
         id: u64,
         m: Vec<u64>,
     },
+    /// Gives an id to the node at `path` (child indices) inside the most
+    /// recently loaded template.
     AssignId {
         path: Vec<u8>,
         id: u64,
@@ -14,6 +18,12 @@ pub enum Mutation {
         tag: String,
         id: u64,
     },
+    /// Creates an element in namespace `ns`, like SVG or MathML.
+    CreateElementNs {
+        tag: String,
+        ns: String,
+        id: u64,
+    },
     CreatePlaceholder {
         id: u64,
     },
@@ -21,16 +31,23 @@ pub enum Mutation {
         text: String,
         id: u64,
     },
+    /// Sets the text of the node at `path` in the most recently loaded
+    /// template and gives it an id.
     HydrateText {
         path: Vec<u8>,
         value: String,
         id: u64,
     },
+    /// Clones root `index` of a registered template and gives it an id.
     LoadTemplate {
         name: String,
         index: usize,
         id: u64,
     },
+    /// Sent once per template, before its first `LoadTemplate`.
+    RegisterTemplate {
+        template: Template,
+    },
     ReplaceWith {
         id: u64,
         m: Vec<u64>,
@@ -49,7 +66,7 @@ pub enum Mutation {
     },
     SetAttribute {
         name: String,
-        value: String, // Value enum in real impl
+        value: AttributeValue,
         id: u64,
         ns: Option<String>,
     },
@@ -75,9 +92,22 @@ pub enum Mutation {
     PushRoot {
         id: u64,
     },
+    /// Gives an id to the existing host element whose id attribute is
+    /// `target`, so a portal can append into it. The element is never removed.
+    LoadContainer {
+        target: String,
+        id: u64,
+    },
+    /// Makes node `id`, already in the document, the base for the `AssignId`,
+    /// `HydrateText` and `ReplacePlaceholder` paths that follow, as
+    /// `LoadTemplate` does for a clone. Hydration uses it to adopt
+    /// server-rendered markup.
+    LoadExisting {
+        id: u64,
+    },
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct MutationBatch {
     pub mutations: Vec<Mutation>,
 }
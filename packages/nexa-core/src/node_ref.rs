@@ -0,0 +1,64 @@
+//! Handles to rendered elements, for imperative access such as focusing an
+//! input or measuring a box.
+//!
+//! A `NodeRef` is attached to an element through `Element::node_ref`
+//! (`node_ref: my_ref` in `rsx!`). Once a commit creates the element, the
+//! runtime fills in its id, the same id the renderer received in the
+//! mutations, and clears it when the element is unmounted. Renderers
+//! resolve the id to their native object.
+
+use std::cell::Cell;
+use std::fmt;
+use std::rc::Rc;
+
+/// A shared slot for the id of a rendered element. Clones share the slot.
+#[derive(Clone, Default)]
+pub struct NodeRef {
+    id: Rc<Cell<Option<u64>>>,
+}
+
+impl NodeRef {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The id of the element this ref is attached to, if it is mounted.
+    pub fn get(&self) -> Option<u64> {
+        self.id.get()
+    }
+}
+
+/// Refs are equal when they share a slot.
+impl PartialEq for NodeRef {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.id, &other.id)
+    }
+}
+
+impl fmt::Debug for NodeRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("NodeRef").field(&self.id.get()).finish()
+    }
+}
+
+/// A change to a ref, applied once the diff that produced it is committed.
+#[derive(Debug)]
+pub(crate) enum RefUpdate {
+    Set(NodeRef, u64),
+    /// Clears the ref unless it has moved on to another element, as when
+    /// the element holding it is replaced by one with the same ref.
+    Clear(NodeRef, u64),
+}
+
+impl RefUpdate {
+    pub(crate) fn apply(self) {
+        match self {
+            RefUpdate::Set(node_ref, id) => node_ref.id.set(Some(id)),
+            RefUpdate::Clear(node_ref, id) => {
+                if node_ref.id.get() == Some(id) {
+                    node_ref.id.set(None);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,240 @@
+//! A peephole pass over a batch of mutations.
+//!
+//! The differ emits mutations as it walks the tree, so a batch covering
+//! several updates, or a node replaced wholesale, carries work a renderer
+//! doesn't need to do. `optimize` removes it without changing the document
+//! the batch produces:
+//!
+//! - an `InsertBefore` followed by the `Remove` of its anchor becomes one
+//!   `ReplaceWith`;
+//! - nodes created and removed within the batch, template clones included,
+//!   are never created, as long as nothing was attached to or positioned
+//!   against them;
+//! - attribute and text changes to nodes the batch removes are dropped;
+//! - of several `SetAttribute`/`RemoveAttribute` for the same attribute, or
+//!   `SetText` for the same node, only the last is kept;
+//! - a `SetText` on a node created in the batch is folded into its
+//!   `CreateTextNode`.
+
+use crate::mutations::Mutation;
+use std::collections::{HashMap, HashSet};
+
+/// Returns `mutations` with redundant operations coalesced or cancelled.
+pub fn optimize(mutations: Vec<Mutation>) -> Vec<Mutation> {
+    let mut slots: Vec<Option<Mutation>> = mutations.into_iter().map(Some).collect();
+    fuse_replacements(&mut slots);
+    cancel_transient_nodes(&mut slots);
+    drop_overwritten(&mut slots);
+    fold_text_into_creation(&mut slots);
+    slots.into_iter().flatten().collect()
+}
+
+/// `InsertBefore { id, m }` then `Remove { id }` is `ReplaceWith { id, m }`.
+fn fuse_replacements(slots: &mut [Option<Mutation>]) {
+    for i in 1..slots.len() {
+        let (Some(Mutation::InsertBefore { id: anchor, .. }), Some(Mutation::Remove { id })) =
+            (&slots[i - 1], &slots[i])
+        else {
+            continue;
+        };
+        if anchor != id {
+            continue;
+        }
+        let Some(Mutation::InsertBefore { id, m }) = slots[i - 1].take() else {
+            unreachable!()
+        };
+        slots[i] = Some(Mutation::ReplaceWith { id, m });
+    }
+}
+
+/// How a mutation refers to a node.
+enum Use {
+    /// Creates the node.
+    Create,
+    /// Changes only the node itself: its attributes, text or listeners.
+    Own,
+    /// Moves the node into place, among others.
+    Moved,
+    Remove,
+    /// Anything that depends on the node staying around: children or
+    /// siblings positioned against it, template paths, root context.
+    Anchor,
+}
+
+/// Every node id a mutation mentions, with how.
+fn uses(mutation: &Mutation) -> Vec<(u64, Use)> {
+    match mutation {
+        Mutation::CreateElement { id, .. }
+        | Mutation::CreateElementNs { id, .. }
+        | Mutation::CreatePlaceholder { id }
+        | Mutation::CreateTextNode { id, .. }
+        | Mutation::LoadTemplate { id, .. } => vec![(*id, Use::Create)],
+        Mutation::SetAttribute { id, .. }
+        | Mutation::RemoveAttribute { id, .. }
+        | Mutation::SetText { id, .. }
+        | Mutation::NewEventListener { id, .. }
+        | Mutation::RemoveEventListener { id, .. } => vec![(*id, Use::Own)],
+        Mutation::AppendChildren { id, m }
+        | Mutation::InsertAfter { id, m }
+        | Mutation::InsertBefore { id, m } => std::iter::once((*id, Use::Anchor))
+            .chain(m.iter().map(|&child| (child, Use::Moved)))
+            .collect(),
+        // An empty replacement would leave the target in place.
+        Mutation::ReplaceWith { id, m } => std::iter::once((*id, Use::Anchor))
+            .chain(m.iter().map(|&child| (child, Use::Anchor)))
+            .collect(),
+        Mutation::ReplacePlaceholder { m, .. } => {
+            m.iter().map(|&child| (child, Use::Anchor)).collect()
+        }
+        Mutation::Remove { id } => vec![(*id, Use::Remove)],
+        Mutation::AssignId { id, .. }
+        | Mutation::HydrateText { id, .. }
+        | Mutation::PushRoot { id }
+        | Mutation::LoadContainer { id, .. }
+        | Mutation::LoadExisting { id } => vec![(*id, Use::Anchor)],
+        Mutation::RegisterTemplate { .. } => vec![],
+    }
+}
+
+#[derive(Default)]
+struct NodeUses {
+    created: bool,
+    removed: bool,
+    anchored: bool,
+    /// Mentioned after its removal.
+    used_after_removal: bool,
+}
+
+/// Drops nodes that are created and removed within the batch, along with
+/// everything done to them, and the changes to other removed nodes, which
+/// can't be observed.
+///
+/// The nodes inside a template clone are treated as part of it: changes
+/// to them count as changes to the clone, and anything else keeps it.
+fn cancel_transient_nodes(slots: &mut [Option<Mutation>]) {
+    // Template clone each node given an id by a path belongs to.
+    let mut owners: HashMap<u64, u64> = HashMap::new();
+    let mut nodes: HashMap<u64, NodeUses> = HashMap::new();
+    let mut base = None;
+    for mutation in slots.iter().flatten() {
+        match mutation {
+            Mutation::LoadTemplate { id, .. } => base = Some(*id),
+            Mutation::LoadExisting { .. } => base = None,
+            Mutation::AssignId { id, .. } | Mutation::HydrateText { id, .. } => {
+                if let Some(root) = base {
+                    owners.insert(*id, root);
+                    continue;
+                }
+            }
+            Mutation::ReplacePlaceholder { .. } => {
+                if let Some(root) = base {
+                    nodes.entry(root).or_default().anchored = true;
+                }
+            }
+            _ => {}
+        }
+        for (id, how) in uses(mutation) {
+            let (id, how) = match owners.get(&id) {
+                Some(&root) if matches!(how, Use::Own) => (root, Use::Own),
+                Some(&root) => (root, Use::Anchor),
+                None => (id, how),
+            };
+            let node = nodes.entry(id).or_default();
+            if node.removed {
+                node.used_after_removal = true;
+            }
+            match how {
+                Use::Create => node.created = true,
+                Use::Remove => node.removed = true,
+                Use::Anchor => node.anchored = true,
+                Use::Own | Use::Moved => {}
+            }
+        }
+    }
+
+    let removed: HashSet<u64> = nodes
+        .iter()
+        .filter(|(_, n)| n.removed && !n.used_after_removal)
+        .map(|(&id, _)| id)
+        .collect();
+    let transient: HashSet<u64> = nodes
+        .iter()
+        .filter(|(id, n)| removed.contains(id) && n.created && !n.anchored)
+        .map(|(&id, _)| id)
+        .collect();
+    let owner = |id: &u64| owners.get(id).copied().unwrap_or(*id);
+
+    for slot in slots.iter_mut() {
+        let drop = match slot {
+            Some(
+                Mutation::CreateElement { id, .. }
+                | Mutation::CreateElementNs { id, .. }
+                | Mutation::CreatePlaceholder { id }
+                | Mutation::CreateTextNode { id, .. }
+                | Mutation::LoadTemplate { id, .. }
+                | Mutation::AssignId { id, .. }
+                | Mutation::HydrateText { id, .. }
+                | Mutation::NewEventListener { id, .. }
+                | Mutation::RemoveEventListener { id, .. }
+                | Mutation::Remove { id },
+            ) => transient.contains(&owner(id)),
+            Some(
+                Mutation::SetAttribute { id, .. }
+                | Mutation::RemoveAttribute { id, .. }
+                | Mutation::SetText { id, .. },
+            ) => removed.contains(&owner(id)),
+            Some(
+                Mutation::AppendChildren { m, .. }
+                | Mutation::InsertAfter { m, .. }
+                | Mutation::InsertBefore { m, .. },
+            ) => {
+                m.retain(|id| !transient.contains(id));
+                m.is_empty()
+            }
+            _ => false,
+        };
+        if drop {
+            *slot = None;
+        }
+    }
+}
+
+/// Keeps only the last write to each attribute and each node's text.
+fn drop_overwritten(slots: &mut [Option<Mutation>]) {
+    let mut attributes: HashSet<(u64, String)> = HashSet::new();
+    let mut texts: HashSet<u64> = HashSet::new();
+    for slot in slots.iter_mut().rev() {
+        let overwritten = match slot {
+            Some(
+                Mutation::SetAttribute { name, id, .. } | Mutation::RemoveAttribute { name, id },
+            ) => !attributes.insert((*id, name.clone())),
+            Some(Mutation::SetText { id, .. }) => !texts.insert(*id),
+            _ => false,
+        };
+        if overwritten {
+            *slot = None;
+        }
+    }
+}
+
+/// Creates text nodes with their final text rather than setting it after.
+fn fold_text_into_creation(slots: &mut [Option<Mutation>]) {
+    let mut created: HashMap<u64, usize> = HashMap::new();
+    for i in 0..slots.len() {
+        match &slots[i] {
+            Some(Mutation::CreateTextNode { id, .. }) => {
+                created.insert(*id, i);
+            }
+            Some(Mutation::SetText { id, .. }) => {
+                let Some(&at) = created.get(id) else { continue };
+                let Some(Mutation::SetText { value, .. }) = slots[i].take() else {
+                    unreachable!()
+                };
+                if let Some(Mutation::CreateTextNode { text, .. }) = &mut slots[at] {
+                    *text = value;
+                }
+            }
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,246 @@
+//! Per-component timings, recorded while `Profiling::start_recording` is on.
+//!
+//! Each render, diff and commit of a scope becomes a `ProfileSpan`. Spans
+//! nest as the work does: a parent's diff contains the renders and diffs of
+//! the children it updates, and its self time excludes them. Export a
+//! recording with `to_chrome_trace` (for `chrome://tracing` or Perfetto) or
+//! `to_folded` (for `flamegraph.pl` and `inferno`).
+//!
+//! ```ignore
+//! runtime.profiling.start_recording();
+//! runtime.update();
+//! let recording = runtime.profiling.stop_recording();
+//! std::fs::write("frame.json", recording.to_chrome_trace())?;
+//! ```
+
+use crate::runtime::{Scope, ScopeId};
+use slotmap::SlotMap;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+use std::time::{Duration, Instant};
+
+/// What a span measures.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum ProfilePhase {
+    /// Running the component's render function.
+    Render,
+    /// Reconciling the rendered tree, or creating it on mount.
+    Diff,
+    /// Running the component's mount and update hooks.
+    Commit,
+}
+
+impl ProfilePhase {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ProfilePhase::Render => "render",
+            ProfilePhase::Diff => "diff",
+            ProfilePhase::Commit => "commit",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ProfileSpan {
+    pub phase: ProfilePhase,
+    pub scope: ScopeId,
+    /// Component names from the root down to the scope's own.
+    pub stack: Vec<String>,
+    /// Since recording started.
+    pub start: Duration,
+    pub duration: Duration,
+    /// `duration` minus the spans nested in this one.
+    pub self_time: Duration,
+    /// Mutations emitted by this span, not counting nested spans.
+    pub mutations: u64,
+    nested_mutations: u64,
+}
+
+impl ProfileSpan {
+    pub fn name(&self) -> &str {
+        self.stack.last().map_or("", String::as_str)
+    }
+}
+
+/// Totals for one scope across a recording.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ComponentProfile {
+    pub scope: ScopeId,
+    pub name: String,
+    pub renders: u64,
+    pub render_time: Duration,
+    pub diff_time: Duration,
+    pub commit_time: Duration,
+    pub mutations: u64,
+}
+
+impl ComponentProfile {
+    pub fn total_time(&self) -> Duration {
+        self.render_time + self.diff_time + self.commit_time
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Recording {
+    origin: Instant,
+    pub spans: Vec<ProfileSpan>,
+    /// Spans still running, innermost last, with their mutation count at the start.
+    open: Vec<(usize, usize)>,
+}
+
+impl Recording {
+    pub(crate) fn new() -> Self {
+        Self {
+            origin: Instant::now(),
+            spans: Vec::new(),
+            open: Vec::new(),
+        }
+    }
+
+    /// Starts a span; `mutations` is the length of the mutation buffer.
+    pub(crate) fn open(
+        &mut self,
+        phase: ProfilePhase,
+        scopes: &SlotMap<ScopeId, Scope>,
+        scope: ScopeId,
+        mutations: usize,
+    ) -> usize {
+        let mut stack = Vec::new();
+        let mut current = Some(scope);
+        while let Some(s) = current.and_then(|id| scopes.get(id)) {
+            stack.push(s.name.clone());
+            current = s.parent;
+        }
+        stack.reverse();
+
+        let index = self.spans.len();
+        self.spans.push(ProfileSpan {
+            phase,
+            scope,
+            stack,
+            start: self.origin.elapsed(),
+            duration: Duration::ZERO,
+            self_time: Duration::ZERO,
+            mutations: 0,
+            nested_mutations: 0,
+        });
+        self.open.push((index, mutations));
+        index
+    }
+
+    /// Ends the span `index` and everything opened after it.
+    pub(crate) fn close(&mut self, index: usize, mutations: usize) {
+        while let Some((open, started_at)) = self.open.pop() {
+            let end = self.origin.elapsed();
+            let span = &mut self.spans[open];
+            span.duration = end.saturating_sub(span.start);
+            span.self_time = span.duration.saturating_sub(span.self_time);
+            // A rolled-back boundary can shrink the buffer under a span.
+            let total = mutations.saturating_sub(started_at) as u64;
+            span.mutations = total.saturating_sub(span.nested_mutations);
+            let (duration, total) = (span.duration, total);
+
+            if let Some(&(parent, _)) = self.open.last() {
+                // Until the parent closes, its `self_time` holds its children's time.
+                self.spans[parent].self_time += duration;
+                self.spans[parent].nested_mutations += total;
+            }
+            if open == index {
+                break;
+            }
+        }
+    }
+
+    /// Per-scope totals, the most expensive first.
+    pub fn components(&self) -> Vec<ComponentProfile> {
+        let mut by_scope: HashMap<ScopeId, ComponentProfile> = HashMap::new();
+        for span in &self.spans {
+            let entry = by_scope
+                .entry(span.scope)
+                .or_insert_with(|| ComponentProfile {
+                    scope: span.scope,
+                    name: span.name().to_string(),
+                    renders: 0,
+                    render_time: Duration::ZERO,
+                    diff_time: Duration::ZERO,
+                    commit_time: Duration::ZERO,
+                    mutations: 0,
+                });
+            match span.phase {
+                ProfilePhase::Render => {
+                    entry.renders += 1;
+                    entry.render_time += span.self_time;
+                }
+                ProfilePhase::Diff => entry.diff_time += span.self_time,
+                ProfilePhase::Commit => entry.commit_time += span.self_time,
+            }
+            entry.mutations += span.mutations;
+        }
+        let mut out: Vec<_> = by_scope.into_values().collect();
+        out.sort_by(|a, b| {
+            b.total_time()
+                .cmp(&a.total_time())
+                .then_with(|| a.name.cmp(&b.name))
+        });
+        out
+    }
+
+    /// The spans as Chrome trace-event JSON, one complete (`"X"`) event per
+    /// span, with the scope and mutation count in `args`.
+    pub fn to_chrome_trace(&self) -> String {
+        let mut out = String::from("{\"traceEvents\":[");
+        for (i, span) in self.spans.iter().enumerate() {
+            if i > 0 {
+                out.push(',');
+            }
+            let _ = write!(
+                out,
+                "{{\"name\":\"{}\",\"cat\":\"{}\",\"ph\":\"X\",\"ts\":{:.3},\"dur\":{:.3},\
+                 \"pid\":1,\"tid\":1,\"args\":{{\"scope\":\"{}\",\"mutations\":{}}}}}",
+                escape_json(span.name()),
+                span.phase.as_str(),
+                micros(span.start),
+                micros(span.duration),
+                escape_json(&format!("{:?}", span.scope)),
+                span.mutations
+            );
+        }
+        out.push_str("],\"displayTimeUnit\":\"ms\"}");
+        out
+    }
+
+    /// The spans in folded-stack format: one line per distinct stack, the
+    /// component names and then the phase, with its self time in
+    /// microseconds.
+    pub fn to_folded(&self) -> String {
+        let mut stacks: BTreeMap<String, Duration> = BTreeMap::new();
+        for span in &self.spans {
+            let stack = format!("{};{}", span.stack.join(";"), span.phase.as_str());
+            *stacks.entry(stack).or_default() += span.self_time;
+        }
+        let mut out = String::new();
+        for (stack, time) in stacks {
+            let _ = writeln!(out, "{stack} {}", time.as_micros());
+        }
+        out
+    }
+}
+
+fn micros(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1_000_000.0
+}
+
+fn escape_json(input: &str) -> String {
+    let mut out = String::with_capacity(input.len());
+    for c in input.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out
+}
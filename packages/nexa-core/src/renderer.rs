@@ -0,0 +1,115 @@
+//! The interface between the runtime and a host.
+//!
+//! A host applies mutations to whatever it draws with: the DOM, a native
+//! widget tree, a terminal, or `TestDom`. It implements `MutationSink` and
+//! lets the runtime push each batch into it:
+//!
+//! ```ignore
+//! runtime.mount("App", app);
+//! runtime.flush_into(&mut host);
+//! loop {
+//!     // ...dispatch events...
+//!     runtime.render_into(&mut host);
+//! }
+//! ```
+//!
+//! Hosts that would rather not match on `Mutation` implement `Renderer`
+//! instead: one callback per mutation, taking borrowed fields, with every
+//! callback a no-op by default so a host only handles what it draws.
+
+use crate::mutations::Mutation;
+use crate::template::Template;
+use crate::vdom::AttributeValue;
+
+/// Receives mutation batches from the runtime.
+pub trait MutationSink {
+    /// Applies one mutation. Mutations arrive in order.
+    fn push(&mut self, mutation: Mutation);
+
+    /// Called after the last mutation of a batch, for hosts that buffer.
+    fn flush(&mut self) {}
+
+    /// Pushes every mutation of `batch`, then flushes.
+    fn push_batch(&mut self, batch: Vec<Mutation>) {
+        for mutation in batch {
+            self.push(mutation);
+        }
+        self.flush();
+    }
+}
+
+/// Collects the batches, for hosts that apply them later or not at all.
+impl MutationSink for Vec<Mutation> {
+    fn push(&mut self, mutation: Mutation) {
+        Vec::push(self, mutation);
+    }
+}
+
+/// A `MutationSink` as one typed callback per mutation.
+///
+/// `create_element` covers both `CreateElement` and `CreateElementNs`; the
+/// others match their mutation field for field.
+#[allow(unused_variables)]
+pub trait Renderer {
+    fn append_children(&mut self, id: u64, children: &[u64]) {}
+    fn assign_id(&mut self, path: &[u8], id: u64) {}
+    fn create_element(&mut self, tag: &str, ns: Option<&str>, id: u64) {}
+    fn create_placeholder(&mut self, id: u64) {}
+    fn create_text_node(&mut self, text: &str, id: u64) {}
+    fn hydrate_text(&mut self, path: &[u8], value: &str, id: u64) {}
+    fn load_template(&mut self, name: &str, index: usize, id: u64) {}
+    fn register_template(&mut self, template: &Template) {}
+    fn replace_with(&mut self, id: u64, nodes: &[u64]) {}
+    fn replace_placeholder(&mut self, path: &[u8], nodes: &[u64]) {}
+    fn insert_after(&mut self, id: u64, nodes: &[u64]) {}
+    fn insert_before(&mut self, id: u64, nodes: &[u64]) {}
+    fn set_attribute(&mut self, name: &str, value: &AttributeValue, ns: Option<&str>, id: u64) {}
+    fn remove_attribute(&mut self, name: &str, id: u64) {}
+    fn set_text(&mut self, value: &str, id: u64) {}
+    fn new_event_listener(&mut self, name: &str, id: u64) {}
+    fn remove_event_listener(&mut self, name: &str, id: u64) {}
+    fn remove(&mut self, id: u64) {}
+    fn push_root(&mut self, id: u64) {}
+    fn load_container(&mut self, target: &str, id: u64) {}
+    fn load_existing(&mut self, id: u64) {}
+    /// Called after the last mutation of a batch.
+    fn finish(&mut self) {}
+}
+
+impl<R: Renderer> MutationSink for R {
+    fn push(&mut self, mutation: Mutation) {
+        match mutation {
+            Mutation::AppendChildren { id, m } => self.append_children(id, &m),
+            Mutation::AssignId { path, id } => self.assign_id(&path, id),
+            Mutation::CreateElement { tag, id } => self.create_element(&tag, None, id),
+            Mutation::CreateElementNs { tag, ns, id } => self.create_element(&tag, Some(&ns), id),
+            Mutation::CreatePlaceholder { id } => self.create_placeholder(id),
+            Mutation::CreateTextNode { text, id } => self.create_text_node(&text, id),
+            Mutation::HydrateText { path, value, id } => self.hydrate_text(&path, &value, id),
+            Mutation::LoadTemplate { name, index, id } => self.load_template(&name, index, id),
+            Mutation::RegisterTemplate { template } => self.register_template(&template),
+            Mutation::ReplaceWith { id, m } => self.replace_with(id, &m),
+            Mutation::ReplacePlaceholder { path, m } => self.replace_placeholder(&path, &m),
+            Mutation::InsertAfter { id, m } => self.insert_after(id, &m),
+            Mutation::InsertBefore { id, m } => self.insert_before(id, &m),
+            Mutation::SetAttribute {
+                name,
+                value,
+                id,
+                ns,
+            } => self.set_attribute(&name, &value, ns.as_deref(), id),
+            Mutation::RemoveAttribute { name, id } => self.remove_attribute(&name, id),
+            Mutation::SetText { value, id } => self.set_text(&value, id),
+            Mutation::NewEventListener { name, id } => self.new_event_listener(&name, id),
+            Mutation::RemoveEventListener { name, id } => self.remove_event_listener(&name, id),
+            Mutation::Remove { id } => self.remove(id),
+            Mutation::PushRoot { id } => self.push_root(id),
+            Mutation::LoadContainer { target, id } => self.load_container(&target, id),
+            Mutation::LoadExisting { id } => self.load_existing(id),
+        }
+    }
+
+    fn flush(&mut self) {
+        self.finish();
+    }
+}
@@ -1,12 +1,25 @@
 use crate::diff::Differ;
+use crate::error_boundary::{CaughtError, catch};
+use crate::events::{self, Event, EventPhase};
+pub use crate::lifecycle::ComponentLifecycle;
 use crate::mutations::Mutation;
-use crate::vdom::{NodeId, VDomArena, VirtualNode, set_active_arena};
-use nexa_signals::NodeType;
+use crate::node_ref::RefUpdate;
+use crate::optimize::optimize;
+use crate::profiler::{ProfilePhase, Recording};
+use crate::renderer::MutationSink;
+use crate::session::{Session, SessionEntry};
+use crate::vdom::{
+    AnyProps, Component, EventListener, NodeId, Portal, RenderFn, VDomArena, VirtualNode,
+};
 use nexa_signals::Scheduler;
-use nexa_signals::dependency::{allocate_node, execute, pop_observer, push_observer, take_dirty};
+use nexa_signals::dependency::{execute, take_dirty};
+use nexa_signals::{Signal, SignalId};
 
+use serde::Serialize;
 use slotmap::{Key, SlotMap, new_key_type};
-use std::collections::HashMap;
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::rc::Rc;
 
 new_key_type! {
     pub struct ScopeId;
@@ -19,11 +32,76 @@ pub enum RenderPhase {
     Commit,
 }
 
+/// Scopes whose effects fired since the last update, waiting to re-render.
+pub type DirtyScopes = Rc<RefCell<Vec<ScopeId>>>;
+
 #[derive(Default, Debug, Clone)]
 pub struct Profiling {
     pub render_count: u64,
     pub diff_count: u64,
+    /// Mutations as the diff emitted them.
     pub mutation_count: u64,
+    /// Mutations returned by `drain_mutations`, once `optimize` has dropped
+    /// the ones a later mutation cancels out.
+    pub drained_mutations: u64,
+    /// Arena nodes freed after commits.
+    pub nodes_collected: u64,
+    /// Render function runs, by component name.
+    pub component_renders: HashMap<String, u64>,
+    /// Re-renders skipped because a component's props compared equal.
+    pub skipped_renders: u64,
+    /// Per-component spans, while recording.
+    pub recording: Option<Recording>,
+}
+
+impl Profiling {
+    /// Starts recording a span for every render, diff and commit of each
+    /// scope, discarding any recording in progress.
+    pub fn start_recording(&mut self) {
+        self.recording = Some(Recording::new());
+    }
+
+    /// Stops recording and returns what was recorded, if anything.
+    pub fn stop_recording(&mut self) -> Option<Recording> {
+        self.recording.take()
+    }
+
+    /// How many times components named `name` have rendered.
+    pub fn renders(&self, name: &str) -> u64 {
+        self.component_renders.get(name).copied().unwrap_or(0)
+    }
+
+    pub(crate) fn count_render(&mut self, name: &str) {
+        match self.component_renders.get_mut(name) {
+            Some(count) => *count += 1,
+            None => {
+                self.component_renders.insert(name.to_string(), 1);
+            }
+        }
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.recording.is_some()
+    }
+
+    /// Opens a span for `scope` when recording; `mutations` is the length
+    /// of the mutation buffer, to count what the span emits.
+    pub(crate) fn open_span(
+        &mut self,
+        phase: ProfilePhase,
+        scopes: &SlotMap<ScopeId, Scope>,
+        scope: ScopeId,
+        mutations: usize,
+    ) -> Option<usize> {
+        let recording = self.recording.as_mut()?;
+        Some(recording.open(phase, scopes, scope, mutations))
+    }
+
+    pub(crate) fn close_span(&mut self, span: Option<usize>, mutations: usize) {
+        if let (Some(recording), Some(span)) = (self.recording.as_mut(), span) {
+            recording.close(span, mutations);
+        }
+    }
 }
 
 pub struct Runtime<S: Scheduler> {
@@ -32,11 +110,26 @@ pub struct Runtime<S: Scheduler> {
     pub mutation_buffer: Vec<Mutation>,
     pub scheduler: S,
     pub component_registry: HashMap<&'static str, fn() -> NodeId>,
-    pub root_fn: Option<fn() -> NodeId>,
-    pub root_effect: Option<nexa_signals::SignalId>,
     pub root_node: Option<NodeId>,
+    /// Roots mounted into containers of their own with `mount_at`, by name.
+    pub roots: BTreeMap<&'static str, Root>,
+    pub dirty_scopes: DirtyScopes,
+    /// Names of the templates already registered with the renderer.
+    pub templates: HashSet<String>,
     pub phase: RenderPhase,
     pub profiling: Profiling,
+    /// The session being recorded, between `start_session` and `stop_session`.
+    pub session: Option<Session>,
+}
+
+/// A root mounted with `Runtime::mount_at`.
+pub struct Root {
+    /// The id of the element the root renders into.
+    pub container: String,
+    /// The root component node.
+    pub node: NodeId,
+    /// The portal that places `node` in `container`.
+    pub portal: NodeId,
 }
 
 pub struct Scope {
@@ -44,13 +137,16 @@ pub struct Scope {
     pub name: String,
     pub lifecycle: ComponentLifecycle,
     pub root_node: Option<NodeId>,
-}
-
-#[derive(Default)]
-pub struct ComponentLifecycle {
-    pub on_mount: Option<fn()>,
-    pub on_update: Option<fn()>,
-    pub on_drop: Option<Option<fn()>>, // Double option for "has drop fn" vs "is it set"
+    /// The component node this scope renders.
+    pub node: NodeId,
+    pub parent: Option<ScopeId>,
+    /// Distance from the root scope; parents re-render before their children.
+    pub height: u32,
+    /// Effect node that subscribes to the signals read during render.
+    pub effect: SignalId,
+    pub render_fn: RenderFn,
+    /// Props from the most recent render.
+    pub props: Rc<dyn AnyProps>,
 }
 
 impl<S: Scheduler> Runtime<S> {
@@ -61,15 +157,39 @@ impl<S: Scheduler> Runtime<S> {
             mutation_buffer: Vec::new(),
             scheduler,
             component_registry: HashMap::new(),
-            root_fn: None,
-            root_effect: None,
             root_node: None,
+            roots: BTreeMap::new(),
+            dirty_scopes: Rc::new(RefCell::new(Vec::new())),
+            templates: HashSet::new(),
             phase: RenderPhase::Begin,
             profiling: Profiling::default(),
+            session: None,
         }
     }
 
     pub fn mount(&mut self, root_component_name: &'static str, root_fn: fn() -> NodeId) {
+        self.mount_root(root_component_name, root_fn, false);
+    }
+
+    /// Mounts like `mount`, but adopts the markup `nexa-ssr` rendered into
+    /// the root container for the same tree instead of creating it. The
+    /// mutations give the existing nodes their ids and attach listeners;
+    /// only what the server doesn't render in place, like portal content,
+    /// is created.
+    ///
+    /// The runtime can't see the markup, so renderers check that it matches
+    /// as they apply the mutations, and report mismatches with the path of
+    /// the node in debug builds.
+    pub fn hydrate(&mut self, root_component_name: &'static str, root_fn: fn() -> NodeId) {
+        self.mount_root(root_component_name, root_fn, true);
+    }
+
+    fn mount_root(
+        &mut self,
+        root_component_name: &'static str,
+        root_fn: fn() -> NodeId,
+        hydrate: bool,
+    ) {
         tracing::info!(
             "Runtime::mount started for component: {}",
             root_component_name
@@ -78,21 +198,56 @@ impl<S: Scheduler> Runtime<S> {
         self.profiling.render_count += 1;
 
         self.component_registry.insert(root_component_name, root_fn);
-        self.root_fn = Some(root_fn);
 
-        // Create a signal for the root effect/computation
-        let effect_id = allocate_node(NodeType::Effect);
-        self.root_effect = Some(effect_id);
+        // The root is an ordinary component node, so it gets a scope and
+        // an effect like any other component.
+        let root_id = self.arena.insert(VirtualNode::Component(Component::new(
+            root_component_name,
+            move |()| root_fn(),
+            (),
+        )));
+        self.root_node = Some(root_id);
 
-        let _scope_id = self.scopes.insert(Scope {
-            id: ScopeId::default(),
-            name: root_component_name.to_string(),
-            lifecycle: ComponentLifecycle::default(),
-            root_node: None,
-        });
+        self.phase = RenderPhase::Commit;
+
+        let mut differ = Differ::new(
+            &mut self.arena,
+            &mut self.mutation_buffer,
+            &mut self.profiling,
+            &mut self.scopes,
+            &self.dirty_scopes,
+            &mut self.templates,
+        );
+        if hydrate {
+            differ.hydrate_tree(root_id);
+        } else {
+            differ.create_tree(root_id);
+        }
+        let mounted = std::mem::take(&mut differ.mounted);
+        let garbage = std::mem::take(&mut differ.garbage);
+        let mut errors = std::mem::take(&mut differ.errors);
+        let refs = std::mem::take(&mut differ.refs);
+
+        // Append the new root to container
+        // We need to flatten to find actual element IDs (skip fragments/components)
+        let roots = differ.flatten_node(root_id);
+        if let Some(&first) = roots.first() {
+            // PushRoot to set the root ID context
+            self.mutation_buffer.push(Mutation::PushRoot { id: first });
+            // Hydrated roots are already in the container.
+            if !hydrate {
+                self.mutation_buffer.push(Mutation::AppendChildren {
+                    id: 0, // Container
+                    m: roots,
+                });
+                self.profiling.mutation_count += 1;
+            }
+        }
 
-        // Initial render via run_root
-        self.run_root();
+        commit_refs(refs);
+        errors.extend(self.run_lifecycle_hooks(mounted, Vec::new()));
+        self.collect_garbage(garbage);
+        self.recover(errors);
 
         tracing::info!(
             "Mount complete. Generated {} mutations.",
@@ -100,107 +255,231 @@ impl<S: Scheduler> Runtime<S> {
         );
     }
 
-    fn run_root(&mut self) {
-        if let Some(root_fn) = self.root_fn {
-            tracing::debug!("Running root render...");
-
-            // Track dependencies
-            if let Some(effect_id) = self.root_effect {
-                push_observer(effect_id);
-            }
-
-            let root_id = unsafe { set_active_arena(&mut self.arena, || (root_fn)()) };
-
-            // Stop tracking
-            if self.root_effect.is_some() {
-                pop_observer();
-            }
-
-            self.phase = RenderPhase::Commit;
-
-            if let Some(old_root) = self.root_node {
-                // Diff against old root
-                Differ::new(
-                    &mut self.arena,
-                    &mut self.mutation_buffer,
-                    &mut self.profiling,
-                    &mut self.scopes,
-                )
-                .diff_nodes(old_root, root_id, None);
-            } else {
-                // Initial creation
-                // PushRoot to set the root ID context
-                self.mutation_buffer.push(Mutation::PushRoot {
-                    id: root_id.data().as_ffi(),
-                });
+    /// Mounts `root_fn` as an independent root in the element with the id
+    /// `container`, alongside the root from `mount` and any others. Roots
+    /// share the signal graph and scheduler, so one `update` re-renders
+    /// whatever changed in any of them. Mounting a name again replaces
+    /// that root.
+    pub fn mount_at(&mut self, name: &'static str, container: &str, root_fn: fn() -> NodeId) {
+        tracing::info!("Runtime::mount_at {} in #{}", name, container);
+        self.unmount(name);
+        self.phase = RenderPhase::Begin;
+        self.profiling.render_count += 1;
 
-                Differ::new(
-                    &mut self.arena,
-                    &mut self.mutation_buffer,
-                    &mut self.profiling,
-                    &mut self.scopes,
-                )
-                .create_tree(root_id);
-
-                // Append the new root to container
-                // We need to flatten to find actual element IDs (skip fragments/components)
-                let roots = self.flatten_children(&[root_id]);
-                if !roots.is_empty() {
-                    self.mutation_buffer.push(Mutation::AppendChildren {
-                        id: 0, // Container
-                        m: roots,
-                    });
-                    self.profiling.mutation_count += 1;
-                }
-            }
+        self.component_registry.insert(name, root_fn);
+        let node = self.arena.insert(VirtualNode::Component(Component::new(
+            name,
+            move |()| root_fn(),
+            (),
+        )));
+        // The portal loads the container and gives the root's top-level
+        // nodes a parent other than container 0, including across re-renders.
+        let portal = self.arena.insert(VirtualNode::Portal(Portal {
+            target: container.to_string(),
+            content: node,
+            parent: None,
+            key: None,
+        }));
+        self.roots.insert(
+            name,
+            Root {
+                container: container.to_string(),
+                node,
+                portal,
+            },
+        );
 
-            self.root_node = Some(root_id);
-        }
+        self.phase = RenderPhase::Commit;
+        let mut differ = Differ::new(
+            &mut self.arena,
+            &mut self.mutation_buffer,
+            &mut self.profiling,
+            &mut self.scopes,
+            &self.dirty_scopes,
+            &mut self.templates,
+        );
+        differ.create_tree(portal);
+        let mounted = std::mem::take(&mut differ.mounted);
+        let garbage = std::mem::take(&mut differ.garbage);
+        let mut errors = std::mem::take(&mut differ.errors);
+        commit_refs(std::mem::take(&mut differ.refs));
+        errors.extend(self.run_lifecycle_hooks(mounted, Vec::new()));
+        self.collect_garbage(garbage);
+        self.recover(errors);
     }
 
-    // ... update ...
+    /// Removes the root `name` mounted with `mount_at` from its container,
+    /// running the unmount hooks of its components. The other roots are
+    /// untouched. Returns whether there was such a root.
+    pub fn unmount(&mut self, name: &str) -> bool {
+        let Some(root) = self.roots.remove(name) else {
+            return false;
+        };
+        tracing::info!("Runtime::unmount {} from #{}", name, root.container);
+
+        let mut differ = Differ::new(
+            &mut self.arena,
+            &mut self.mutation_buffer,
+            &mut self.profiling,
+            &mut self.scopes,
+            &self.dirty_scopes,
+            &mut self.templates,
+        );
+        differ.remove_subtree(root.portal);
+        let garbage = std::mem::take(&mut differ.garbage);
+        let errors = std::mem::take(&mut differ.errors);
+        commit_refs(std::mem::take(&mut differ.refs));
+        self.collect_garbage(garbage);
+        self.recover(errors);
+        true
+    }
 
     pub fn update(&mut self) {
         self.phase = RenderPhase::Begin;
 
+        // Resources resolving here mark the scopes waiting on them dirty.
+        crate::suspense::poll_tasks();
+
         // 1. Gather dirty signals
+        // Writes outside a batch have already propagated; batched ones are still queued.
         let dirty = take_dirty();
 
-        if dirty.is_empty() {
+        if !dirty.is_empty() {
+            // 2. Schedule
+            self.scheduler.schedule(dirty);
+
+            // 3. Run Scheduler
+            let queue = nexa_signals::dependency::GRAPH.with(|g| {
+                let graph = g.borrow();
+                self.scheduler.run(&graph)
+            });
+
+            // Execute signal updates. Scope effects queue their scopes in `dirty_scopes`.
+            execute(queue);
+        }
+
+        if self.dirty_scopes.borrow().is_empty() {
             return;
         }
 
         self.profiling.render_count += 1;
+        self.phase = RenderPhase::Diff;
 
-        // 2. Schedule
-        self.scheduler.schedule(dirty);
+        // Re-render only the dirty scopes, parents first. A parent re-render
+        // diffs its child components too, which clears them from the queue.
+        let mut mounted = Vec::new();
+        let mut updated = Vec::new();
+        let mut garbage = Vec::new();
+        let mut errors = Vec::new();
+        let mut refs = Vec::new();
+        while let Some(scope_id) = self.next_dirty_scope() {
+            tracing::debug!("Scope {:?} dirty, re-rendering...", scope_id);
+            let mut differ = Differ::new(
+                &mut self.arena,
+                &mut self.mutation_buffer,
+                &mut self.profiling,
+                &mut self.scopes,
+                &self.dirty_scopes,
+                &mut self.templates,
+            );
+            differ.diff_scope(scope_id);
+            mounted.append(&mut differ.mounted);
+            updated.append(&mut differ.updated);
+            garbage.append(&mut differ.garbage);
+            errors.append(&mut differ.errors);
+            refs.append(&mut differ.refs);
+        }
 
-        // 3. Run Scheduler
-        self.phase = RenderPhase::Diff;
-        let queue = nexa_signals::dependency::GRAPH.with(|g| {
-            let graph = g.borrow();
-            self.scheduler.run(&graph)
-        });
-
-        // Execute signal updates
-        nexa_signals::dependency::execute(queue.clone());
-
-        for sig in queue {
-            // Re-render components dependent on sig
-            if Some(sig) == self.root_effect {
-                tracing::info!("Root effect dirty, re-rendering...");
-                self.run_root();
+        // Children may re-render after their parent (when their props were
+        // unchanged), but their update hooks still run first.
+        updated.sort_by_key(|&id| std::cmp::Reverse(self.scopes.get(id).map_or(0, |s| s.height)));
+
+        self.phase = RenderPhase::Commit;
+        // Batching/Draining happens in drain_mutations
+        commit_refs(refs);
+        errors.extend(self.run_lifecycle_hooks(mounted, updated));
+        self.collect_garbage(garbage);
+        self.recover(errors);
+    }
+
+    /// Swaps in the fallbacks of the boundaries above caught failures,
+    /// then commits the fallbacks, which may fail in turn.
+    fn recover(&mut self, mut errors: Vec<(NodeId, CaughtError)>) {
+        while !errors.is_empty() {
+            let mut differ = Differ::new(
+                &mut self.arena,
+                &mut self.mutation_buffer,
+                &mut self.profiling,
+                &mut self.scopes,
+                &self.dirty_scopes,
+                &mut self.templates,
+            );
+            differ.show_errors(errors);
+            let mounted = std::mem::take(&mut differ.mounted);
+            let garbage = std::mem::take(&mut differ.garbage);
+            errors = std::mem::take(&mut differ.errors);
+            commit_refs(std::mem::take(&mut differ.refs));
+            errors.extend(self.run_lifecycle_hooks(mounted, Vec::new()));
+            self.collect_garbage(garbage);
+        }
+    }
+
+    /// Frees the arena nodes that a commit replaced or removed.
+    fn collect_garbage(&mut self, garbage: Vec<NodeId>) {
+        for id in garbage {
+            if self.arena.remove(id).is_some() {
+                self.profiling.nodes_collected += 1;
             }
         }
+    }
 
-        for scope in self.scopes.values_mut() {
-            if let Some(on_update) = scope.lifecycle.on_update {
-                on_update();
+    /// Runs the mount and update hooks of the scopes touched by a commit,
+    /// returning the failures of hooks that panicked.
+    fn run_lifecycle_hooks(
+        &mut self,
+        mounted: Vec<ScopeId>,
+        updated: Vec<ScopeId>,
+    ) -> Vec<(NodeId, CaughtError)> {
+        let mut errors = Vec::new();
+        for scope_id in mounted {
+            let span = self.open_commit_span(scope_id);
+            if let Some(scope) = self.scopes.get_mut(scope_id) {
+                for hook in std::mem::take(&mut scope.lifecycle.on_mount) {
+                    if let Err(error) = catch(hook) {
+                        errors.push((scope.node, error));
+                    }
+                }
+            }
+            self.profiling.close_span(span, self.mutation_buffer.len());
+        }
+        for scope_id in updated {
+            let span = self.open_commit_span(scope_id);
+            if let Some(scope) = self.scopes.get_mut(scope_id) {
+                for hook in scope.lifecycle.on_update.iter_mut() {
+                    if let Err(error) = catch(hook) {
+                        errors.push((scope.node, error));
+                    }
+                }
             }
+            self.profiling.close_span(span, self.mutation_buffer.len());
         }
+        errors
+    }
 
-        self.phase = RenderPhase::Commit;
-        // Batching/Draining happens in drain_mutations
+    fn open_commit_span(&mut self, scope_id: ScopeId) -> Option<usize> {
+        self.profiling.open_span(
+            ProfilePhase::Commit,
+            &self.scopes,
+            scope_id,
+            self.mutation_buffer.len(),
+        )
+    }
+
+    fn next_dirty_scope(&mut self) -> Option<ScopeId> {
+        let mut dirty = self.dirty_scopes.borrow_mut();
+        dirty.retain(|&id| self.scopes.contains_key(id));
+        let idx = (0..dirty.len()).min_by_key(|&i| self.scopes[dirty[i]].height)?;
+        Some(dirty.swap_remove(idx))
     }
 
     pub fn flatten_fragment(&self, id: NodeId, output: &mut Vec<NodeId>) {
@@ -213,43 +492,161 @@ impl<S: Scheduler> Runtime<S> {
         }
     }
 
+    /// Takes the mutations since the last drain, with redundant ones
+    /// removed by `optimize::optimize`.
     pub fn drain_mutations(&mut self) -> Vec<Mutation> {
-        let mut mutations = Vec::new();
-        std::mem::swap(&mut self.mutation_buffer, &mut mutations);
-        mutations
-    }
-
-    pub fn handle_event(&mut self, node_id: u64, event_name: &str, event: crate::events::Event) {
-        // use slotmap::Key;
-        // Reconstruct NodeId from u64 (assuming 1:1 mapping with ffi_id logic)
-        // Helper: NodeId::from(Data::from_ffi(node_id))
-        // But NodeId key type details are hidden by slotmap macro?
-        // Actually NodeId is new_key_type, so we need to construct it carefully.
-        // nexa_core's NodeId might not be directly constructible from u64 if logic is complex,
-        // but slotmap keys are usually (version, index).
-        // Wait, ffi_id = id.data().as_ffi().
-        // We need to reverse this.
-        let id = NodeId::from(slotmap::KeyData::from_ffi(node_id));
-
-        tracing::debug!("Runtime handling event '{}' for node {:?}", event_name, id);
-
-        let mut callback_to_run = None;
-
-        if let Some(VirtualNode::Element(el)) = self.arena.nodes.get(id) {
-            for listener in &el.listeners {
-                if listener.name == event_name {
-                    callback_to_run = Some(listener.cb.clone());
+        let batch = optimize(std::mem::take(&mut self.mutation_buffer));
+        self.profiling.drained_mutations += batch.len() as u64;
+        if let Some(session) = &mut self.session
+            && !batch.is_empty()
+        {
+            session.entries.push(SessionEntry::Batch(batch.clone()));
+        }
+        batch
+    }
+
+    /// Pushes the mutations since the last drain into `sink` as one batch.
+    /// Nothing is pushed when there are none.
+    pub fn flush_into<M: MutationSink + ?Sized>(&mut self, sink: &mut M) {
+        let batch = self.drain_mutations();
+        if !batch.is_empty() {
+            sink.push_batch(batch);
+        }
+    }
+
+    /// Runs `update` and pushes what it changed into `sink`.
+    pub fn render_into<M: MutationSink + ?Sized>(&mut self, sink: &mut M) {
+        self.update();
+        self.flush_into(sink);
+    }
+
+    /// Starts recording the events, signal writes and mutation batches that
+    /// `session::Replayer` can replay, discarding any session in progress.
+    ///
+    /// Only signal writes made through `write_signal` are recorded; a
+    /// `Signal::set` from outside the tree is lost to the replay. Start
+    /// before `mount`, or before its batch is drained, to record the mount.
+    pub fn start_session(&mut self) {
+        self.session = Some(Session::default());
+    }
+
+    /// Stops recording and returns the session, if one was recorded.
+    pub fn stop_session(&mut self) -> Option<Session> {
+        self.session.take()
+    }
+
+    /// Sets `signal` from outside the tree, say from a timer or a socket.
+    /// While a session is recorded, the write is logged under `name` for a
+    /// replay to repeat.
+    pub fn write_signal<T: PartialEq + Serialize + 'static>(
+        &mut self,
+        name: &str,
+        signal: &Signal<T>,
+        value: T,
+    ) {
+        if let Some(session) = &mut self.session {
+            match serde_json::to_value(&value) {
+                Ok(value) => session.entries.push(SessionEntry::SignalWrite {
+                    signal: name.to_string(),
+                    value,
+                }),
+                Err(error) => tracing::warn!("Cannot record write to '{}': {}", name, error),
+            }
+        }
+        signal.set(value);
+    }
+
+    /// Dispatches `event` to the element `node_id`.
+    ///
+    /// Capture listeners run from the outermost element down to the target,
+    /// then regular listeners from the target up through its ancestors, unless
+    /// the event doesn't bubble or a listener stops propagation. Afterwards
+    /// the runtime updates once, so every state change lands in one batch.
+    pub fn handle_event(&mut self, node_id: u64, event_name: &str, event: Event) {
+        if let Some(session) = &mut self.session {
+            session.entries.push(SessionEntry::Event {
+                node: node_id,
+                name: event_name.to_string(),
+                data: event.data().clone(),
+            });
+        }
+        let target = NodeId::from(slotmap::KeyData::from_ffi(node_id));
+
+        tracing::debug!(
+            "Runtime handling event '{}' for node {:?}",
+            event_name,
+            target
+        );
+
+        if !matches!(self.arena.nodes.get(target), Some(VirtualNode::Element(_))) {
+            tracing::warn!("Event targeted at missing or non-element node {:?}", target);
+            return;
+        }
+
+        // Target first, outermost element last.
+        let mut path = Vec::new();
+        let mut current = Some(target);
+        while let Some(id) = current {
+            let Some(node) = self.arena.nodes.get(id) else {
+                break;
+            };
+            if let VirtualNode::Element(el) = node {
+                let listeners: Vec<_> = el
+                    .listeners
+                    .iter()
+                    .filter(|l| l.name == event_name)
+                    .cloned()
+                    .collect();
+                path.push((id, listeners));
+            }
+            current = node.parent();
+        }
+
+        let mut ran = false;
+        let mut failure = None;
+        // A listener that panics stops the dispatch.
+        let mut dispatch = |current: NodeId, listeners: &[EventListener], capture, phase| {
+            for listener in listeners.iter().filter(|l| l.capture == capture) {
+                let event = event.at(node_id, current.data().as_ffi(), phase);
+                ran = true;
+                if let Err(error) = catch(|| (listener.cb.borrow_mut())(event)) {
+                    failure = Some((current, error));
+                    return true;
+                }
+            }
+            event.propagation_stopped()
+        };
+
+        'dispatch: {
+            for (i, (id, listeners)) in path.iter().enumerate().rev() {
+                let phase = if i == 0 {
+                    EventPhase::AtTarget
+                } else {
+                    EventPhase::Capturing
+                };
+                if dispatch(*id, listeners, true, phase) {
+                    break 'dispatch;
+                }
+            }
+            for (i, (id, listeners)) in path.iter().enumerate() {
+                if i > 0 && !events::bubbles(event_name) {
+                    break;
+                }
+                let phase = if i == 0 {
+                    EventPhase::AtTarget
+                } else {
+                    EventPhase::Bubbling
+                };
+                if dispatch(*id, listeners, false, phase) {
                     break;
                 }
             }
-        } else {
-            // Maybe it's a component root or something?
-            // Or maybe the node was removed?
-            tracing::warn!("Event targeted at missing or non-element node {:?}", id);
         }
 
-        if let Some(cb) = callback_to_run {
-            (cb.borrow_mut())(event);
+        if let Some(failure) = failure {
+            self.recover(vec![failure]);
+        }
+        if ran {
             self.update(); // Trigger reactivity update after event
         }
     }
@@ -270,6 +667,9 @@ impl<S: Scheduler> Runtime<S> {
         if let Some(root) = self.root_node {
             self.walk_verify(root);
         }
+        for root in self.roots.values() {
+            self.walk_verify(root.node);
+        }
     }
 
     fn walk_verify(&self, id: NodeId) {
@@ -292,11 +692,21 @@ impl<S: Scheduler> Runtime<S> {
             VirtualNode::Component(comp) => {
                 // components are essentially the start of a subtree
                 if let Some(scope_id) = comp.scope {
-                    if !self.scopes.contains_key(scope_id) {
+                    let Some(scope) = self.scopes.get(scope_id) else {
                         panic!("Component {} has invalid ScopeId!", comp.name);
+                    };
+                    if let Some(root) = scope.root_node {
+                        self.walk_verify(root);
                     }
                 }
             }
+            VirtualNode::Suspense(susp) if susp.suspended => self.walk_verify(susp.fallback),
+            VirtualNode::Suspense(susp) => self.walk_verify(susp.actual),
+            VirtualNode::ErrorBoundary(eb) => match &eb.caught {
+                Some(caught) => self.walk_verify(caught.fallback),
+                None => self.walk_verify(eb.content),
+            },
+            VirtualNode::Portal(portal) => self.walk_verify(portal.content),
             _ => {}
         }
     }
@@ -305,3 +715,10 @@ impl<S: Scheduler> Runtime<S> {
         self.walk_verify(id);
     }
 }
+
+/// Fills in and clears the node refs touched by a commit, in diff order.
+fn commit_refs(refs: Vec<RefUpdate>) {
+    for update in refs {
+        update.apply();
+    }
+}
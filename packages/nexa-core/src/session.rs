@@ -0,0 +1,169 @@
+//! Recording sessions and replaying them, to reproduce UI bugs.
+//!
+//! Between `Runtime::start_session` and `Runtime::stop_session` the runtime
+//! records the events it handles, the signal writes made through
+//! `Runtime::write_signal`, and every batch `drain_mutations` returns. A
+//! `Session` is plain serde data, so a host can save it with a bug report.
+//! `Replayer` drives a fresh runtime through the same inputs and reports
+//! the first mutation that comes out differently:
+//!
+//! ```ignore
+//! runtime.start_session();
+//! runtime.mount("App", app);
+//! // ...the user reproduces the bug...
+//! let session = runtime.stop_session().unwrap();
+//! std::fs::write("session.json", serde_json::to_string(&session)?)?;
+//!
+//! let mut runtime = Runtime::new(LocalScheduler::new());
+//! runtime.mount("App", app);
+//! Replayer::new(&session).signal("user", user).run(&mut runtime)?;
+//! ```
+//!
+//! # What a session holds
+//!
+//! Signal writes are recorded only when they go through
+//! `Runtime::write_signal`. A plain `Signal::set` from outside the tree, in
+//! a timer, a socket callback or a test, leaves no trace, and a replay of
+//! the session diverges at the next batch. Writes made by event handlers
+//! need no recording, as replaying the event makes them again.
+//!
+//! Batches are recorded as they are drained, and a replay matches each one
+//! against what its own runtime drains at the same point. The replay runtime
+//! must therefore hold what the recorded one did when the session started.
+//! A session started before the mount's mutations were drained, as above,
+//! begins with the mount batch: mount the same roots and leave them
+//! undrained. For a session started later, drain the mount before `run`.
+
+use crate::events::{Event, EventData};
+use crate::mutations::Mutation;
+use crate::runtime::Runtime;
+use nexa_signals::{Scheduler, Signal};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum SessionEntry {
+    /// A `Runtime::handle_event` call.
+    Event {
+        node: u64,
+        name: String,
+        data: EventData,
+    },
+    /// A `Runtime::write_signal` call, with the value as JSON.
+    SignalWrite {
+        signal: String,
+        value: serde_json::Value,
+    },
+    /// A batch returned by `Runtime::drain_mutations`. Empty ones are skipped.
+    Batch(Vec<Mutation>),
+}
+
+/// The inputs and output of a runtime, in the order they happened.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Session {
+    pub entries: Vec<SessionEntry>,
+}
+
+impl Session {
+    pub fn batches(&self) -> impl Iterator<Item = &[Mutation]> {
+        self.entries.iter().filter_map(|entry| match entry {
+            SessionEntry::Batch(batch) => Some(batch.as_slice()),
+            _ => None,
+        })
+    }
+}
+
+#[derive(Debug, thiserror::Error, PartialEq)]
+pub enum ReplayError {
+    #[error("no signal registered for '{0}'")]
+    UnknownSignal(String),
+    #[error("recorded value of '{signal}' doesn't deserialize: {message}")]
+    InvalidValue { signal: String, message: String },
+    /// Mutation `index` of batch `batch` isn't the recorded one; `None`
+    /// where one of the batches is shorter.
+    #[error("batch {batch} differs at mutation {index}: expected {expected:?}, got {actual:?}")]
+    Diverged {
+        batch: usize,
+        index: usize,
+        expected: Option<Box<Mutation>>,
+        actual: Option<Box<Mutation>>,
+    },
+}
+
+type SignalWriter = Box<dyn Fn(serde_json::Value) -> Result<(), serde_json::Error>>;
+
+/// Drives a runtime through a recorded session.
+pub struct Replayer<'a> {
+    session: &'a Session,
+    signals: HashMap<String, SignalWriter>,
+}
+
+impl<'a> Replayer<'a> {
+    pub fn new(session: &'a Session) -> Self {
+        Self {
+            session,
+            signals: HashMap::new(),
+        }
+    }
+
+    /// Replays the writes recorded under `name` into `signal`.
+    pub fn signal<T: PartialEq + DeserializeOwned + 'static>(
+        mut self,
+        name: &str,
+        signal: Signal<T>,
+    ) -> Self {
+        let writer = move |value| {
+            signal.set(serde_json::from_value(value)?);
+            Ok(())
+        };
+        self.signals.insert(name.to_string(), Box::new(writer));
+        self
+    }
+
+    /// Replays the session into `runtime`, which must start out the way the
+    /// recorded one did: usually freshly created, with the same roots
+    /// mounted and, if the session recorded the mount, not yet drained; see
+    /// the module docs. Each recorded batch is compared with what an `update`
+    /// produces at the same point, stopping at the first difference.
+    pub fn run<S: Scheduler>(&self, runtime: &mut Runtime<S>) -> Result<(), ReplayError> {
+        let mut batches = 0;
+        for entry in &self.session.entries {
+            match entry {
+                SessionEntry::Event { node, name, data } => {
+                    runtime.handle_event(*node, name, Event::new(data.clone()));
+                }
+                SessionEntry::SignalWrite { signal, value } => {
+                    let writer = self
+                        .signals
+                        .get(signal)
+                        .ok_or_else(|| ReplayError::UnknownSignal(signal.clone()))?;
+                    writer(value.clone()).map_err(|error| ReplayError::InvalidValue {
+                        signal: signal.clone(),
+                        message: error.to_string(),
+                    })?;
+                }
+                SessionEntry::Batch(expected) => {
+                    runtime.update();
+                    let actual = runtime.drain_mutations();
+                    compare(batches, expected, &actual)?;
+                    batches += 1;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn compare(batch: usize, expected: &[Mutation], actual: &[Mutation]) -> Result<(), ReplayError> {
+    let len = expected.len().max(actual.len());
+    match (0..len).find(|&i| expected.get(i) != actual.get(i)) {
+        Some(index) => Err(ReplayError::Diverged {
+            batch,
+            index,
+            expected: expected.get(index).cloned().map(Box::new),
+            actual: actual.get(index).cloned().map(Box::new),
+        }),
+        None => Ok(()),
+    }
+}
@@ -0,0 +1,358 @@
+//! Snapshot assertions for rendered trees and mutation streams.
+//!
+//! `tree_snapshot` and `mutations_snapshot` turn a runtime's state into text
+//! that is stable between runs, and `assert_snapshot!` compares it against a
+//! file under the calling crate's `tests/snapshots`:
+//!
+//! ```ignore
+//! runtime.mount("App", app);
+//! assert_snapshot!("app_mount", mutations_snapshot(&runtime.drain_mutations()));
+//! assert_snapshot!("app_tree", tree_snapshot(&runtime));
+//! ```
+//!
+//! Run with `NEXA_UPDATE_SNAPSHOTS=1` to write the snapshots instead of
+//! checking them, then review the changes like any other diff.
+
+use crate::mutations::Mutation;
+use crate::runtime::Runtime;
+use crate::template::{Template, TemplateNode};
+use crate::vdom::{AttributeValue, NodeId, VirtualNode};
+use nexa_signals::Scheduler;
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::path::Path;
+
+/// Set to write snapshots instead of comparing against them.
+pub const UPDATE_ENV_VAR: &str = "NEXA_UPDATE_SNAPSHOTS";
+
+/// Compares `actual` with the snapshot in `tests/snapshots/<name>.snap` of
+/// the crate being tested.
+#[macro_export]
+macro_rules! assert_snapshot {
+    ($name:literal, $actual:expr) => {
+        $crate::snapshot::assert_snapshot(
+            ::std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+                .join("tests/snapshots")
+                .join(concat!($name, ".snap")),
+            &$actual,
+        )
+    };
+}
+
+/// Compares `actual` with the snapshot at `path`, or writes it there when
+/// `NEXA_UPDATE_SNAPSHOTS` is set.
+pub fn assert_snapshot(path: impl AsRef<Path>, actual: &str) {
+    let path = path.as_ref();
+    if std::env::var_os(UPDATE_ENV_VAR).is_some_and(|v| !v.is_empty() && v != "0") {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).expect("failed to create the snapshot directory");
+        }
+        std::fs::write(path, actual).expect("failed to write the snapshot");
+        return;
+    }
+
+    let expected = std::fs::read_to_string(path).unwrap_or_else(|_| {
+        panic!(
+            "no snapshot at {}; run with {UPDATE_ENV_VAR}=1 to create it\n\n{actual}",
+            path.display()
+        )
+    });
+    if expected != actual {
+        panic!(
+            "snapshot {} does not match; run with {UPDATE_ENV_VAR}=1 to update it\n\n{}",
+            path.display(),
+            line_diff(&expected, actual)
+        );
+    }
+}
+
+/// The lines of `expected` and `actual`, prefixed with `-` and `+` where
+/// they differ.
+fn line_diff(expected: &str, actual: &str) -> String {
+    let expected: Vec<&str> = expected.lines().collect();
+    let actual: Vec<&str> = actual.lines().collect();
+
+    // Longest common subsequence, filled from the end.
+    let mut lcs = vec![vec![0usize; actual.len() + 1]; expected.len() + 1];
+    for i in (0..expected.len()).rev() {
+        for j in (0..actual.len()).rev() {
+            lcs[i][j] = if expected[i] == actual[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut out = String::new();
+    let (mut i, mut j) = (0, 0);
+    while i < expected.len() || j < actual.len() {
+        if i < expected.len() && j < actual.len() && expected[i] == actual[j] {
+            let _ = writeln!(out, "  {}", expected[i]);
+            i += 1;
+            j += 1;
+        } else if j < actual.len() && (i == expected.len() || lcs[i][j + 1] >= lcs[i + 1][j]) {
+            let _ = writeln!(out, "+ {}", actual[j]);
+            j += 1;
+        } else {
+            let _ = writeln!(out, "- {}", expected[i]);
+            i += 1;
+        }
+    }
+    out
+}
+
+/// The runtime's virtual tree, one node per line, indented by depth.
+/// Components are shown as `<Name>` above what they rendered. Roots from
+/// `Runtime::mount_at` follow the main one, by name.
+pub fn tree_snapshot<S: Scheduler>(runtime: &Runtime<S>) -> String {
+    let mut out = String::new();
+    if let Some(root) = runtime.root_node {
+        write_node(runtime, root, 0, &mut out);
+    }
+    for (name, root) in &runtime.roots {
+        let _ = writeln!(out, "Root {name:?} in #{}", root.container);
+        write_node(runtime, root.node, 1, &mut out);
+    }
+    out
+}
+
+fn write_node<S: Scheduler>(runtime: &Runtime<S>, id: NodeId, depth: usize, out: &mut String) {
+    let Some(node) = runtime.arena.nodes.get(id) else {
+        let _ = writeln!(out, "{}<missing>", "  ".repeat(depth));
+        return;
+    };
+    let _ = write!(out, "{}", "  ".repeat(depth));
+    let children: Vec<NodeId> = match node {
+        VirtualNode::Element(el) => {
+            out.push_str(el.tag);
+            if let Some(ns) = el.namespace {
+                let _ = write!(out, " xmlns={ns:?}");
+            }
+            if let Some(key) = &el.key {
+                let _ = write!(out, " key={key:?}");
+            }
+            for prop in &el.props {
+                match &prop.value {
+                    AttributeValue::Text(text) => {
+                        let _ = write!(out, " {}={text:?}", prop.name);
+                    }
+                    AttributeValue::Bool(b) => {
+                        let _ = write!(out, " {}={b}", prop.name);
+                    }
+                    AttributeValue::Int(i) => {
+                        let _ = write!(out, " {}={i}", prop.name);
+                    }
+                    AttributeValue::Float(f) => {
+                        let _ = write!(out, " {}={f}", prop.name);
+                    }
+                    AttributeValue::None | AttributeValue::Listener => {}
+                }
+            }
+            for listener in &el.listeners {
+                let _ = write!(out, " @{}", listener.name.to_lowercase());
+            }
+            if el.node_ref.is_some() {
+                out.push_str(" ref");
+            }
+            el.children.to_vec()
+        }
+        VirtualNode::Text(text) => {
+            let _ = write!(out, "{:?}", text.text);
+            vec![]
+        }
+        VirtualNode::Fragment(frag) => {
+            out.push_str("<>");
+            frag.children.to_vec()
+        }
+        VirtualNode::Component(comp) => {
+            let _ = write!(out, "<{}>", comp.name);
+            comp.scope
+                .and_then(|scope| runtime.scopes.get(scope))
+                .and_then(|scope| scope.root_node)
+                .into_iter()
+                .collect()
+        }
+        VirtualNode::Suspense(susp) if susp.suspended => {
+            out.push_str("Suspense (fallback)");
+            vec![susp.fallback]
+        }
+        VirtualNode::Suspense(susp) => {
+            out.push_str("Suspense");
+            vec![susp.actual]
+        }
+        VirtualNode::ErrorBoundary(eb) => match &eb.caught {
+            Some(caught) => {
+                let _ = write!(out, "ErrorBoundary (caught {:?})", caught.error.message);
+                vec![caught.fallback]
+            }
+            None => {
+                out.push_str("ErrorBoundary");
+                vec![eb.content]
+            }
+        },
+        VirtualNode::Portal(portal) => {
+            let _ = write!(out, "Portal target={:?}", portal.target);
+            vec![portal.content]
+        }
+        VirtualNode::Placeholder => {
+            out.push_str("Placeholder");
+            vec![]
+        }
+    };
+    out.push('\n');
+    for child in children {
+        write_node(runtime, child, depth + 1, out);
+    }
+}
+
+/// The mutations, one per line. Ids are renumbered `#1`, `#2`, ... in the
+/// order they first appear, and templates `T1`, `T2`, ..., so the text
+/// doesn't depend on arena slots or template hashes. The root container
+/// is `root`.
+pub fn mutations_snapshot(mutations: &[Mutation]) -> String {
+    let mut names = Names::default();
+    let mut out = String::new();
+    for mutation in mutations {
+        let line = match mutation {
+            Mutation::AppendChildren { id, m } => {
+                format!("AppendChildren {} {}", names.id(*id), names.ids(m))
+            }
+            Mutation::AssignId { path, id } => {
+                format!("AssignId {path:?} -> {}", names.id(*id))
+            }
+            Mutation::CreateElement { tag, id } => {
+                format!("CreateElement {tag} -> {}", names.id(*id))
+            }
+            Mutation::CreateElementNs { tag, ns, id } => {
+                format!("CreateElementNs {tag} {ns:?} -> {}", names.id(*id))
+            }
+            Mutation::CreatePlaceholder { id } => {
+                format!("CreatePlaceholder -> {}", names.id(*id))
+            }
+            Mutation::CreateTextNode { text, id } => {
+                format!("CreateTextNode {text:?} -> {}", names.id(*id))
+            }
+            Mutation::HydrateText { path, value, id } => {
+                format!("HydrateText {path:?} {value:?} -> {}", names.id(*id))
+            }
+            Mutation::LoadTemplate { name, index, id } => {
+                let template = names.template(name);
+                format!("LoadTemplate {template}[{index}] -> {}", names.id(*id))
+            }
+            Mutation::RegisterTemplate { template } => {
+                let name = names.template(&template.name);
+                format!("RegisterTemplate {name} {}", template_text(template))
+            }
+            Mutation::ReplaceWith { id, m } => {
+                format!("ReplaceWith {} {}", names.id(*id), names.ids(m))
+            }
+            Mutation::ReplacePlaceholder { path, m } => {
+                format!("ReplacePlaceholder {path:?} {}", names.ids(m))
+            }
+            Mutation::InsertAfter { id, m } => {
+                format!("InsertAfter {} {}", names.id(*id), names.ids(m))
+            }
+            Mutation::InsertBefore { id, m } => {
+                format!("InsertBefore {} {}", names.id(*id), names.ids(m))
+            }
+            Mutation::SetAttribute {
+                name,
+                value,
+                id,
+                ns,
+            } => {
+                let ns = ns.as_ref().map_or(String::new(), |ns| format!(" {ns:?}"));
+                format!("SetAttribute {} {name}={value:?}{ns}", names.id(*id))
+            }
+            Mutation::RemoveAttribute { name, id } => {
+                format!("RemoveAttribute {} {name}", names.id(*id))
+            }
+            Mutation::SetText { value, id } => {
+                format!("SetText {} {value:?}", names.id(*id))
+            }
+            Mutation::NewEventListener { name, id } => {
+                format!("NewEventListener {} {name}", names.id(*id))
+            }
+            Mutation::RemoveEventListener { name, id } => {
+                format!("RemoveEventListener {} {name}", names.id(*id))
+            }
+            Mutation::Remove { id } => format!("Remove {}", names.id(*id)),
+            Mutation::PushRoot { id } => format!("PushRoot {}", names.id(*id)),
+            Mutation::LoadContainer { target, id } => {
+                format!("LoadContainer {target:?} -> {}", names.id(*id))
+            }
+            Mutation::LoadExisting { id } => format!("LoadExisting {}", names.id(*id)),
+        };
+        out.push_str(&line);
+        out.push('\n');
+    }
+    out
+}
+
+#[derive(Default)]
+struct Names {
+    ids: HashMap<u64, usize>,
+    templates: HashMap<String, usize>,
+}
+
+impl Names {
+    fn id(&mut self, id: u64) -> String {
+        if id == 0 {
+            return "root".to_string();
+        }
+        let next = self.ids.len() + 1;
+        format!("#{}", self.ids.entry(id).or_insert(next))
+    }
+
+    fn ids(&mut self, ids: &[u64]) -> String {
+        let ids: Vec<String> = ids.iter().map(|&id| self.id(id)).collect();
+        format!("[{}]", ids.join(", "))
+    }
+
+    fn template(&mut self, name: &str) -> String {
+        let next = self.templates.len() + 1;
+        format!(
+            "T{}",
+            self.templates.entry(name.to_string()).or_insert(next)
+        )
+    }
+}
+
+/// The template's roots as markup, with `{text}` for dynamic text and
+/// `{..}` for dynamic nodes and children.
+fn template_text(template: &Template) -> String {
+    let mut out = String::new();
+    for root in template.roots.iter() {
+        write_template_node(root, &mut out);
+    }
+    out
+}
+
+fn write_template_node(node: &TemplateNode, out: &mut String) {
+    match node {
+        TemplateNode::Element {
+            tag,
+            attrs,
+            children,
+            dynamic_children,
+            ..
+        } => {
+            let _ = write!(out, "<{tag}");
+            for attr in attrs.iter() {
+                let _ = write!(out, " {}={:?}", attr.name, attr.value);
+            }
+            out.push('>');
+            if *dynamic_children {
+                out.push_str("{..}");
+            }
+            for child in children.iter() {
+                write_template_node(child, out);
+            }
+            let _ = write!(out, "</{tag}>");
+        }
+        TemplateNode::Text { text } => out.push_str(text),
+        TemplateNode::DynamicText => out.push_str("{text}"),
+        TemplateNode::Dynamic => out.push_str("{..}"),
+    }
+}
@@ -0,0 +1,181 @@
+//! Async work that Suspense boundaries wait for.
+//!
+//! A `Resource` runs a future on the runtime's local executor. Reading it
+//! while the future is pending registers the read with the nearest Suspense
+//! boundary being created, which then shows its fallback and retries once the
+//! resource resolves.
+
+use futures::task::{ArcWake, waker};
+use nexa_signals::{Signal, SignalId};
+use std::cell::RefCell;
+use std::future::Future;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::task::{Context, Poll};
+
+/// The eventual result of a future, readable during render.
+///
+/// Create resources outside the components that read them (a parent, a
+/// prop, a global): a component's locals don't survive its re-renders, so a
+/// resource created in the reading component would restart every time.
+pub struct Resource<T> {
+    inner: Rc<ResourceInner<T>>,
+}
+
+struct ResourceInner<T> {
+    value: RefCell<Option<T>>,
+    ready: Signal<bool>,
+}
+
+impl<T: 'static> Resource<T> {
+    pub fn new(future: impl Future<Output = T> + 'static) -> Self {
+        let inner = Rc::new(ResourceInner {
+            value: RefCell::new(None),
+            ready: Signal::new(false),
+        });
+        let weak = Rc::downgrade(&inner);
+        spawn_local(async move {
+            let value = future.await;
+            if let Some(inner) = weak.upgrade() {
+                *inner.value.borrow_mut() = Some(value);
+                inner.ready.set(true);
+            }
+        });
+        Self { inner }
+    }
+
+    /// The value, or `None` while the future is pending. A pending read
+    /// suspends the nearest Suspense boundary.
+    pub fn read(&self) -> Option<T>
+    where
+        T: Clone,
+    {
+        if self.inner.ready.get() {
+            self.inner.value.borrow().clone()
+        } else {
+            suspend(self.inner.ready.id());
+            None
+        }
+    }
+
+    pub fn is_ready(&self) -> bool {
+        self.inner.ready.get()
+    }
+}
+
+impl<T> Clone for Resource<T> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+/// Resources are compared by identity, so they can be passed as props.
+impl<T> PartialEq for Resource<T> {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.inner, &other.inner)
+    }
+}
+
+thread_local! {
+    static PENDING: RefCell<Option<Vec<SignalId>>> = const { RefCell::new(None) };
+    static TASKS: RefCell<Vec<Task>> = const { RefCell::new(Vec::new()) };
+    static WAKE_HANDLER: RefCell<Option<Rc<dyn Fn()>>> = const { RefCell::new(None) };
+}
+
+fn suspend(resource: SignalId) {
+    PENDING.with(|p| {
+        if let Some(pending) = p.borrow_mut().as_mut() {
+            pending.push(resource);
+        }
+    });
+}
+
+/// Runs `f`, collecting the pending resources read inside it. Collections
+/// nest: reads under an inner boundary are not seen by the outer one.
+pub(crate) fn collect_pending<F, R>(f: F) -> (R, Vec<SignalId>)
+where
+    F: FnOnce() -> R,
+{
+    let outer = PENDING.with(|p| p.borrow_mut().replace(Vec::new()));
+    let res = f();
+    let pending = PENDING.with(|p| std::mem::replace(&mut *p.borrow_mut(), outer));
+    (res, pending.unwrap_or_default())
+}
+
+struct Task {
+    future: Pin<Box<dyn Future<Output = ()>>>,
+    woken: Arc<TaskWaker>,
+}
+
+struct TaskWaker {
+    woken: AtomicBool,
+}
+
+impl ArcWake for TaskWaker {
+    fn wake_by_ref(arc_self: &Arc<Self>) {
+        arc_self.woken.store(true, Ordering::Release);
+        // Wakes from other threads are picked up by the next poll.
+        let _ = WAKE_HANDLER.try_with(|h| {
+            if let Some(handler) = h.borrow().clone() {
+                handler();
+            }
+        });
+    }
+}
+
+/// Spawns a future on the current thread's executor. It is first polled by
+/// the next `Runtime::update`.
+pub fn spawn_local(future: impl Future<Output = ()> + 'static) {
+    let task = Task {
+        future: Box::pin(future),
+        woken: Arc::new(TaskWaker {
+            woken: AtomicBool::new(true),
+        }),
+    };
+    TASKS.with(|t| t.borrow_mut().push(task));
+    let _ = WAKE_HANDLER.try_with(|h| {
+        if let Some(handler) = h.borrow().clone() {
+            handler();
+        }
+    });
+}
+
+/// Polls every woken task once. Returns whether any task completed.
+pub fn poll_tasks() -> bool {
+    let tasks = TASKS.with(|t| std::mem::take(&mut *t.borrow_mut()));
+    let mut completed = false;
+    let mut remaining = Vec::with_capacity(tasks.len());
+    for mut task in tasks {
+        if !task.woken.woken.swap(false, Ordering::Acquire) {
+            remaining.push(task);
+            continue;
+        }
+        let waker = waker(task.woken.clone());
+        match task.future.as_mut().poll(&mut Context::from_waker(&waker)) {
+            Poll::Ready(()) => completed = true,
+            Poll::Pending => remaining.push(task),
+        }
+    }
+    // Tasks spawned while polling go after the ones that were already queued.
+    TASKS.with(|t| {
+        let mut tasks = t.borrow_mut();
+        remaining.append(&mut tasks);
+        *tasks = remaining;
+    });
+    completed
+}
+
+/// Whether any spawned task has yet to complete.
+pub fn has_pending_tasks() -> bool {
+    TASKS.with(|t| !t.borrow().is_empty())
+}
+
+/// Registers a callback for when a task is spawned or woken, so renderers
+/// can schedule an update instead of polling.
+pub fn set_wake_handler(handler: impl Fn() + 'static) {
+    WAKE_HANDLER.with(|h| *h.borrow_mut() = Some(Rc::new(handler)));
+}
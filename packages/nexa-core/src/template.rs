@@ -0,0 +1,43 @@
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+
+/// The static skeleton of an rsx element tree.
+///
+/// `rsx!` emits one template per element tree. The renderer receives it once
+/// through `Mutation::RegisterTemplate`, and every instance is then created
+/// with `Mutation::LoadTemplate` (a deep clone of the skeleton) followed by
+/// mutations for the dynamic parts only.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Template {
+    pub name: Cow<'static, str>,
+    pub roots: Cow<'static, [TemplateNode]>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum TemplateNode {
+    Element {
+        tag: Cow<'static, str>,
+        /// `None` is HTML. Unlike `Element::namespace` it is not inherited,
+        /// since the skeleton is built without its surroundings.
+        namespace: Option<Cow<'static, str>>,
+        /// Literal attributes. Dynamic attributes are set per instance.
+        attrs: Cow<'static, [TemplateAttribute]>,
+        children: Cow<'static, [TemplateNode]>,
+        /// The children vary between renders (loops, conditionals, fragments),
+        /// so they are left out of the skeleton and created per instance.
+        dynamic_children: bool,
+    },
+    Text {
+        text: Cow<'static, str>,
+    },
+    /// An empty text node, filled in by `Mutation::HydrateText`.
+    DynamicText,
+    /// A placeholder for a component, replaced once the component is created.
+    Dynamic,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TemplateAttribute {
+    pub name: Cow<'static, str>,
+    pub value: Cow<'static, str>,
+}
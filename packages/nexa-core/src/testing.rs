@@ -0,0 +1,794 @@
+//! A headless renderer for testing components natively.
+//!
+//! `TestDom` applies mutations to an in-memory tree the way a browser
+//! renderer would, so tests can query the result instead of inspecting raw
+//! mutations:
+//!
+//! ```ignore
+//! let mut runtime = Runtime::new(LocalScheduler::new());
+//! let mut dom = TestDom::new();
+//! runtime.mount("Counter", counter);
+//! dom.sync(&mut runtime);
+//!
+//! let button = dom.find_by_test_id("increment").unwrap();
+//! dom.click(&mut runtime, button);
+//! assert_eq!(dom.text_content(dom.find_by_tag("p").unwrap()), "Count: 1");
+//! ```
+//!
+//! `TestDom::with_html` starts from server-rendered markup instead, for
+//! testing `Runtime::hydrate`.
+
+use crate::events::{Event, EventData, MouseData};
+use crate::mutations::Mutation;
+use crate::renderer::MutationSink;
+use crate::runtime::Runtime;
+use crate::template::{Template, TemplateNode};
+use crate::vdom::tag_namespace;
+use nexa_signals::Scheduler;
+use std::collections::HashMap;
+
+/// A node in a `TestDom`. Detached nodes keep their handle but no longer
+/// show up in queries or serialization.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TestNodeId(usize);
+
+#[derive(Debug, Clone)]
+enum Kind {
+    Element {
+        tag: String,
+        namespace: Option<String>,
+        attributes: Vec<(String, String)>,
+        listeners: Vec<String>,
+    },
+    Text(String),
+    Comment(String),
+}
+
+#[derive(Debug, Clone)]
+struct Node {
+    kind: Kind,
+    parent: Option<TestNodeId>,
+    children: Vec<TestNodeId>,
+    /// The id the runtime gave this node, if any. Nodes cloned from a
+    /// template only get one when something needs to reach them later.
+    id: Option<u64>,
+}
+
+/// An in-memory DOM that applies every `Mutation`.
+#[derive(Debug)]
+pub struct TestDom {
+    nodes: Vec<Node>,
+    ids: HashMap<u64, TestNodeId>,
+    templates: HashMap<String, Template>,
+    /// Root of the most recently loaded template; template paths start here.
+    last_template: Option<TestNodeId>,
+    /// Portal targets outside the root, by their id attribute.
+    containers: Vec<(String, TestNodeId)>,
+    /// Paths start at existing markup rather than a template clone.
+    hydrating: bool,
+    mismatches: Vec<String>,
+}
+
+const ROOT: TestNodeId = TestNodeId(0);
+
+impl Default for TestDom {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TestDom {
+    /// An empty DOM whose root container has the runtime's container id 0.
+    pub fn new() -> Self {
+        let mut dom = Self {
+            nodes: Vec::new(),
+            ids: HashMap::new(),
+            templates: HashMap::new(),
+            last_template: None,
+            containers: Vec::new(),
+            hydrating: false,
+            mismatches: Vec::new(),
+        };
+        let root = dom.create(Kind::Element {
+            tag: "div".to_string(),
+            namespace: None,
+            attributes: Vec::new(),
+            listeners: Vec::new(),
+        });
+        dom.assign_id(root, 0);
+        dom
+    }
+
+    /// A DOM whose root container holds `html`: the output of `nexa-ssr`
+    /// for a runtime to hydrate, or a page with containers for `mount_at`.
+    pub fn with_html(html: &str) -> Self {
+        let mut dom = Self::new();
+        dom.parse_html(html);
+        dom
+    }
+
+    /// Where hydration found markup other than what the runtime expected,
+    /// one message per node, with its path.
+    pub fn hydration_mismatches(&self) -> &[String] {
+        &self.mismatches
+    }
+
+    /// Applies the runtime's pending mutations.
+    pub fn sync<S: Scheduler>(&mut self, runtime: &mut Runtime<S>) {
+        runtime.flush_into(self);
+    }
+
+    pub fn apply(&mut self, mutations: impl IntoIterator<Item = Mutation>) {
+        for mutation in mutations {
+            self.apply_one(mutation);
+        }
+    }
+
+    fn apply_one(&mut self, mutation: Mutation) {
+        match mutation {
+            Mutation::AppendChildren { id, m } => {
+                let parent = self.by_id(id);
+                for child in m {
+                    let child = self.by_id(child);
+                    self.detach(child);
+                    self.nodes[child.0].parent = Some(parent);
+                    self.nodes[parent.0].children.push(child);
+                }
+            }
+            Mutation::AssignId { path, id } => {
+                if let Some(node) = self.at_path(&path, "an element") {
+                    self.assign_id(node, id);
+                }
+            }
+            Mutation::CreateElement { tag, id } => self.create_element(tag, None, id),
+            Mutation::CreateElementNs { tag, ns, id } => self.create_element(tag, Some(ns), id),
+            Mutation::CreatePlaceholder { id } => {
+                let node = self.create(Kind::Comment("placeholder".to_string()));
+                self.assign_id(node, id);
+            }
+            Mutation::CreateTextNode { text, id } => {
+                let node = self.create(Kind::Text(text));
+                self.assign_id(node, id);
+            }
+            Mutation::HydrateText { path, value, id } => {
+                let Some(node) = self.at_path(&path, "text") else {
+                    return;
+                };
+                if self.hydrating
+                    && let Kind::Text(text) = &self.nodes[node.0].kind
+                    && *text != value
+                {
+                    self.mismatches.push(format!(
+                        "expected text {value:?} at {path:?}, found {text:?}"
+                    ));
+                }
+                self.nodes[node.0].kind = Kind::Text(value);
+                self.assign_id(node, id);
+            }
+            Mutation::LoadTemplate { name, index, id } => {
+                let root = self
+                    .templates
+                    .get(&name)
+                    .and_then(|t| t.roots.get(index))
+                    .cloned()
+                    .unwrap_or_else(|| panic!("template {name} root {index} was not registered"));
+                let node = self.build_template_node(&root);
+                self.assign_id(node, id);
+                self.last_template = Some(node);
+                self.hydrating = false;
+            }
+            Mutation::RegisterTemplate { template } => {
+                self.templates.insert(template.name.to_string(), template);
+            }
+            Mutation::ReplaceWith { id, m } => {
+                let old = self.by_id(id);
+                self.insert_at(old, 0, m);
+                self.detach(old);
+            }
+            Mutation::ReplacePlaceholder { path, m } => {
+                if let Some(old) = self.at_path(&path, "a comment") {
+                    self.insert_at(old, 0, m);
+                    self.detach(old);
+                }
+            }
+            Mutation::InsertAfter { id, m } => {
+                let anchor = self.by_id(id);
+                self.insert_at(anchor, 1, m);
+            }
+            Mutation::InsertBefore { id, m } => {
+                let anchor = self.by_id(id);
+                self.insert_at(anchor, 0, m);
+            }
+            Mutation::SetAttribute {
+                name, value, id, ..
+            } => {
+                let node = self.by_id(id);
+                let text = value.to_attribute_text(&name);
+                if let Kind::Element { attributes, .. } = &mut self.nodes[node.0].kind {
+                    attributes.retain(|(n, _)| *n != name);
+                    if let Some(text) = text {
+                        attributes.push((name, text));
+                    }
+                }
+            }
+            Mutation::RemoveAttribute { name, id } => {
+                let node = self.by_id(id);
+                if let Kind::Element { attributes, .. } = &mut self.nodes[node.0].kind {
+                    attributes.retain(|(n, _)| *n != name);
+                }
+            }
+            Mutation::SetText { value, id } => {
+                let node = self.by_id(id);
+                match &mut self.nodes[node.0].kind {
+                    Kind::Text(text) => *text = value,
+                    // Like `textContent`, replaces the element's children.
+                    _ => {
+                        for child in self.nodes[node.0].children.clone() {
+                            self.detach(child);
+                        }
+                        let text = self.create(Kind::Text(value));
+                        self.nodes[text.0].parent = Some(node);
+                        self.nodes[node.0].children.push(text);
+                    }
+                }
+            }
+            Mutation::NewEventListener { name, id } => {
+                let node = self.by_id(id);
+                if let Kind::Element { listeners, .. } = &mut self.nodes[node.0].kind
+                    && !listeners.contains(&name)
+                {
+                    listeners.push(name);
+                }
+            }
+            Mutation::RemoveEventListener { name, id } => {
+                let node = self.by_id(id);
+                if let Kind::Element { listeners, .. } = &mut self.nodes[node.0].kind {
+                    listeners.retain(|n| *n != name);
+                }
+            }
+            Mutation::Remove { id } => {
+                let node = self.by_id(id);
+                self.detach(node);
+            }
+            Mutation::PushRoot { .. } => {}
+            Mutation::LoadContainer { target, id } => {
+                let container = match self.find_by_attribute("id", &target) {
+                    Some(node) => node,
+                    // The host page is not part of the test, so stand one in.
+                    None => {
+                        let node = self.create(Kind::Element {
+                            tag: "div".to_string(),
+                            namespace: None,
+                            attributes: vec![("id".to_string(), target.clone())],
+                            listeners: Vec::new(),
+                        });
+                        self.containers.push((target, node));
+                        node
+                    }
+                };
+                self.assign_id(container, id);
+            }
+            Mutation::LoadExisting { id } => {
+                self.last_template = Some(self.by_id(id));
+                self.hydrating = true;
+            }
+        }
+    }
+
+    fn create(&mut self, kind: Kind) -> TestNodeId {
+        self.nodes.push(Node {
+            kind,
+            parent: None,
+            children: Vec::new(),
+            id: None,
+        });
+        TestNodeId(self.nodes.len() - 1)
+    }
+
+    fn create_element(&mut self, tag: String, namespace: Option<String>, id: u64) {
+        let node = self.create(Kind::Element {
+            tag,
+            namespace,
+            attributes: Vec::new(),
+            listeners: Vec::new(),
+        });
+        self.assign_id(node, id);
+    }
+
+    fn build_template_node(&mut self, template: &TemplateNode) -> TestNodeId {
+        let (kind, children) = match template {
+            TemplateNode::Element {
+                tag,
+                namespace,
+                attrs,
+                children,
+                ..
+            } => {
+                let kind = Kind::Element {
+                    tag: tag.to_string(),
+                    namespace: namespace.as_deref().map(str::to_string),
+                    attributes: attrs
+                        .iter()
+                        .map(|a| (a.name.to_string(), a.value.to_string()))
+                        .collect(),
+                    listeners: Vec::new(),
+                };
+                (kind, children.as_ref())
+            }
+            TemplateNode::Text { text } => (Kind::Text(text.to_string()), &[][..]),
+            TemplateNode::DynamicText => (Kind::Text(String::new()), &[][..]),
+            TemplateNode::Dynamic => (Kind::Comment("placeholder".to_string()), &[][..]),
+        };
+        let node = self.create(kind);
+        for child in children {
+            let child = self.build_template_node(child);
+            self.nodes[child.0].parent = Some(node);
+            self.nodes[node.0].children.push(child);
+        }
+        node
+    }
+
+    fn assign_id(&mut self, node: TestNodeId, id: u64) {
+        self.nodes[node.0].id = Some(id);
+        self.ids.insert(id, node);
+    }
+
+    fn by_id(&self, id: u64) -> TestNodeId {
+        *self
+            .ids
+            .get(&id)
+            .unwrap_or_else(|| panic!("mutation refers to unknown node {id}"))
+    }
+
+    /// The node at `path` from the last loaded template or existing node.
+    /// While hydrating, a missing node or one that isn't `expected` is
+    /// recorded as a mismatch and `None` returned.
+    fn at_path(&mut self, path: &[u8], expected: &str) -> Option<TestNodeId> {
+        let mut node = self
+            .last_template
+            .expect("template path without a loaded template");
+        for &index in path {
+            match self.nodes[node.0].children.get(index as usize) {
+                Some(&child) => node = child,
+                None if self.hydrating => {
+                    self.mismatches
+                        .push(format!("expected {expected} at {path:?}, found nothing"));
+                    return None;
+                }
+                None => panic!("template has no node at {path:?}"),
+            }
+        }
+        if self.hydrating {
+            let found = match &self.nodes[node.0].kind {
+                Kind::Element { .. } => "an element",
+                Kind::Text(_) => "text",
+                Kind::Comment(_) => "a comment",
+            };
+            if found != expected {
+                self.mismatches
+                    .push(format!("expected {expected} at {path:?}, found {found}"));
+                return None;
+            }
+        }
+        Some(node)
+    }
+
+    fn detach(&mut self, node: TestNodeId) {
+        if let Some(parent) = self.nodes[node.0].parent.take() {
+            self.nodes[parent.0].children.retain(|&c| c != node);
+        }
+    }
+
+    /// Inserts the nodes with ids `ids` next to `anchor`: before it for
+    /// offset 0, after it for offset 1.
+    fn insert_at(&mut self, anchor: TestNodeId, offset: usize, ids: Vec<u64>) {
+        let parent = self.nodes[anchor.0]
+            .parent
+            .expect("cannot insert next to a detached node");
+        let new: Vec<TestNodeId> = ids.into_iter().map(|id| self.by_id(id)).collect();
+        for &node in &new {
+            self.detach(node);
+            self.nodes[node.0].parent = Some(parent);
+        }
+        let siblings = &mut self.nodes[parent.0].children;
+        let index = siblings.iter().position(|&c| c == anchor).unwrap() + offset;
+        siblings.splice(index..index, new);
+    }
+
+    /// Parses `html` into the root container. Handles what `nexa-ssr`
+    /// writes: elements, quoted attributes, text, comments and the usual
+    /// entities. Closing tags of void elements are ignored, as browsers do.
+    fn parse_html(&mut self, html: &str) {
+        let mut open = vec![ROOT];
+        let mut rest = html;
+        while !rest.is_empty() {
+            let parent = *open.last().unwrap();
+            if let Some(comment) = rest.strip_prefix("<!--") {
+                let end = comment.find("-->").unwrap_or(comment.len());
+                let node = self.create(Kind::Comment(comment[..end].to_string()));
+                self.append(parent, node);
+                rest = comment.get(end + 3..).unwrap_or("");
+            } else if let Some(closing) = rest.strip_prefix("</") {
+                let end = closing.find('>').unwrap_or(closing.len());
+                let tag = closing[..end].trim();
+                if let Some(depth) = open.iter().rposition(|&n| self.tag(n) == Some(tag))
+                    && depth > 0
+                {
+                    open.truncate(depth);
+                }
+                rest = closing.get(end + 1..).unwrap_or("");
+            } else if let Some(tag) = rest.strip_prefix('<') {
+                let (node, self_closing, after) = self.parse_start_tag(tag, parent);
+                self.append(parent, node);
+                if !self_closing && !self.tag(node).is_some_and(is_void) {
+                    open.push(node);
+                }
+                rest = after;
+            } else {
+                let end = rest.find('<').unwrap_or(rest.len());
+                let node = self.create(Kind::Text(decode_entities(&rest[..end])));
+                self.append(parent, node);
+                rest = &rest[end..];
+            }
+        }
+    }
+
+    /// Parses a start tag, without its `<`, into a detached element.
+    /// Returns it, whether it closed itself, and the input after the tag.
+    fn parse_start_tag<'h>(
+        &mut self,
+        input: &'h str,
+        parent: TestNodeId,
+    ) -> (TestNodeId, bool, &'h str) {
+        let name_end = input
+            .find(|c: char| c.is_whitespace() || c == '>' || c == '/')
+            .unwrap_or(input.len());
+        let tag = input[..name_end].to_string();
+        let mut rest = &input[name_end..];
+        let mut attributes = Vec::new();
+        let mut self_closing = false;
+        loop {
+            rest = rest.trim_start();
+            if let Some(after) = rest.strip_prefix('>') {
+                rest = after;
+                break;
+            }
+            if let Some(after) = rest.strip_prefix("/>") {
+                self_closing = true;
+                rest = after;
+                break;
+            }
+            if rest.is_empty() {
+                break;
+            }
+            let name_end = rest
+                .find(|c: char| c.is_whitespace() || c == '=' || c == '>' || c == '/')
+                .unwrap_or(rest.len())
+                .max(1);
+            let name = rest[..name_end].to_string();
+            rest = &rest[name_end..];
+            let mut value = String::new();
+            if let Some(after) = rest.trim_start().strip_prefix('=') {
+                let after = after.trim_start();
+                let (raw, remaining) = match after.chars().next() {
+                    Some(quote @ ('"' | '\'')) => {
+                        let end = after[1..].find(quote).map_or(after.len(), |i| i + 1);
+                        (&after[1..end], after.get(end + 1..).unwrap_or(""))
+                    }
+                    _ => {
+                        let end = after
+                            .find(|c: char| c.is_whitespace() || c == '>')
+                            .unwrap_or(after.len());
+                        (&after[..end], &after[end..])
+                    }
+                };
+                value = decode_entities(raw);
+                rest = remaining;
+            }
+            attributes.push((name, value));
+        }
+
+        // Like a browser's parser: `svg` and `math` switch namespace, and
+        // `foreignObject` switches back to HTML for its children.
+        let namespace =
+            tag_namespace(&tag)
+                .map(str::to_string)
+                .or_else(|| match &self.nodes[parent.0].kind {
+                    Kind::Element { tag, .. } if tag == "foreignObject" => None,
+                    Kind::Element { namespace, .. } => namespace.clone(),
+                    _ => None,
+                });
+        let node = self.create(Kind::Element {
+            tag,
+            namespace,
+            attributes,
+            listeners: Vec::new(),
+        });
+        (node, self_closing, rest)
+    }
+
+    fn append(&mut self, parent: TestNodeId, child: TestNodeId) {
+        self.nodes[child.0].parent = Some(parent);
+        self.nodes[parent.0].children.push(child);
+    }
+
+    /// The root container the app is mounted into.
+    pub fn root(&self) -> TestNodeId {
+        ROOT
+    }
+
+    /// The container loaded for `Mutation::LoadContainer`: the element with
+    /// the id `target`, or a stand-in when the DOM has none.
+    pub fn container(&self, target: &str) -> Option<TestNodeId> {
+        self.containers
+            .iter()
+            .find(|(t, _)| t == target)
+            .map(|&(_, node)| node)
+            .or_else(|| self.find_by_attribute("id", target))
+    }
+
+    pub fn tag(&self, node: TestNodeId) -> Option<&str> {
+        match &self.nodes[node.0].kind {
+            Kind::Element { tag, .. } => Some(tag),
+            _ => None,
+        }
+    }
+
+    pub fn namespace(&self, node: TestNodeId) -> Option<&str> {
+        match &self.nodes[node.0].kind {
+            Kind::Element { namespace, .. } => namespace.as_deref(),
+            _ => None,
+        }
+    }
+
+    pub fn attribute(&self, node: TestNodeId, name: &str) -> Option<&str> {
+        match &self.nodes[node.0].kind {
+            Kind::Element { attributes, .. } => attributes
+                .iter()
+                .find(|(n, _)| n == name)
+                .map(|(_, v)| v.as_str()),
+            _ => None,
+        }
+    }
+
+    /// The names of the events the node listens to.
+    pub fn listeners(&self, node: TestNodeId) -> &[String] {
+        match &self.nodes[node.0].kind {
+            Kind::Element { listeners, .. } => listeners,
+            _ => &[],
+        }
+    }
+
+    pub fn parent(&self, node: TestNodeId) -> Option<TestNodeId> {
+        self.nodes[node.0].parent
+    }
+
+    pub fn children(&self, node: TestNodeId) -> &[TestNodeId] {
+        &self.nodes[node.0].children
+    }
+
+    /// The concatenated text of the node and its descendants.
+    pub fn text_content(&self, node: TestNodeId) -> String {
+        let mut out = String::new();
+        self.collect_text(node, &mut out);
+        out
+    }
+
+    fn collect_text(&self, node: TestNodeId, out: &mut String) {
+        match &self.nodes[node.0].kind {
+            Kind::Text(text) => out.push_str(text),
+            _ => {
+                for &child in &self.nodes[node.0].children {
+                    self.collect_text(child, out);
+                }
+            }
+        }
+    }
+
+    /// Every attached node, the root's tree first, then the portal targets',
+    /// each in document order.
+    fn attached(&self) -> Vec<TestNodeId> {
+        let mut out = Vec::new();
+        let mut stack: Vec<TestNodeId> = self
+            .containers
+            .iter()
+            .rev()
+            .map(|&(_, node)| node)
+            .chain([ROOT])
+            .collect();
+        while let Some(node) = stack.pop() {
+            out.push(node);
+            stack.extend(self.nodes[node.0].children.iter().rev());
+        }
+        out
+    }
+
+    fn find_all(&self, pred: impl Fn(TestNodeId) -> bool) -> Vec<TestNodeId> {
+        self.attached()
+            .into_iter()
+            .filter(|&node| self.tag(node).is_some() && pred(node))
+            .collect()
+    }
+
+    /// The innermost element whose text content is `text`.
+    pub fn find_by_text(&self, text: &str) -> Option<TestNodeId> {
+        let matches = self.find_all(|node| self.text_content(node) == text);
+        // In document order an element's descendants follow it, so the
+        // innermost match is the last of a run of nested ones.
+        matches
+            .iter()
+            .copied()
+            .find(|&node| !matches.iter().any(|&m| m != node && self.contains(node, m)))
+    }
+
+    pub fn find_by_tag(&self, tag: &str) -> Option<TestNodeId> {
+        self.find_all_by_tag(tag).into_iter().next()
+    }
+
+    pub fn find_all_by_tag(&self, tag: &str) -> Vec<TestNodeId> {
+        self.find_all(|node| self.tag(node) == Some(tag))
+    }
+
+    pub fn find_by_attribute(&self, name: &str, value: &str) -> Option<TestNodeId> {
+        self.find_all(|node| self.attribute(node, name) == Some(value))
+            .into_iter()
+            .next()
+    }
+
+    /// The element whose `data-testid` is `id`.
+    pub fn find_by_test_id(&self, id: &str) -> Option<TestNodeId> {
+        self.find_by_attribute("data-testid", id)
+    }
+
+    /// Whether `descendant` is inside `node`.
+    fn contains(&self, node: TestNodeId, descendant: TestNodeId) -> bool {
+        let mut current = self.nodes[descendant.0].parent;
+        while let Some(parent) = current {
+            if parent == node {
+                return true;
+            }
+            current = self.nodes[parent.0].parent;
+        }
+        false
+    }
+
+    /// Dispatches an event to `node` through `Runtime::handle_event`, then
+    /// runs the resulting update and applies its mutations. Nodes without an
+    /// id of their own dispatch from the nearest ancestor that has one, which
+    /// is where their listeners would be.
+    pub fn fire_event<S: Scheduler>(
+        &mut self,
+        runtime: &mut Runtime<S>,
+        node: TestNodeId,
+        name: &str,
+        data: EventData,
+    ) -> Event {
+        let mut current = Some(node);
+        let target = loop {
+            let node = current.expect("event target is not rendered by the runtime");
+            match self.nodes[node.0].id {
+                Some(id) if id != 0 => break id,
+                _ => current = self.nodes[node.0].parent,
+            }
+        };
+        let event = Event::new(data);
+        runtime.handle_event(target, name, event.clone());
+        runtime.update();
+        self.sync(runtime);
+        event
+    }
+
+    pub fn click<S: Scheduler>(&mut self, runtime: &mut Runtime<S>, node: TestNodeId) -> Event {
+        self.fire_event(
+            runtime,
+            node,
+            "click",
+            EventData::Mouse(MouseData::default()),
+        )
+    }
+
+    /// The root's contents as HTML on one line.
+    pub fn to_html(&self) -> String {
+        let mut out = String::new();
+        for &child in &self.nodes[ROOT.0].children {
+            self.write_html(child, None, &mut out);
+        }
+        out
+    }
+
+    /// The root's contents as indented HTML, one node per line.
+    pub fn to_pretty_html(&self) -> String {
+        let mut out = String::new();
+        for &child in &self.nodes[ROOT.0].children {
+            self.write_html(child, Some(0), &mut out);
+        }
+        out
+    }
+
+    /// Writes `node`; `depth` is `None` for compact output.
+    fn write_html(&self, node: TestNodeId, depth: Option<usize>, out: &mut String) {
+        let indent = depth.map_or(String::new(), |d| "  ".repeat(d));
+        let newline = if depth.is_some() { "\n" } else { "" };
+        match &self.nodes[node.0].kind {
+            Kind::Text(text) => {
+                out.push_str(&format!("{indent}{}{newline}", escape_html(text)));
+            }
+            Kind::Comment(text) => out.push_str(&format!("{indent}<!--{text}-->{newline}")),
+            Kind::Element {
+                tag, attributes, ..
+            } => {
+                out.push_str(&format!("{indent}<{tag}"));
+                for (name, value) in attributes {
+                    if value.is_empty() {
+                        out.push_str(&format!(" {name}"));
+                    } else {
+                        out.push_str(&format!(" {name}=\"{}\"", escape_html(value)));
+                    }
+                }
+                out.push('>');
+                if is_void(tag) {
+                    out.push_str(newline);
+                    return;
+                }
+                let children = &self.nodes[node.0].children;
+                if children.is_empty() {
+                    out.push_str(&format!("</{tag}>{newline}"));
+                    return;
+                }
+                out.push_str(newline);
+                for &child in children {
+                    self.write_html(child, depth.map(|d| d + 1), out);
+                }
+                out.push_str(&format!("{indent}</{tag}>{newline}"));
+            }
+        }
+    }
+}
+
+impl MutationSink for TestDom {
+    fn push(&mut self, mutation: Mutation) {
+        self.apply_one(mutation);
+    }
+}
+
+fn is_void(tag: &str) -> bool {
+    matches!(
+        tag,
+        "area"
+            | "base"
+            | "br"
+            | "col"
+            | "embed"
+            | "hr"
+            | "img"
+            | "input"
+            | "link"
+            | "meta"
+            | "source"
+            | "track"
+            | "wbr"
+    )
+}
+
+fn escape_html(input: &str) -> String {
+    let mut output = String::with_capacity(input.len());
+    for c in input.chars() {
+        match c {
+            '<' => output.push_str("&lt;"),
+            '>' => output.push_str("&gt;"),
+            '&' => output.push_str("&amp;"),
+            '"' => output.push_str("&quot;"),
+            _ => output.push(c),
+        }
+    }
+    output
+}
+
+fn decode_entities(input: &str) -> String {
+    input
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&")
+}
@@ -1,4 +1,5 @@
-use slotmap::{SlotMap, new_key_type};
+use crate::template::Template;
+use slotmap::{SecondaryMap, SlotMap, new_key_type};
 use smallvec::SmallVec;
 
 new_key_type! {
@@ -25,37 +26,79 @@ impl<T> GenericArena<T> {
     pub fn get_mut(&mut self, id: NodeId) -> Option<&mut T> {
         self.items.get_mut(id)
     }
+    pub fn remove(&mut self, id: NodeId) -> Option<T> {
+        self.items.remove(id)
+    }
+    pub fn contains(&self, id: NodeId) -> bool {
+        self.items.contains_key(id)
+    }
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
 }
 
 pub struct VDomArena {
     pub nodes: GenericArena<VirtualNode>,
-    pub metadata: GenericArena<NodeMetadata>,
+    /// Keyed by the node it describes, so it can't drift from `nodes`.
+    pub metadata: SecondaryMap<NodeId, NodeMetadata>,
 }
 
 #[derive(Default, Clone, Copy)]
 pub struct NodeMetadata {
     pub is_static: bool,
     pub render_count: u64,
+    /// Set by `rsx!` on the root of an element tree: the element and its
+    /// fixed descendants are created by cloning this template.
+    pub template: Option<&'static Template>,
 }
 
 impl VDomArena {
     pub fn new() -> Self {
         Self {
             nodes: GenericArena::new(),
-            metadata: GenericArena::new(),
+            metadata: SecondaryMap::new(),
         }
     }
 
     pub fn insert_with_metadata(&mut self, node: VirtualNode, metadata: NodeMetadata) -> NodeId {
         let id = self.nodes.insert(node);
-        // Ensure metadata arena stays in sync
-        self.metadata.items.insert_with_key(|_| metadata);
+        self.metadata.insert(id, metadata);
         id
     }
 
     pub fn insert(&mut self, node: VirtualNode) -> NodeId {
         self.insert_with_metadata(node, NodeMetadata::default())
     }
+
+    /// Frees a node along with its metadata. Children are not touched.
+    pub fn remove(&mut self, id: NodeId) -> Option<VirtualNode> {
+        self.metadata.remove(id);
+        self.nodes.remove(id)
+    }
+
+    /// The single node of `nodes`, or a fragment holding all of them.
+    pub fn insert_roots(&mut self, nodes: SmallVec<[NodeId; 4]>) -> NodeId {
+        if nodes.len() == 1 {
+            nodes[0]
+        } else {
+            self.insert(VirtualNode::Fragment(Fragment {
+                children: nodes,
+                parent: None,
+                key: None,
+            }))
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -65,10 +108,46 @@ pub enum VirtualNode {
     Fragment(Fragment),
     Component(Component),
     Suspense(Suspense),
+    ErrorBoundary(ErrorBoundary),
+    Portal(Portal),
     Placeholder,
 }
 
+impl VirtualNode {
+    /// The key that identifies this node among its siblings when diffing.
+    /// Placeholders carry no state and are never keyed.
+    pub fn key(&self) -> Option<&str> {
+        match self {
+            VirtualNode::Element(el) => el.key.as_deref(),
+            VirtualNode::Text(txt) => txt.key.as_deref(),
+            VirtualNode::Fragment(frag) => frag.key.as_deref(),
+            VirtualNode::Component(comp) => comp.key.as_deref(),
+            VirtualNode::Suspense(susp) => susp.key.as_deref(),
+            VirtualNode::ErrorBoundary(eb) => eb.key.as_deref(),
+            VirtualNode::Portal(portal) => portal.key.as_deref(),
+            VirtualNode::Placeholder => None,
+        }
+    }
+
+    pub fn parent(&self) -> Option<NodeId> {
+        match self {
+            VirtualNode::Element(el) => el.parent,
+            VirtualNode::Text(txt) => txt.parent,
+            VirtualNode::Fragment(frag) => frag.parent,
+            VirtualNode::Component(comp) => comp.parent,
+            VirtualNode::Suspense(susp) => susp.parent,
+            VirtualNode::ErrorBoundary(eb) => eb.parent,
+            VirtualNode::Portal(portal) => portal.parent,
+            VirtualNode::Placeholder => None,
+        }
+    }
+}
+
+use crate::error_boundary::{CaughtError, ErrorFallback, ResetHandle};
 use crate::events::Event;
+use crate::node_ref::NodeRef;
+use serde::{Deserialize, Serialize};
+use std::any::{Any, TypeId};
 use std::cell::RefCell;
 use std::fmt;
 use std::rc::Rc;
@@ -77,6 +156,9 @@ use std::rc::Rc;
 pub struct EventListener {
     pub name: &'static str,
     pub cb: Rc<RefCell<dyn FnMut(Event)>>,
+    /// Runs while the event travels down to its target, before any
+    /// bubbling listeners.
+    pub capture: bool,
 }
 
 impl fmt::Debug for EventListener {
@@ -84,6 +166,7 @@ impl fmt::Debug for EventListener {
         f.debug_struct("EventListener")
             .field("name", &self.name)
             .field("cb", &"FnMut")
+            .field("capture", &self.capture)
             .finish()
     }
 }
@@ -91,44 +174,386 @@ impl fmt::Debug for EventListener {
 #[derive(Debug, Clone)]
 pub struct Element {
     pub tag: &'static str,
+    /// The XML namespace, like `SVG_NAMESPACE`. `None` inherits the parent
+    /// element's namespace, and is HTML at the top or inside `foreignObject`.
+    pub namespace: Option<&'static str>,
     pub props: SmallVec<[Attribute; 4]>,
     pub listeners: SmallVec<[EventListener; 1]>,
     pub children: SmallVec<[NodeId; 4]>,
     pub parent: Option<NodeId>,
     pub key: Option<String>,
+    /// Filled in with this element's id while it is mounted.
+    pub node_ref: Option<NodeRef>,
+}
+
+pub const SVG_NAMESPACE: &str = "http://www.w3.org/2000/svg";
+pub const MATHML_NAMESPACE: &str = "http://www.w3.org/1998/Math/MathML";
+pub const XLINK_NAMESPACE: &str = "http://www.w3.org/1999/xlink";
+pub const XML_NAMESPACE: &str = "http://www.w3.org/XML/1998/namespace";
+pub const XMLNS_NAMESPACE: &str = "http://www.w3.org/2000/xmlns/";
+
+/// The namespace a tag opens on its own: `svg` and `math`.
+pub fn tag_namespace(tag: &str) -> Option<&'static str> {
+    match tag {
+        "svg" => Some(SVG_NAMESPACE),
+        "math" => Some(MATHML_NAMESPACE),
+        _ => None,
+    }
+}
+
+/// The namespace of a prefixed attribute name, like `xlink:href`.
+pub fn attribute_namespace(name: &str) -> Option<&'static str> {
+    match name.split_once(':') {
+        Some(("xlink", _)) => Some(XLINK_NAMESPACE),
+        Some(("xml", _)) => Some(XML_NAMESPACE),
+        Some(("xmlns", _)) => Some(XMLNS_NAMESPACE),
+        _ if name == "xmlns" => Some(XMLNS_NAMESPACE),
+        _ => None,
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct Attribute {
     pub name: &'static str,
-    pub value: String,
+    pub value: AttributeValue,
+}
+
+/// The value of an attribute, kept typed until a renderer applies it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum AttributeValue {
+    Text(String),
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    /// The attribute is absent.
+    None,
+    /// The attribute is bound to an event listener, which renderers register
+    /// through `Mutation::NewEventListener`; the value itself is not applied.
+    Listener,
+}
+
+/// Floats compare by their bits, so that the diff leaves a `NaN` alone and
+/// still writes `-0.0` over `0.0`, as the two render differently.
+impl PartialEq for AttributeValue {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::Text(a), Self::Text(b)) => a == b,
+            (Self::Bool(a), Self::Bool(b)) => a == b,
+            (Self::Int(a), Self::Int(b)) => a == b,
+            (Self::Float(a), Self::Float(b)) => a.to_bits() == b.to_bits(),
+            (Self::None, Self::None) | (Self::Listener, Self::Listener) => true,
+            _ => false,
+        }
+    }
+}
+
+impl AttributeValue {
+    /// The text to write for attribute `name`, or `None` if the attribute
+    /// should be absent. `false` removes boolean attributes, except those
+    /// whose values are the strings `"true"` and `"false"`, like `aria-*`.
+    pub fn to_attribute_text(&self, name: &str) -> Option<String> {
+        match self {
+            AttributeValue::Text(text) => Some(text.clone()),
+            AttributeValue::Bool(b) if has_boolean_text(name) => Some(b.to_string()),
+            AttributeValue::Bool(true) => Some(String::new()),
+            AttributeValue::Bool(false) | AttributeValue::None | AttributeValue::Listener => None,
+            AttributeValue::Int(i) => Some(i.to_string()),
+            AttributeValue::Float(f) => Some(f.to_string()),
+        }
+    }
+}
+
+fn has_boolean_text(name: &str) -> bool {
+    name.starts_with("aria-")
+        || name.starts_with("data-")
+        || matches!(name, "contenteditable" | "draggable" | "spellcheck")
+}
+
+/// Whether attribute `name` should be applied as a DOM property. For these
+/// the attribute only holds the initial state, while the property is what
+/// the user sees and edits.
+pub fn is_property(name: &str) -> bool {
+    matches!(
+        name,
+        "value" | "checked" | "selected" | "muted" | "indeterminate"
+    )
+}
+
+impl From<String> for AttributeValue {
+    fn from(value: String) -> Self {
+        AttributeValue::Text(value)
+    }
+}
+
+impl From<&str> for AttributeValue {
+    fn from(value: &str) -> Self {
+        AttributeValue::Text(value.to_string())
+    }
+}
+
+impl From<&String> for AttributeValue {
+    fn from(value: &String) -> Self {
+        AttributeValue::Text(value.clone())
+    }
+}
+
+impl From<std::borrow::Cow<'_, str>> for AttributeValue {
+    fn from(value: std::borrow::Cow<'_, str>) -> Self {
+        AttributeValue::Text(value.into_owned())
+    }
+}
+
+impl From<char> for AttributeValue {
+    fn from(value: char) -> Self {
+        AttributeValue::Text(value.to_string())
+    }
+}
+
+impl From<bool> for AttributeValue {
+    fn from(value: bool) -> Self {
+        AttributeValue::Bool(value)
+    }
+}
+
+macro_rules! int_attribute_values {
+    ($($ty:ty),*) => {
+        $(
+            impl From<$ty> for AttributeValue {
+                fn from(value: $ty) -> Self {
+                    AttributeValue::Int(value as i64)
+                }
+            }
+        )*
+    };
+}
+
+int_attribute_values!(i8, i16, i32, i64, isize, u8, u16, u32, u64, usize);
+
+impl From<f32> for AttributeValue {
+    fn from(value: f32) -> Self {
+        AttributeValue::Float(value as f64)
+    }
+}
+
+impl From<f64> for AttributeValue {
+    fn from(value: f64) -> Self {
+        AttributeValue::Float(value)
+    }
+}
+
+/// `None` leaves the attribute out.
+impl<T: Into<AttributeValue>> From<Option<T>> for AttributeValue {
+    fn from(value: Option<T>) -> Self {
+        value.map_or(AttributeValue::None, Into::into)
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct Text {
     pub text: String,
     pub parent: Option<NodeId>,
+    pub key: Option<String>,
 }
 
 #[derive(Debug, Clone)]
 pub struct Fragment {
     pub children: SmallVec<[NodeId; 4]>,
     pub parent: Option<NodeId>,
+    pub key: Option<String>,
 }
 
-#[derive(Debug, Clone)]
+/// Type-erased component props.
+pub trait AnyProps: Any {
+    fn as_any(&self) -> &dyn Any;
+}
+
+impl<P: 'static> AnyProps for P {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+/// A component's render function, taking its props type-erased.
+pub type RenderFn = Rc<dyn Fn(&dyn AnyProps) -> NodeId>;
+
+/// Compares a component's old and new props; see `Component::memo_by`.
+pub type PropsEq = Rc<dyn Fn(&dyn AnyProps, &dyn AnyProps) -> bool>;
+
+#[derive(Clone)]
 pub struct Component {
     pub name: &'static str,
-    pub render_fn: fn() -> NodeId,
+    pub render_fn: RenderFn,
+    pub props: Rc<dyn AnyProps>,
+    /// Identifies the component: two nodes are the same component if their
+    /// render functions have the same type.
+    pub type_id: TypeId,
+    /// Set by `new` and `memo_by`. Without it, as with `eager`, the
+    /// component re-renders whenever its parent does.
+    pub props_eq: Option<PropsEq>,
     pub scope: Option<crate::runtime::ScopeId>,
     pub parent: Option<NodeId>,
+    pub key: Option<String>,
+}
+
+impl Component {
+    /// A component that keeps its subtree while its props stay equal,
+    /// re-rendering only when they change.
+    pub fn new<P, F>(name: &'static str, render: F, props: P) -> Self
+    where
+        P: Clone + PartialEq + 'static,
+        F: Fn(P) -> NodeId + 'static,
+    {
+        Self::eager(name, render, props).memo_by(P::eq)
+    }
+
+    /// A component whose props can't be compared, like callbacks: it
+    /// re-renders whenever its parent does.
+    pub fn eager<P, F>(name: &'static str, render: F, props: P) -> Self
+    where
+        P: Clone + 'static,
+        F: Fn(P) -> NodeId + 'static,
+    {
+        let render_fn: RenderFn = Rc::new(move |props: &dyn AnyProps| {
+            let props = props
+                .as_any()
+                .downcast_ref::<P>()
+                .expect("Component rendered with props of the wrong type");
+            render(props.clone())
+        });
+        Self {
+            name,
+            render_fn,
+            props: Rc::new(props),
+            type_id: TypeId::of::<F>(),
+            props_eq: None,
+            scope: None,
+            parent: None,
+            key: None,
+        }
+    }
+
+    pub fn with_key(mut self, key: impl ToString) -> Self {
+        self.key = Some(key.to_string());
+        self
+    }
+
+    /// Compares props with `eq` rather than `PartialEq` when the parent
+    /// re-renders. While `eq(old, new)` holds, the component keeps its
+    /// subtree without rendering or diffing, e.g. to ignore props that
+    /// don't affect the output.
+    pub fn memo_by<P: 'static>(mut self, eq: impl Fn(&P, &P) -> bool + 'static) -> Self {
+        self.props_eq = Some(Rc::new(
+            move |old: &dyn AnyProps, new: &dyn AnyProps| match (
+                old.as_any().downcast_ref::<P>(),
+                new.as_any().downcast_ref::<P>(),
+            ) {
+                (Some(old), Some(new)) => eq(old, new),
+                _ => false,
+            },
+        ));
+        self
+    }
+}
+
+impl fmt::Debug for Component {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Component")
+            .field("name", &self.name)
+            .field("scope", &self.scope)
+            .field("parent", &self.parent)
+            .field("key", &self.key)
+            .finish()
+    }
+}
+
+/// Picks the constructor for `rsx!` components: `Component::new` when the
+/// props are `PartialEq`, `Component::eager` otherwise. Method resolution
+/// tries `ComparableProps` on `&ComponentFor<P>` before falling back to
+/// `EagerProps` on `&&ComponentFor<P>`.
+pub struct ComponentFor<P>(pub std::marker::PhantomData<P>);
+
+pub trait ComparableProps<P> {
+    fn component<F>(&self, name: &'static str, render: F, props: P) -> Component
+    where
+        F: Fn(P) -> NodeId + 'static;
 }
 
+impl<P: Clone + PartialEq + 'static> ComparableProps<P> for ComponentFor<P> {
+    fn component<F>(&self, name: &'static str, render: F, props: P) -> Component
+    where
+        F: Fn(P) -> NodeId + 'static,
+    {
+        Component::new(name, render, props)
+    }
+}
+
+pub trait EagerProps<P> {
+    fn component<F>(&self, name: &'static str, render: F, props: P) -> Component
+    where
+        F: Fn(P) -> NodeId + 'static;
+}
+
+impl<P: Clone + 'static> EagerProps<P> for &ComponentFor<P> {
+    fn component<F>(&self, name: &'static str, render: F, props: P) -> Component
+    where
+        F: Fn(P) -> NodeId + 'static,
+    {
+        Component::eager(name, render, props)
+    }
+}
+
+/// Shows `fallback` until nothing in `actual` is waiting on a `Resource`.
 #[derive(Debug, Clone)]
 pub struct Suspense {
     pub fallback: NodeId,
     pub actual: NodeId,
+    /// Set by the differ while the fallback is showing.
+    pub suspended: bool,
     pub parent: Option<NodeId>,
+    pub key: Option<String>,
+}
+
+/// Shows `content`, or a fallback built by `fallback` once something in
+/// `content` panics.
+#[derive(Clone)]
+pub struct ErrorBoundary {
+    pub content: NodeId,
+    pub fallback: ErrorFallback,
+    /// Set by the differ while the fallback is showing.
+    pub caught: Option<Box<Caught>>,
+    pub parent: Option<NodeId>,
+    pub key: Option<String>,
+}
+
+/// The state of a boundary that is showing its fallback.
+#[derive(Debug, Clone)]
+pub struct Caught {
+    pub error: CaughtError,
+    pub reset: ResetHandle,
+    /// The fallback component node.
+    pub fallback: NodeId,
+}
+
+impl fmt::Debug for ErrorBoundary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ErrorBoundary")
+            .field("content", &self.content)
+            .field("caught", &self.caught)
+            .field("parent", &self.parent)
+            .field("key", &self.key)
+            .finish()
+    }
+}
+
+/// Renders `content` into the host element whose id attribute is `target`
+/// rather than in place. Events from the content still bubble through the
+/// portal's ancestors.
+#[derive(Debug, Clone)]
+pub struct Portal {
+    pub target: String,
+    pub content: NodeId,
+    pub parent: Option<NodeId>,
+    pub key: Option<String>,
 }
 
 thread_local! {
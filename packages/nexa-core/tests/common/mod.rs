@@ -0,0 +1,40 @@
+//! Node factories for the tests that build trees by hand rather than with
+//! `rsx!`. Each test binary uses only some of them.
+#![allow(dead_code)]
+
+use nexa_core::{Element, EventListener, NodeId, Text, VirtualNode, get_active_arena};
+
+pub fn text(text: &str) -> NodeId {
+    get_active_arena(|arena| {
+        arena.insert(VirtualNode::Text(Text {
+            text: text.to_string(),
+            parent: None,
+            key: None,
+        }))
+    })
+}
+
+/// An element with just a tag and children, for struct update syntax.
+pub fn bare_element(tag: &'static str, children: Vec<NodeId>) -> Element {
+    Element {
+        tag,
+        namespace: None,
+        props: Default::default(),
+        listeners: Default::default(),
+        children: children.into(),
+        parent: None,
+        key: None,
+        node_ref: None,
+    }
+}
+
+pub fn insert(element: Element) -> NodeId {
+    get_active_arena(|arena| arena.insert(VirtualNode::Element(element)))
+}
+
+pub fn element(tag: &'static str, listeners: Vec<EventListener>, children: Vec<NodeId>) -> NodeId {
+    insert(Element {
+        listeners: listeners.into(),
+        ..bare_element(tag, children)
+    })
+}
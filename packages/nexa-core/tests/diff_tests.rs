@@ -0,0 +1,542 @@
+use nexa_core::{
+    Attribute, AttributeValue, Component, Element, Fragment, Mutation, NodeId, NodeMetadata,
+    Runtime, ScopeId, Template, TemplateAttribute, TemplateNode, Text, VDomArena, VirtualNode,
+    get_active_arena,
+};
+use nexa_scheduler::LocalScheduler;
+use nexa_signals::Signal;
+use std::borrow::Cow;
+use std::cell::RefCell;
+
+/// One list item: (key, tag, text).
+type Item = (Option<&'static str>, &'static str, &'static str);
+
+thread_local! {
+    static ITEMS: RefCell<Option<Signal<Vec<Item>>>> = const { RefCell::new(None) };
+}
+
+fn items() -> Signal<Vec<Item>> {
+    ITEMS.with(|i| i.borrow().clone().expect("ITEMS not initialised"))
+}
+
+fn list_app() -> NodeId {
+    let children = items()
+        .get()
+        .into_iter()
+        .map(|(key, tag, text)| {
+            get_active_arena(|arena| {
+                let text = arena.insert(VirtualNode::Text(Text {
+                    text: text.to_string(),
+                    parent: None,
+                    key: None,
+                }));
+                arena.insert(VirtualNode::Element(Element {
+                    tag,
+                    namespace: None,
+                    props: Default::default(),
+                    listeners: Default::default(),
+                    children: smallvec::smallvec![text],
+                    parent: None,
+                    key: key.map(str::to_string),
+                    node_ref: None,
+                }))
+            })
+        })
+        .collect();
+    get_active_arena(|arena| {
+        arena.insert(VirtualNode::Element(Element {
+            tag: "ul",
+            namespace: None,
+            props: Default::default(),
+            listeners: Default::default(),
+            children,
+            parent: None,
+            key: None,
+            node_ref: None,
+        }))
+    })
+}
+
+fn mounted(initial: Vec<Item>) -> Runtime<LocalScheduler> {
+    ITEMS.with(|i| *i.borrow_mut() = Some(Signal::new(initial)));
+    let mut runtime = Runtime::new(LocalScheduler::new());
+    runtime.mount("List", list_app);
+    runtime.drain_mutations();
+    runtime
+}
+
+fn rerender(runtime: &mut Runtime<LocalScheduler>, next: Vec<Item>) -> Vec<&'static str> {
+    items().set(next);
+    runtime.update();
+    runtime.verify_tree_integrity();
+    runtime.drain_mutations().iter().map(kind).collect()
+}
+
+fn kind(mutation: &Mutation) -> &'static str {
+    match mutation {
+        Mutation::AppendChildren { .. } => "AppendChildren",
+        Mutation::CreateElement { .. } => "CreateElement",
+        Mutation::CreateTextNode { .. } => "CreateTextNode",
+        Mutation::InsertBefore { .. } => "InsertBefore",
+        Mutation::Remove { .. } => "Remove",
+        Mutation::SetText { .. } => "SetText",
+        Mutation::SetAttribute { .. } => "SetAttribute",
+        Mutation::RegisterTemplate { .. } => "RegisterTemplate",
+        Mutation::LoadTemplate { .. } => "LoadTemplate",
+        Mutation::AssignId { .. } => "AssignId",
+        Mutation::HydrateText { .. } => "HydrateText",
+        Mutation::ReplaceWith { .. } => "ReplaceWith",
+        Mutation::PushRoot { .. } => "PushRoot",
+        _ => "Other",
+    }
+}
+
+#[test]
+fn test_unkeyed_children_patched_in_place() {
+    let mut runtime = mounted(vec![
+        (None, "li", "a"),
+        (None, "li", "b"),
+        (None, "li", "c"),
+    ]);
+
+    let mutations = rerender(
+        &mut runtime,
+        vec![(None, "li", "a"), (None, "li", "B"), (None, "li", "c")],
+    );
+    assert_eq!(mutations, vec!["SetText"]);
+
+    // Same content: nothing to do.
+    let mutations = rerender(
+        &mut runtime,
+        vec![(None, "li", "a"), (None, "li", "B"), (None, "li", "c")],
+    );
+    assert!(mutations.is_empty());
+}
+
+#[test]
+fn test_unkeyed_children_grow_and_shrink() {
+    let mut runtime = mounted(vec![(None, "li", "a"), (None, "li", "b")]);
+
+    let mutations = rerender(
+        &mut runtime,
+        vec![(None, "li", "a"), (None, "li", "b"), (None, "li", "c")],
+    );
+    assert_eq!(
+        mutations,
+        vec![
+            "CreateElement",
+            "CreateTextNode",
+            "AppendChildren",
+            "AppendChildren"
+        ]
+    );
+
+    let mutations = rerender(&mut runtime, vec![(None, "li", "a")]);
+    assert_eq!(mutations, vec!["Remove", "Remove"]);
+}
+
+#[test]
+fn test_unkeyed_child_of_different_type_is_replaced() {
+    let mut runtime = mounted(vec![(None, "li", "a"), (None, "li", "b")]);
+
+    let mutations = rerender(&mut runtime, vec![(None, "li", "a"), (None, "p", "b")]);
+    assert_eq!(
+        mutations,
+        vec![
+            "CreateElement",
+            "CreateTextNode",
+            "AppendChildren",
+            "ReplaceWith"
+        ]
+    );
+}
+
+#[test]
+fn test_mixed_keyed_and_unkeyed_children() {
+    let mut runtime = mounted(vec![
+        (None, "h1", "title"),
+        (Some("x"), "li", "x"),
+        (Some("y"), "li", "y"),
+        (None, "footer", "end"),
+    ]);
+
+    // Keyed rows swap, the unkeyed header and footer keep their position.
+    let mutations = rerender(
+        &mut runtime,
+        vec![
+            (None, "h1", "new title"),
+            (Some("y"), "li", "y"),
+            (Some("x"), "li", "x"),
+            (None, "footer", "end"),
+        ],
+    );
+    assert_eq!(mutations, vec!["SetText", "InsertBefore"]);
+
+    // A new keyed row does not steal an unkeyed slot.
+    let mutations = rerender(
+        &mut runtime,
+        vec![
+            (None, "h1", "new title"),
+            (Some("y"), "li", "y"),
+            (Some("z"), "li", "z"),
+            (Some("x"), "li", "x"),
+            (None, "footer", "end"),
+        ],
+    );
+    assert_eq!(
+        mutations,
+        vec![
+            "CreateElement",
+            "CreateTextNode",
+            "AppendChildren",
+            "InsertBefore"
+        ]
+    );
+}
+
+thread_local! {
+    static ROWS: RefCell<Option<Signal<Vec<u32>>>> = const { RefCell::new(None) };
+}
+
+fn rows() -> Signal<Vec<u32>> {
+    ROWS.with(|r| r.borrow().clone().expect("ROWS not initialised"))
+}
+
+fn row(id: u32) -> NodeId {
+    get_active_arena(|arena| {
+        arena.insert(VirtualNode::Text(Text {
+            text: format!("row {}", id),
+            parent: None,
+            key: None,
+        }))
+    })
+}
+
+fn table_app() -> NodeId {
+    let children = rows()
+        .get()
+        .into_iter()
+        .map(|id| {
+            let comp = Component::new("Row", row, id).with_key(id);
+            get_active_arena(|arena| arena.insert(VirtualNode::Component(comp)))
+        })
+        .collect();
+    get_active_arena(|arena| {
+        arena.insert(VirtualNode::Fragment(Fragment {
+            children,
+            parent: None,
+            key: None,
+        }))
+    })
+}
+
+/// The (key, scope) of each row, in document order.
+fn row_scopes(runtime: &Runtime<LocalScheduler>) -> Vec<(String, ScopeId)> {
+    let Some(VirtualNode::Component(root)) = runtime.arena.nodes.get(runtime.root_node.unwrap())
+    else {
+        panic!("root should be a component");
+    };
+    let list = runtime.scopes[root.scope.unwrap()].root_node.unwrap();
+    let Some(VirtualNode::Fragment(list)) = runtime.arena.nodes.get(list) else {
+        panic!("rows should be in a fragment");
+    };
+    list.children
+        .iter()
+        .map(|&id| match runtime.arena.nodes.get(id) {
+            Some(VirtualNode::Component(c)) => (c.key.clone().unwrap(), c.scope.unwrap()),
+            _ => panic!("expected a row component"),
+        })
+        .collect()
+}
+
+#[test]
+fn test_keyed_components_keep_scopes_when_sorted() {
+    ROWS.with(|r| *r.borrow_mut() = Some(Signal::new(vec![1, 2, 3])));
+
+    let mut runtime = Runtime::new(LocalScheduler::new());
+    runtime.mount("Table", table_app);
+    runtime.drain_mutations();
+    let before = row_scopes(&runtime);
+
+    rows().set(vec![3, 1, 2]);
+    runtime.update();
+    runtime.verify_tree_integrity();
+    let mutations: Vec<_> = runtime.drain_mutations().iter().map(kind).collect();
+    assert_eq!(mutations, vec!["InsertBefore"]);
+
+    let after = row_scopes(&runtime);
+    assert_eq!(
+        after,
+        vec![before[2].clone(), before[0].clone(), before[1].clone()]
+    );
+    assert_eq!(runtime.scopes.len(), 4);
+}
+
+/// `div { class: "card", h1 { "Title" }, p { class: {n}, {n} }, Badge {} }`
+static CARD: Template = Template {
+    name: Cow::Borrowed("card"),
+    roots: Cow::Borrowed(&[TemplateNode::Element {
+        tag: Cow::Borrowed("div"),
+        namespace: None,
+        attrs: Cow::Borrowed(&[TemplateAttribute {
+            name: Cow::Borrowed("class"),
+            value: Cow::Borrowed("card"),
+        }]),
+        children: Cow::Borrowed(&[
+            TemplateNode::Element {
+                tag: Cow::Borrowed("h1"),
+                namespace: None,
+                attrs: Cow::Borrowed(&[]),
+                children: Cow::Borrowed(&[TemplateNode::Text {
+                    text: Cow::Borrowed("Title"),
+                }]),
+                dynamic_children: false,
+            },
+            TemplateNode::Element {
+                tag: Cow::Borrowed("p"),
+                namespace: None,
+                attrs: Cow::Borrowed(&[]),
+                children: Cow::Borrowed(&[TemplateNode::DynamicText]),
+                dynamic_children: false,
+            },
+            TemplateNode::Dynamic,
+        ]),
+        dynamic_children: false,
+    }]),
+};
+
+fn element_node(
+    tag: &'static str,
+    props: Vec<Attribute>,
+    children: Vec<NodeId>,
+    template: Option<&'static Template>,
+) -> NodeId {
+    get_active_arena(|arena| {
+        arena.insert_with_metadata(
+            VirtualNode::Element(Element {
+                tag,
+                namespace: None,
+                props: props.into_iter().collect(),
+                listeners: Default::default(),
+                children: children.into_iter().collect(),
+                parent: None,
+                key: None,
+                node_ref: None,
+            }),
+            NodeMetadata {
+                template,
+                ..Default::default()
+            },
+        )
+    })
+}
+
+fn text_node(text: String) -> NodeId {
+    get_active_arena(|arena| {
+        arena.insert(VirtualNode::Text(Text {
+            text,
+            parent: None,
+            key: None,
+        }))
+    })
+}
+
+fn card(n: u32) -> NodeId {
+    let title = text_node("Title".to_string());
+    let h1 = element_node("h1", vec![], vec![title], None);
+    let value = text_node(n.to_string());
+    let class = Attribute {
+        name: "class",
+        value: format!("n{}", n).into(),
+    };
+    let p = element_node("p", vec![class], vec![value], None);
+    let badge = Component::new("Badge", |()| text_node("badge".to_string()), ());
+    let badge = get_active_arena(|arena| arena.insert(VirtualNode::Component(badge)));
+    let class = Attribute {
+        name: "class",
+        value: "card".into(),
+    };
+    element_node("div", vec![class], vec![h1, p, badge], Some(&CARD))
+}
+
+fn cards_app() -> NodeId {
+    let n = rows().get()[0];
+    let children = vec![card(n), card(n + 1)];
+    get_active_arena(|arena| {
+        arena.insert(VirtualNode::Fragment(Fragment {
+            children: children.into_iter().collect(),
+            parent: None,
+            key: None,
+        }))
+    })
+}
+
+#[test]
+fn test_template_instances() {
+    ROWS.with(|r| *r.borrow_mut() = Some(Signal::new(vec![1])));
+
+    let mut runtime = Runtime::new(LocalScheduler::new());
+    runtime.mount("Cards", cards_app);
+    let mutations = runtime.drain_mutations();
+
+    // Registered once, cloned twice. Only the dynamic parts are patched.
+    let instance = [
+        "LoadTemplate",
+        "AssignId",
+        "SetAttribute",
+        "HydrateText",
+        "AssignId",
+        "CreateTextNode",
+        "ReplaceWith",
+    ];
+    let mut expected = vec!["RegisterTemplate"];
+    expected.extend(instance);
+    expected.extend(instance);
+    expected.extend(["PushRoot", "AppendChildren"]);
+    assert_eq!(mutations.iter().map(kind).collect::<Vec<_>>(), expected);
+
+    let Mutation::AssignId { path, .. } = &mutations[2] else {
+        unreachable!()
+    };
+    assert_eq!(path, &vec![1]);
+    let Mutation::HydrateText { path, value, .. } = &mutations[4] else {
+        unreachable!()
+    };
+    assert_eq!((path, value.as_str()), (&vec![1, 0], "1"));
+
+    // Updates go to the ids assigned while hydrating.
+    rows().set(vec![5]);
+    runtime.update();
+    runtime.verify_tree_integrity();
+    let mutations: Vec<_> = runtime.drain_mutations().iter().map(kind).collect();
+    assert_eq!(
+        mutations,
+        vec!["SetAttribute", "SetText", "SetAttribute", "SetText"]
+    );
+}
+
+fn input_app() -> NodeId {
+    let n = rows().get()[0];
+    let props = vec![
+        Attribute {
+            name: "disabled",
+            value: n.is_multiple_of(2).into(),
+        },
+        Attribute {
+            name: "title",
+            value: (n > 1).then(|| format!("step {}", n)).into(),
+        },
+        Attribute {
+            name: "tabindex",
+            value: n.into(),
+        },
+    ];
+    element_node("input", props, vec![], None)
+}
+
+/// Attribute writes as (name, value), with removals as `None`.
+fn attribute_changes(mutations: Vec<Mutation>) -> Vec<(String, Option<AttributeValue>)> {
+    mutations
+        .into_iter()
+        .filter_map(|m| match m {
+            Mutation::SetAttribute { name, value, .. } => Some((name, Some(value))),
+            Mutation::RemoveAttribute { name, .. } => Some((name, None)),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn test_typed_attribute_values() {
+    ROWS.with(|r| *r.borrow_mut() = Some(Signal::new(vec![0])));
+
+    let mut runtime = Runtime::new(LocalScheduler::new());
+    runtime.mount("Input", input_app);
+    // A `None` value is never written.
+    assert_eq!(
+        attribute_changes(runtime.drain_mutations()),
+        vec![
+            ("disabled".to_string(), Some(AttributeValue::Bool(true))),
+            ("tabindex".to_string(), Some(AttributeValue::Int(0))),
+        ]
+    );
+
+    rows().set(vec![3]);
+    runtime.update();
+    assert_eq!(
+        attribute_changes(runtime.drain_mutations()),
+        vec![
+            ("disabled".to_string(), Some(AttributeValue::Bool(false))),
+            (
+                "title".to_string(),
+                Some(AttributeValue::Text("step 3".to_string()))
+            ),
+            ("tabindex".to_string(), Some(AttributeValue::Int(3))),
+        ]
+    );
+
+    // Going back to `None` removes the attribute.
+    rows().set(vec![1]);
+    runtime.update();
+    assert_eq!(
+        attribute_changes(runtime.drain_mutations()),
+        vec![
+            ("tabindex".to_string(), Some(AttributeValue::Int(1))),
+            ("title".to_string(), None)
+        ]
+    );
+}
+
+/// A progress bar whose value is unknown below row 10.
+fn progress_app() -> NodeId {
+    let n = rows().get()[0];
+    let value = Attribute {
+        name: "value",
+        value: if n < 10 { f64::NAN } else { n as f64 / 100.0 }.into(),
+    };
+    let label = text_node(format!("row {n}"));
+    element_node("progress", vec![value], vec![label], None)
+}
+
+#[test]
+fn test_nan_attribute_is_not_rewritten() {
+    ROWS.with(|r| *r.borrow_mut() = Some(Signal::new(vec![0])));
+
+    let mut runtime = Runtime::new(LocalScheduler::new());
+    runtime.mount("Progress", progress_app);
+    assert_eq!(attribute_changes(runtime.drain_mutations()).len(), 1);
+
+    rows().set(vec![1]);
+    runtime.update();
+    let mutations = runtime.drain_mutations();
+    assert!(!mutations.is_empty());
+    assert_eq!(attribute_changes(mutations), vec![]);
+
+    rows().set(vec![50]);
+    runtime.update();
+    assert_eq!(
+        attribute_changes(runtime.drain_mutations()),
+        vec![("value".to_string(), Some(AttributeValue::Float(0.5)))]
+    );
+}
+
+#[test]
+fn test_metadata_follows_its_node() {
+    let mut arena = VDomArena::new();
+    let static_meta = NodeMetadata {
+        is_static: true,
+        ..Default::default()
+    };
+    let first = arena.insert_with_metadata(VirtualNode::Placeholder, static_meta);
+    // A node inserted around `insert_with_metadata` has no metadata of its own.
+    let bare = arena.nodes.insert(VirtualNode::Placeholder);
+    let second = arena.insert_with_metadata(VirtualNode::Placeholder, static_meta);
+    assert!(arena.metadata.get(bare).is_none());
+    assert!(arena.metadata[second].is_static);
+
+    // A slot reused after a removal doesn't inherit the old metadata.
+    arena.remove(first);
+    let reused = arena.insert(VirtualNode::Placeholder);
+    assert!(arena.metadata.get(first).is_none());
+    assert!(!arena.metadata[reused].is_static);
+}
@@ -0,0 +1,233 @@
+use nexa_core::{
+    AttributeValue, DecodeError, Mutation, MutationDecoder, MutationEncoder, Template,
+    TemplateAttribute, TemplateNode,
+};
+use std::borrow::Cow;
+
+fn every_mutation() -> Vec<Mutation> {
+    vec![
+        Mutation::PushRoot { id: 1 },
+        Mutation::LoadContainer {
+            target: "modal-root".to_string(),
+            id: 7,
+        },
+        Mutation::LoadExisting { id: 0 },
+        Mutation::CreateElement {
+            tag: "div".to_string(),
+            id: 1,
+        },
+        Mutation::CreateTextNode {
+            text: "héllo".to_string(),
+            id: u64::MAX,
+        },
+        Mutation::CreateElementNs {
+            tag: "circle".to_string(),
+            ns: "http://www.w3.org/2000/svg".to_string(),
+            id: 8,
+        },
+        Mutation::CreatePlaceholder { id: 300 },
+        Mutation::AppendChildren {
+            id: 1,
+            m: vec![2, 3, 1 << 40],
+        },
+        Mutation::RegisterTemplate {
+            template: Template {
+                name: Cow::Borrowed("card"),
+                roots: Cow::Owned(vec![TemplateNode::Element {
+                    tag: Cow::Borrowed("div"),
+                    namespace: None,
+                    attrs: Cow::Owned(vec![TemplateAttribute {
+                        name: Cow::Borrowed("class"),
+                        value: Cow::Borrowed("card"),
+                    }]),
+                    children: Cow::Owned(vec![
+                        TemplateNode::Text {
+                            text: Cow::Borrowed("Title"),
+                        },
+                        TemplateNode::DynamicText,
+                        TemplateNode::Dynamic,
+                    ]),
+                    dynamic_children: false,
+                }]),
+            },
+        },
+        Mutation::LoadTemplate {
+            name: "card".to_string(),
+            index: 0,
+            id: 10,
+        },
+        Mutation::AssignId {
+            path: vec![0, 2],
+            id: 11,
+        },
+        Mutation::HydrateText {
+            path: vec![1],
+            value: "dynamic".to_string(),
+            id: 12,
+        },
+        Mutation::ReplaceWith { id: 11, m: vec![4] },
+        Mutation::ReplacePlaceholder {
+            path: vec![2],
+            m: vec![5, 6],
+        },
+        Mutation::InsertAfter { id: 4, m: vec![7] },
+        Mutation::InsertBefore { id: 4, m: vec![] },
+        Mutation::SetAttribute {
+            name: "class".to_string(),
+            value: "active".into(),
+            id: 1,
+            ns: None,
+        },
+        Mutation::SetAttribute {
+            name: "href".to_string(),
+            value: "#top".into(),
+            id: 1,
+            ns: Some("xlink".to_string()),
+        },
+        Mutation::SetAttribute {
+            name: "disabled".to_string(),
+            value: AttributeValue::Bool(false),
+            id: 1,
+            ns: None,
+        },
+        Mutation::SetAttribute {
+            name: "tabindex".to_string(),
+            value: AttributeValue::Int(-3),
+            id: 1,
+            ns: None,
+        },
+        Mutation::SetAttribute {
+            name: "value".to_string(),
+            value: AttributeValue::Float(0.5),
+            id: 1,
+            ns: None,
+        },
+        Mutation::SetAttribute {
+            name: "onclick".to_string(),
+            value: AttributeValue::Listener,
+            id: 1,
+            ns: None,
+        },
+        Mutation::SetAttribute {
+            name: "title".to_string(),
+            value: AttributeValue::None,
+            id: 1,
+            ns: None,
+        },
+        Mutation::RemoveAttribute {
+            name: "class".to_string(),
+            id: 1,
+        },
+        Mutation::SetText {
+            value: String::new(),
+            id: 2,
+        },
+        Mutation::NewEventListener {
+            name: "click".to_string(),
+            id: 1,
+        },
+        Mutation::RemoveEventListener {
+            name: "click".to_string(),
+            id: 1,
+        },
+        Mutation::Remove { id: 3 },
+    ]
+}
+
+#[test]
+fn test_round_trip_every_mutation() {
+    let mutations = every_mutation();
+    let bytes = MutationEncoder::new().encode_batch(&mutations);
+    let decoded = MutationDecoder::new().decode_batch(&bytes).unwrap();
+    assert_eq!(decoded, mutations);
+}
+
+#[test]
+fn test_names_are_interned_across_batches() {
+    let mut encoder = MutationEncoder::new();
+    let mut decoder = MutationDecoder::new();
+    // Arena ids, with the slot version in the high half.
+    let batch: Vec<Mutation> = (0..100)
+        .map(|index| Mutation::CreateElement {
+            tag: "section".to_string(),
+            id: 1 << 32 | index,
+        })
+        .collect();
+
+    let first = encoder.encode_batch(&batch);
+    let second = encoder.encode_batch(&batch);
+    // Count, then opcode + name reference + index + version per mutation;
+    // only the first batch spells out the tag.
+    assert_eq!(second.len(), 1 + 100 * 4);
+    assert_eq!(first.len(), second.len() + "section".len() + 1);
+
+    assert_eq!(decoder.decode_batch(&first).unwrap(), batch);
+    assert_eq!(decoder.decode_batch(&second).unwrap(), batch);
+
+    // A fresh decoder has never seen the tag.
+    assert_eq!(
+        MutationDecoder::new().decode_batch(&second),
+        Err(DecodeError::UnknownString(0))
+    );
+}
+
+#[test]
+fn test_malformed_input() {
+    let bytes = MutationEncoder::new().encode_batch(&every_mutation());
+    for len in 0..bytes.len() {
+        assert_eq!(
+            MutationDecoder::new().decode_batch(&bytes[..len]),
+            Err(DecodeError::UnexpectedEof),
+            "truncated at {}",
+            len
+        );
+    }
+
+    assert_eq!(
+        MutationDecoder::new().decode_batch(&[1, 200]),
+        Err(DecodeError::UnknownOpcode(200))
+    );
+    assert_eq!(
+        MutationDecoder::new().decode_batch(&[0xff; 11]),
+        Err(DecodeError::VarintOverflow)
+    );
+    assert_eq!(
+        MutationDecoder::new().decode_batch(&[1, 4, 1, 0xff, 0]),
+        Err(DecodeError::InvalidUtf8)
+    );
+    assert_eq!(
+        MutationDecoder::new().decode_batch(&[1, 17, 0x80, 0x80, 0x80, 0x80, 0x10, 1]),
+        Err(DecodeError::IdOverflow)
+    );
+}
+
+#[test]
+fn test_corrupt_batch_leaves_names_untouched() {
+    let mut encoder = MutationEncoder::new();
+    let mut decoder = MutationDecoder::new();
+    let first = vec![
+        Mutation::CreateElement {
+            tag: "div".to_string(),
+            id: 1 << 32 | 1,
+        },
+        Mutation::CreateElement {
+            tag: "span".to_string(),
+            id: 1 << 32 | 2,
+        },
+    ];
+    let second = vec![Mutation::CreateElement {
+        tag: "span".to_string(),
+        id: 1 << 32 | 3,
+    }];
+    let first_bytes = encoder.encode_batch(&first);
+    let second_bytes = encoder.encode_batch(&second);
+
+    // Cut inside the second name, after the first was read.
+    let corrupt = &first_bytes[..first_bytes.len() - 4];
+    assert_eq!(
+        decoder.decode_batch(corrupt),
+        Err(DecodeError::UnexpectedEof)
+    );
+    assert_eq!(decoder.decode_batch(&first_bytes).unwrap(), first);
+    assert_eq!(decoder.decode_batch(&second_bytes).unwrap(), second);
+}
@@ -0,0 +1,253 @@
+mod common;
+
+use common::{element, text};
+use nexa_core::{
+    CaughtError, Component, ErrorBoundary, Event, EventData, EventListener, Mutation, NodeId,
+    ResetHandle, Runtime, VirtualNode, get_active_arena, on_mount, on_unmount,
+};
+use nexa_scheduler::LocalScheduler;
+use nexa_signals::Signal;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+thread_local! {
+    static BROKEN: RefCell<Option<Signal<bool>>> = const { RefCell::new(None) };
+    static RESET: RefCell<Option<ResetHandle>> = const { RefCell::new(None) };
+}
+
+fn setup(broken: bool) {
+    BROKEN.with(|b| *b.borrow_mut() = Some(Signal::new(broken)));
+}
+
+fn broken() -> Signal<bool> {
+    BROKEN.with(|b| b.borrow().clone().unwrap())
+}
+
+fn boundary(content: NodeId) -> NodeId {
+    get_active_arena(|arena| {
+        arena.insert(VirtualNode::ErrorBoundary(ErrorBoundary {
+            content,
+            fallback: Rc::new(|error: CaughtError, reset: ResetHandle| {
+                RESET.with(|r| *r.borrow_mut() = Some(reset));
+                text(&format!("Failed: {error}"))
+            }),
+            caught: None,
+            parent: None,
+            key: None,
+        }))
+    })
+}
+
+fn component(name: &'static str, render: fn(()) -> NodeId) -> NodeId {
+    get_active_arena(|arena| arena.insert(VirtualNode::Component(Component::new(name, render, ()))))
+}
+
+/// Panics while `BROKEN` is set.
+fn widget(_: ()) -> NodeId {
+    if broken().get() {
+        panic!("widget exploded");
+    }
+    text("Widget")
+}
+
+fn widget_app() -> NodeId {
+    element(
+        "div",
+        vec![],
+        vec![text("Header"), boundary(component("Widget", widget))],
+    )
+}
+
+fn created_text(mutations: &[Mutation]) -> Vec<String> {
+    mutations
+        .iter()
+        .filter_map(|m| match m {
+            Mutation::CreateTextNode { text, .. } => Some(text.clone()),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Whether `mutations` take node `id` out of the document.
+fn removes(mutations: &[Mutation], id: u64) -> bool {
+    mutations.iter().any(|m| match m {
+        Mutation::Remove { id: removed } | Mutation::ReplaceWith { id: removed, .. } => {
+            *removed == id
+        }
+        _ => false,
+    })
+}
+
+fn text_id(mutations: &[Mutation], wanted: &str) -> u64 {
+    mutations
+        .iter()
+        .find_map(|m| match m {
+            Mutation::CreateTextNode { text, id } if text == wanted => Some(*id),
+            _ => None,
+        })
+        .unwrap()
+}
+
+#[test]
+fn test_render_panic_shows_fallback() {
+    setup(true);
+    let mut runtime = Runtime::new(LocalScheduler::new());
+    runtime.mount("App", widget_app);
+
+    let mutations = runtime.drain_mutations();
+    assert_eq!(
+        created_text(&mutations),
+        vec!["Header", "Failed: widget exploded"]
+    );
+    // The failed widget's scope was rolled back; the fallback has its own.
+    let names: Vec<_> = runtime.scopes.values().map(|s| s.name.clone()).collect();
+    assert_eq!(names.len(), 2);
+    assert!(names.contains(&"ErrorFallback".to_string()));
+}
+
+#[test]
+fn test_update_panic_replaces_content() {
+    setup(false);
+    let mut runtime = Runtime::new(LocalScheduler::new());
+    runtime.mount("App", widget_app);
+    let widget_text = text_id(&runtime.drain_mutations(), "Widget");
+
+    broken().set(true);
+    runtime.update();
+
+    let mutations = runtime.drain_mutations();
+    assert_eq!(created_text(&mutations), vec!["Failed: widget exploded"]);
+    let fallback = text_id(&mutations, "Failed: widget exploded");
+    assert!(mutations.contains(&Mutation::ReplaceWith {
+        id: widget_text,
+        m: vec![fallback],
+    }));
+    assert!(!runtime.scopes.values().any(|s| s.name == "Widget"));
+}
+
+#[test]
+fn test_reset_retries_content() {
+    setup(true);
+    let mut runtime = Runtime::new(LocalScheduler::new());
+    runtime.mount("App", widget_app);
+    let first_fallback = text_id(&runtime.drain_mutations(), "Failed: widget exploded");
+
+    // Retrying while the widget is still broken keeps a fallback up.
+    RESET.with(|r| r.borrow().clone().unwrap()).reset();
+    runtime.update();
+    let mutations = runtime.drain_mutations();
+    assert!(created_text(&mutations).is_empty());
+    assert!(mutations.is_empty());
+
+    broken().set(false);
+    RESET.with(|r| r.borrow().clone().unwrap()).reset();
+    runtime.update();
+    let mutations = runtime.drain_mutations();
+    assert_eq!(created_text(&mutations), vec!["Widget"]);
+    assert!(removes(&mutations, first_fallback));
+    assert!(runtime.scopes.values().any(|s| s.name == "Widget"));
+    assert!(!runtime.scopes.values().any(|s| s.name == "ErrorFallback"));
+}
+
+fn clicker(_: ()) -> NodeId {
+    let listener = EventListener {
+        name: "click",
+        capture: false,
+        cb: Rc::new(RefCell::new(|_: Event| panic!("handler exploded"))),
+    };
+    element("button", vec![listener], vec![text("Click")])
+}
+
+fn clicker_app() -> NodeId {
+    element("div", vec![], vec![boundary(component("Clicker", clicker))])
+}
+
+#[test]
+fn test_event_handler_panic_shows_fallback() {
+    let mut runtime = Runtime::new(LocalScheduler::new());
+    runtime.mount("App", clicker_app);
+    let button = runtime
+        .drain_mutations()
+        .into_iter()
+        .find_map(|m| match m {
+            Mutation::CreateElement { tag, id } if tag == "button" => Some(id),
+            _ => None,
+        })
+        .unwrap();
+
+    runtime.handle_event(button, "click", Event::new(EventData::Unknown));
+
+    let mutations = runtime.drain_mutations();
+    assert_eq!(created_text(&mutations), vec!["Failed: handler exploded"]);
+    assert!(removes(&mutations, button));
+}
+
+fn mounting(_: ()) -> NodeId {
+    on_mount(|| panic!("mount hook exploded"));
+    text("Mounting")
+}
+
+fn mounting_app() -> NodeId {
+    element(
+        "div",
+        vec![],
+        vec![boundary(component("Mounting", mounting))],
+    )
+}
+
+#[test]
+fn test_mount_hook_panic_shows_fallback() {
+    let mut runtime = Runtime::new(LocalScheduler::new());
+    runtime.mount("App", mounting_app);
+
+    let mutations = runtime.drain_mutations();
+    let mounted = text_id(&mutations, "Mounting");
+    assert!(created_text(&mutations).contains(&"Failed: mount hook exploded".to_string()));
+    assert!(removes(&mutations, mounted));
+}
+
+fn leaving(_: ()) -> NodeId {
+    on_unmount(|| panic!("unmount hook exploded"));
+    text("Leaving")
+}
+
+/// Drops its `Leaving` child once `BROKEN` is set.
+fn panel(_: ()) -> NodeId {
+    let mut children = vec![text("Panel")];
+    if !broken().get() {
+        children.push(component("Leaving", leaving));
+    }
+    element("div", vec![], children)
+}
+
+fn panel_app() -> NodeId {
+    element("div", vec![], vec![boundary(component("Panel", panel))])
+}
+
+#[test]
+fn test_unmount_hook_panic_shows_fallback() {
+    setup(false);
+    let mut runtime = Runtime::new(LocalScheduler::new());
+    runtime.mount("App", panel_app);
+    runtime.drain_mutations();
+
+    broken().set(true);
+    runtime.update();
+
+    let mutations = runtime.drain_mutations();
+    assert_eq!(
+        created_text(&mutations),
+        vec!["Failed: unmount hook exploded"]
+    );
+    let names: Vec<_> = runtime.scopes.values().map(|s| s.name.as_str()).collect();
+    assert!(!names.contains(&"Panel"));
+    assert!(!names.contains(&"Leaving"));
+}
+
+#[test]
+#[should_panic(expected = "uncaught error in component: widget exploded")]
+fn test_error_without_boundary_panics() {
+    setup(true);
+    let mut runtime = Runtime::new(LocalScheduler::new());
+    runtime.mount("App", || component("Widget", widget));
+}
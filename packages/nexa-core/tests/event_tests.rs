@@ -0,0 +1,214 @@
+mod common;
+
+use common::element;
+use nexa_core::{
+    Event, EventData, EventListener, EventPhase, KeyboardData, MouseData, Mutation, NodeId, Runtime,
+};
+use nexa_scheduler::LocalScheduler;
+use nexa_signals::Signal;
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::rc::Rc;
+
+/// (listener label, phase, target, current target)
+type Record = (&'static str, EventPhase, u64, u64);
+
+thread_local! {
+    static LOG: RefCell<Vec<Record>> = const { RefCell::new(Vec::new()) };
+    static LAST_DATA: RefCell<Option<EventData>> = const { RefCell::new(None) };
+    static STOP_AT: Cell<Option<&'static str>> = const { Cell::new(None) };
+    static PREVENT_AT: Cell<Option<&'static str>> = const { Cell::new(None) };
+    static KEYBOARD: RefCell<Option<Signal<bool>>> = const { RefCell::new(None) };
+}
+
+fn listener(name: &'static str, capture: bool, label: &'static str) -> EventListener {
+    EventListener {
+        name,
+        capture,
+        cb: Rc::new(RefCell::new(move |e: Event| {
+            LOG.with(|l| {
+                l.borrow_mut()
+                    .push((label, e.phase(), e.target(), e.current_target()))
+            });
+            LAST_DATA.with(|d| *d.borrow_mut() = Some(e.data().clone()));
+            if STOP_AT.with(|s| s.get()) == Some(label) {
+                e.stop_propagation();
+            }
+            if PREVENT_AT.with(|p| p.get()) == Some(label) {
+                e.prevent_default();
+            }
+        })),
+    }
+}
+
+/// div > section > button, with listeners at every level.
+fn nested_app() -> NodeId {
+    let button = element(
+        "button",
+        vec![
+            listener("click", false, "button"),
+            listener("click", true, "button capture"),
+        ],
+        vec![],
+    );
+    let section = element(
+        "section",
+        vec![
+            listener("click", false, "section"),
+            listener("focus", false, "section"),
+        ],
+        vec![button],
+    );
+    element(
+        "div",
+        vec![
+            listener("click", true, "div capture"),
+            listener("click", false, "div"),
+            listener("focus", true, "div capture"),
+            listener("focus", false, "div"),
+        ],
+        vec![section],
+    )
+}
+
+fn mount(root: fn() -> NodeId) -> (Runtime<LocalScheduler>, HashMap<String, u64>) {
+    LOG.with(|l| l.borrow_mut().clear());
+    STOP_AT.with(|s| s.set(None));
+    PREVENT_AT.with(|p| p.set(None));
+
+    let mut runtime = Runtime::new(LocalScheduler::new());
+    runtime.mount("App", root);
+    let ids = runtime
+        .drain_mutations()
+        .into_iter()
+        .filter_map(|m| match m {
+            Mutation::CreateElement { tag, id } => Some((tag, id)),
+            _ => None,
+        })
+        .collect();
+    (runtime, ids)
+}
+
+fn take_log() -> Vec<Record> {
+    LOG.with(|l| std::mem::take(&mut *l.borrow_mut()))
+}
+
+fn labels(log: &[Record]) -> Vec<&'static str> {
+    log.iter().map(|r| r.0).collect()
+}
+
+fn click() -> Event {
+    Event::new(EventData::Mouse(MouseData {
+        client_x: 4.0,
+        client_y: 2.0,
+        ..Default::default()
+    }))
+}
+
+#[test]
+fn test_capture_then_bubble() {
+    let (mut runtime, ids) = mount(nested_app);
+    let button = ids["button"];
+
+    runtime.handle_event(button, "click", click());
+
+    assert_eq!(
+        take_log(),
+        vec![
+            ("div capture", EventPhase::Capturing, button, ids["div"]),
+            ("button capture", EventPhase::AtTarget, button, button),
+            ("button", EventPhase::AtTarget, button, button),
+            ("section", EventPhase::Bubbling, button, ids["section"]),
+            ("div", EventPhase::Bubbling, button, ids["div"]),
+        ]
+    );
+    let data = LAST_DATA.with(|d| d.borrow_mut().take());
+    assert!(matches!(data, Some(EventData::Mouse(m)) if m.client_x == 4.0));
+}
+
+#[test]
+fn test_stop_propagation() {
+    let (mut runtime, ids) = mount(nested_app);
+
+    STOP_AT.with(|s| s.set(Some("section")));
+    runtime.handle_event(ids["button"], "click", click());
+    assert_eq!(
+        labels(&take_log()),
+        vec!["div capture", "button capture", "button", "section"]
+    );
+
+    // Stopping during capture keeps the event from reaching the target.
+    STOP_AT.with(|s| s.set(Some("div capture")));
+    let event = click();
+    runtime.handle_event(ids["button"], "click", event.clone());
+    assert_eq!(labels(&take_log()), vec!["div capture"]);
+    assert!(event.propagation_stopped());
+}
+
+#[test]
+fn test_non_bubbling_events_only_capture() {
+    let (mut runtime, ids) = mount(nested_app);
+
+    runtime.handle_event(
+        ids["section"],
+        "focus",
+        Event::new(EventData::Focus(Default::default())),
+    );
+
+    assert_eq!(labels(&take_log()), vec!["div capture", "section"]);
+}
+
+#[test]
+fn test_prevent_default_is_visible_to_the_renderer() {
+    let (mut runtime, ids) = mount(nested_app);
+
+    let event = click();
+    runtime.handle_event(ids["button"], "click", event.clone());
+    assert!(!event.default_prevented());
+
+    PREVENT_AT.with(|p| p.set(Some("section")));
+    let event = click();
+    runtime.handle_event(ids["button"], "click", event.clone());
+    assert!(event.default_prevented());
+    // Preventing the default action doesn't stop propagation.
+    assert_eq!(labels(&take_log()).last(), Some(&"div"));
+}
+
+fn switching_app() -> NodeId {
+    let keyboard = KEYBOARD.with(|k| k.borrow().clone().unwrap()).get();
+    let listeners = if keyboard {
+        vec![listener("keydown", false, "input")]
+    } else {
+        vec![listener("click", false, "input")]
+    };
+    element("input", listeners, vec![])
+}
+
+#[test]
+fn test_listener_changes_are_diffed() {
+    KEYBOARD.with(|k| *k.borrow_mut() = Some(Signal::new(false)));
+    let (mut runtime, ids) = mount(switching_app);
+    let input = ids["input"];
+
+    KEYBOARD.with(|k| k.borrow().clone().unwrap()).set(true);
+    runtime.update();
+    let mutations = runtime.drain_mutations();
+    assert!(mutations.contains(&Mutation::NewEventListener {
+        name: "keydown".to_string(),
+        id: input,
+    }));
+    assert!(mutations.contains(&Mutation::RemoveEventListener {
+        name: "click".to_string(),
+        id: input,
+    }));
+
+    let keydown = Event::new(EventData::Keyboard(KeyboardData {
+        key: "Enter".to_string(),
+        ..Default::default()
+    }));
+    runtime.handle_event(input, "click", click());
+    runtime.handle_event(input, "keydown", keydown);
+    assert_eq!(labels(&take_log()), vec!["input"]);
+    let data = LAST_DATA.with(|d| d.borrow_mut().take());
+    assert!(matches!(data, Some(EventData::Keyboard(k)) if k.key == "Enter"));
+}
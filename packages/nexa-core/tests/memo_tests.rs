@@ -0,0 +1,226 @@
+use nexa_core::testing::TestDom;
+use nexa_core::{Component, Fragment, NodeId, Runtime, VirtualNode, get_active_arena};
+use nexa_rsx::rsx;
+use nexa_scheduler::LocalScheduler;
+use nexa_signals::Signal;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+/// `(id, label)` per row.
+type Rows = Vec<(u32, &'static str)>;
+
+thread_local! {
+    static TITLE: RefCell<Option<Signal<&'static str>>> = const { RefCell::new(None) };
+    static ROWS: RefCell<Option<Signal<Rows>>> = const { RefCell::new(None) };
+    static SELECTED: RefCell<Option<Signal<u32>>> = const { RefCell::new(None) };
+}
+
+fn setup() {
+    TITLE.with(|t| *t.borrow_mut() = Some(Signal::new("Rows")));
+    ROWS.with(|r| *r.borrow_mut() = Some(Signal::new(vec![(1, "one"), (2, "two"), (3, "three")])));
+    SELECTED.with(|s| *s.borrow_mut() = Some(Signal::new(0)));
+}
+
+fn title() -> Signal<&'static str> {
+    TITLE.with(|t| t.borrow().clone().unwrap())
+}
+
+fn rows() -> Signal<Rows> {
+    ROWS.with(|r| r.borrow().clone().unwrap())
+}
+
+fn selected() -> Signal<u32> {
+    SELECTED.with(|s| s.borrow().clone().unwrap())
+}
+
+fn table() -> NodeId {
+    let title = title().get();
+    let rows = rows().get();
+    rsx! {
+        section {
+            h1 { {title.to_string()} }
+            ul {
+                for (id, label) in rows {
+                    Row { key: id, id: id, label: label }
+                }
+            }
+        }
+    }
+    .pop()
+    .unwrap()
+}
+
+/// Rows keyed on `id` alone, so relabelling doesn't re-render them.
+fn stale_table() -> NodeId {
+    let title = title().get();
+    let rows = rows().get();
+    rsx! {
+        ul {
+            "data-title": title,
+            for (id, label) in rows {
+                Row { key: id, id: id, label: label, memo: |old, new| old.id == new.id }
+            }
+        }
+    }
+    .pop()
+    .unwrap()
+}
+
+/// Rows that re-render with their parent whatever their props.
+fn eager_table() -> NodeId {
+    // Subscribes the table, though it doesn't show the title.
+    title().get();
+    let children = rows()
+        .get()
+        .into_iter()
+        .map(|(id, label)| {
+            let row = Component::eager("Row", Row, RowProps { id, label }).with_key(id);
+            get_active_arena(|arena| arena.insert(VirtualNode::Component(row)))
+        })
+        .collect();
+    get_active_arena(|arena| {
+        arena.insert(VirtualNode::Fragment(Fragment {
+            children,
+            parent: None,
+            key: None,
+        }))
+    })
+}
+
+/// Rows with a callback prop, which can't be compared.
+fn callback_table() -> NodeId {
+    title().get();
+    let rows = rows().get();
+    rsx! {
+        ul {
+            for (id, label) in rows {
+                LabelRow { key: id, label: Rc::new(move || label) }
+            }
+        }
+    }
+    .pop()
+    .unwrap()
+}
+
+#[allow(non_snake_case)]
+fn LabelRow(props: LabelRowProps) -> NodeId {
+    rsx! { li { {(props.label)()} } }.pop().unwrap()
+}
+
+#[derive(Clone)]
+struct LabelRowProps {
+    label: Rc<dyn Fn() -> &'static str>,
+}
+
+#[allow(non_snake_case)]
+fn Row(props: RowProps) -> NodeId {
+    let marker = if selected().get() == props.id {
+        "*"
+    } else {
+        ""
+    };
+    rsx! { li { {format!("{}{}", props.label, marker)} } }
+        .pop()
+        .unwrap()
+}
+
+#[derive(Clone, PartialEq)]
+struct RowProps {
+    id: u32,
+    label: &'static str,
+}
+
+fn mounted(root: fn() -> NodeId) -> (Runtime<LocalScheduler>, TestDom) {
+    setup();
+    let mut runtime = Runtime::new(LocalScheduler::new());
+    let mut dom = TestDom::new();
+    runtime.mount("Table", root);
+    dom.sync(&mut runtime);
+    (runtime, dom)
+}
+
+fn labels(dom: &TestDom) -> Vec<String> {
+    dom.find_all_by_tag("li")
+        .into_iter()
+        .map(|li| dom.text_content(li))
+        .collect()
+}
+
+#[test]
+fn test_unchanged_props_skip_render() {
+    let (mut runtime, mut dom) = mounted(table);
+    assert_eq!(runtime.profiling.renders("Table"), 1);
+    assert_eq!(runtime.profiling.renders("Row"), 3);
+
+    title().set("All rows");
+    runtime.update();
+    dom.sync(&mut runtime);
+    assert_eq!(runtime.profiling.renders("Table"), 2);
+    assert_eq!(runtime.profiling.renders("Row"), 3);
+    assert_eq!(runtime.profiling.skipped_renders, 3);
+    assert_eq!(dom.text_content(dom.find_by_tag("h1").unwrap()), "All rows");
+
+    rows().set(vec![(1, "one"), (2, "deux"), (3, "three")]);
+    runtime.update();
+    dom.sync(&mut runtime);
+    assert_eq!(runtime.profiling.renders("Row"), 4);
+    assert_eq!(runtime.profiling.skipped_renders, 5);
+    assert_eq!(labels(&dom), ["one", "deux", "three"]);
+}
+
+#[test]
+fn test_skipped_component_still_tracks_its_signals() {
+    let (mut runtime, mut dom) = mounted(table);
+    title().set("All rows");
+    runtime.update();
+    assert_eq!(runtime.profiling.renders("Row"), 3);
+
+    selected().set(2);
+    runtime.update();
+    dom.sync(&mut runtime);
+    assert_eq!(runtime.profiling.renders("Table"), 2);
+    assert_eq!(runtime.profiling.renders("Row"), 6);
+    assert_eq!(labels(&dom), ["one", "two*", "three"]);
+}
+
+#[test]
+fn test_custom_comparator() {
+    let (mut runtime, mut dom) = mounted(stale_table);
+
+    // The comparator ignores labels, so the rows keep what they rendered.
+    rows().set(vec![(1, "uno"), (2, "dos"), (3, "tres")]);
+    runtime.update();
+    let mutations = runtime.drain_mutations();
+    assert_eq!(runtime.profiling.renders("Row"), 3);
+    assert!(mutations.is_empty(), "{mutations:?}");
+
+    rows().set(vec![(1, "uno"), (4, "cuatro")]);
+    runtime.update();
+    dom.sync(&mut runtime);
+    assert_eq!(runtime.profiling.renders("Row"), 4);
+    assert_eq!(labels(&dom), ["one", "cuatro"]);
+}
+
+#[test]
+fn test_eager_components_always_render() {
+    let (mut runtime, _dom) = mounted(eager_table);
+    assert_eq!(runtime.profiling.renders("Row"), 3);
+
+    title().set("All rows");
+    runtime.update();
+    assert_eq!(runtime.profiling.renders("Row"), 6);
+    assert_eq!(runtime.profiling.skipped_renders, 0);
+}
+
+#[test]
+fn test_props_without_partial_eq_always_render() {
+    let (mut runtime, mut dom) = mounted(callback_table);
+    assert_eq!(runtime.profiling.renders("LabelRow"), 3);
+
+    title().set("All rows");
+    runtime.update();
+    dom.sync(&mut runtime);
+    assert_eq!(runtime.profiling.renders("LabelRow"), 6);
+    assert_eq!(runtime.profiling.skipped_renders, 0);
+    assert_eq!(labels(&dom), ["one", "two", "three"]);
+}
@@ -0,0 +1,154 @@
+use nexa_core::snapshot::tree_snapshot;
+use nexa_core::testing::TestDom;
+use nexa_core::{Fragment, NodeId, Runtime, VirtualNode, get_active_arena};
+use nexa_rsx::rsx;
+use nexa_scheduler::LocalScheduler;
+use nexa_signals::Signal;
+use std::cell::RefCell;
+
+thread_local! {
+    static ITEMS: RefCell<Option<Signal<Vec<&'static str>>>> = const { RefCell::new(None) };
+    static LOG: RefCell<Vec<String>> = const { RefCell::new(Vec::new()) };
+}
+
+fn setup(initial: Vec<&'static str>) {
+    ITEMS.with(|i| *i.borrow_mut() = Some(Signal::new(initial)));
+    LOG.with(|l| l.borrow_mut().clear());
+}
+
+fn items() -> Signal<Vec<&'static str>> {
+    ITEMS.with(|i| i.borrow().clone().unwrap())
+}
+
+fn log() -> Vec<String> {
+    LOG.with(|l| l.borrow().clone())
+}
+
+fn cart() -> NodeId {
+    let items = items();
+    let add = items.clone();
+    nexa_core::on_unmount(|| LOG.with(|l| l.borrow_mut().push("cart unmount".to_string())));
+    rsx! {
+        div {
+            p { "data-testid": "cart-count", {format!("{} items", items.get().len())} }
+            button { onclick: move |_| add.update(|items| items.push("new")), "Add" }
+        }
+    }
+    .pop()
+    .unwrap()
+}
+
+/// Renders a fragment, so its nodes sit directly in the container.
+fn list() -> NodeId {
+    let items = items().get();
+    let empty = items.is_empty();
+    let children = rsx! {
+        for item in items {
+            span { key: item, {item.to_string()} }
+        }
+        if empty {
+            em { "Empty" }
+        }
+    };
+    get_active_arena(|arena| {
+        arena.insert(VirtualNode::Fragment(Fragment {
+            children: children.into_iter().collect(),
+            parent: None,
+            key: None,
+        }))
+    })
+}
+
+fn page() -> (Runtime<LocalScheduler>, TestDom) {
+    let runtime = Runtime::new(LocalScheduler::new());
+    let dom = TestDom::with_html(r#"<header id="cart"></header><main id="list"></main>"#);
+    (runtime, dom)
+}
+
+#[test]
+fn test_roots_render_into_their_containers() {
+    setup(vec!["a", "b"]);
+    let (mut runtime, mut dom) = page();
+    runtime.mount_at("Cart", "cart", cart);
+    runtime.mount_at("List", "list", list);
+    dom.sync(&mut runtime);
+
+    assert_eq!(
+        dom.to_html(),
+        "<header id=\"cart\"><div><p data-testid=\"cart-count\">2 items</p>\
+         <button>Add</button></div></header>\
+         <main id=\"list\"><span>a</span><span>b</span></main>"
+    );
+    assert_eq!(runtime.roots["List"].container, "list");
+    runtime.verify_tree_integrity();
+}
+
+#[test]
+fn test_roots_share_signals() {
+    setup(vec!["a"]);
+    let (mut runtime, mut dom) = page();
+    runtime.mount_at("Cart", "cart", cart);
+    runtime.mount_at("List", "list", list);
+    dom.sync(&mut runtime);
+
+    // A click in one root re-renders the other in the same update.
+    let button = dom.find_by_tag("button").unwrap();
+    dom.click(&mut runtime, button);
+    let count = dom.find_by_test_id("cart-count").unwrap();
+    assert_eq!(dom.text_content(count), "2 items");
+    let list = dom.container("list").unwrap();
+    assert_eq!(dom.text_content(list), "anew");
+
+    // Top-level nodes of a root stay in its container as they change.
+    items().set(vec![]);
+    runtime.update();
+    dom.sync(&mut runtime);
+    assert_eq!(dom.to_html().matches("<em>Empty</em>").count(), 1);
+    assert_eq!(dom.text_content(list), "Empty");
+    assert_eq!(dom.children(dom.root()).len(), 2);
+}
+
+#[test]
+fn test_unmount_removes_only_that_root() {
+    setup(vec!["a"]);
+    let (mut runtime, mut dom) = page();
+    runtime.mount("App", || rsx! { h1 { "App" } }.pop().unwrap());
+    runtime.mount_at("Cart", "cart", cart);
+    runtime.mount_at("List", "list", list);
+    dom.sync(&mut runtime);
+    let scopes = runtime.scopes.len();
+
+    assert!(runtime.unmount("Cart"));
+    assert!(!runtime.unmount("Cart"));
+    dom.sync(&mut runtime);
+    assert_eq!(log(), ["cart unmount"]);
+    assert_eq!(runtime.scopes.len(), scopes - 1);
+    assert!(dom.find_by_tag("button").is_none());
+    assert_eq!(
+        dom.find_by_tag("h1").map(|h1| dom.text_content(h1)),
+        Some("App".into())
+    );
+
+    items().set(vec!["a", "b"]);
+    runtime.update();
+    dom.sync(&mut runtime);
+    assert_eq!(dom.text_content(dom.container("list").unwrap()), "ab");
+    assert_eq!(
+        tree_snapshot(&runtime),
+        "<App>\n  h1\n    \"App\"\nRoot \"List\" in #list\n  <List>\n    <>\n      span key=\"a\"\n        \"a\"\n      span key=\"b\"\n        \"b\"\n"
+    );
+}
+
+#[test]
+fn test_mounting_a_name_again_replaces_the_root() {
+    setup(vec!["a"]);
+    let (mut runtime, mut dom) = page();
+    runtime.mount_at("Widget", "cart", cart);
+    runtime.mount_at("Widget", "list", list);
+    dom.sync(&mut runtime);
+
+    assert_eq!(log(), ["cart unmount"]);
+    assert_eq!(runtime.roots.len(), 1);
+    assert_eq!(dom.text_content(dom.container("cart").unwrap()), "");
+    assert_eq!(dom.text_content(dom.container("list").unwrap()), "a");
+}
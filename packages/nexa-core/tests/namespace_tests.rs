@@ -0,0 +1,138 @@
+mod common;
+
+use common::{bare_element, insert};
+use nexa_core::{
+    Attribute, Component, Element, Mutation, NodeId, NodeMetadata, Runtime, SVG_NAMESPACE,
+    Template, TemplateNode, VirtualNode, XLINK_NAMESPACE, get_active_arena,
+};
+use nexa_scheduler::LocalScheduler;
+use std::borrow::Cow;
+
+fn element(tag: &'static str, props: Vec<Attribute>, children: Vec<NodeId>) -> NodeId {
+    insert(Element {
+        props: props.into(),
+        ..bare_element(tag, children)
+    })
+}
+
+/// Renders on its own, with no `svg` of its own to open the namespace.
+fn icon(_: ()) -> NodeId {
+    let href = Attribute {
+        name: "xlink:href",
+        value: "#star".into(),
+    };
+    element("use", vec![href], vec![])
+}
+
+fn chart() -> NodeId {
+    let icon = get_active_arena(|arena| {
+        arena.insert(VirtualNode::Component(Component::new("Icon", icon, ())))
+    });
+    let label = element("span", vec![], vec![]);
+    let foreign = element("foreignObject", vec![], vec![label]);
+    let group = element("g", vec![], vec![icon, foreign]);
+    let svg = element("svg", vec![], vec![group]);
+    element("div", vec![], vec![svg])
+}
+
+/// (tag, namespace) for every created element, in creation order.
+fn created(mutations: &[Mutation]) -> Vec<(&str, Option<&str>)> {
+    mutations
+        .iter()
+        .filter_map(|m| match m {
+            Mutation::CreateElement { tag, .. } => Some((tag.as_str(), None)),
+            Mutation::CreateElementNs { tag, ns, .. } => Some((tag.as_str(), Some(ns.as_str()))),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn test_svg_namespace_is_inherited() {
+    let mut runtime = Runtime::new(LocalScheduler::new());
+    runtime.mount("Chart", chart);
+    let mutations = runtime.drain_mutations();
+
+    assert_eq!(
+        created(&mutations),
+        vec![
+            ("div", None),
+            ("svg", Some(SVG_NAMESPACE)),
+            ("g", Some(SVG_NAMESPACE)),
+            ("use", Some(SVG_NAMESPACE)),
+            ("foreignObject", Some(SVG_NAMESPACE)),
+            // Back to HTML inside `foreignObject`.
+            ("span", None),
+        ]
+    );
+}
+
+#[test]
+fn test_prefixed_attributes_are_namespaced() {
+    let mut runtime = Runtime::new(LocalScheduler::new());
+    runtime.mount("Chart", chart);
+    let mutations = runtime.drain_mutations();
+
+    assert!(mutations.iter().any(|m| matches!(m,
+        Mutation::SetAttribute { name, ns: Some(ns), .. }
+            if name == "xlink:href" && ns == XLINK_NAMESPACE)));
+}
+
+/// Built the way `rsx!` builds a lone `path`, which can't know it is SVG.
+static PATH: Template = Template {
+    name: Cow::Borrowed("path"),
+    roots: Cow::Borrowed(&[TemplateNode::Element {
+        tag: Cow::Borrowed("path"),
+        namespace: None,
+        attrs: Cow::Borrowed(&[]),
+        children: Cow::Borrowed(&[]),
+        dynamic_children: false,
+    }]),
+};
+
+fn path_icon(_: ()) -> NodeId {
+    get_active_arena(|arena| {
+        arena.insert_with_metadata(
+            VirtualNode::Element(Element {
+                tag: "path",
+                namespace: None,
+                props: Default::default(),
+                listeners: Default::default(),
+                children: Default::default(),
+                parent: None,
+                key: None,
+                node_ref: None,
+            }),
+            NodeMetadata {
+                template: Some(&PATH),
+                ..Default::default()
+            },
+        )
+    })
+}
+
+#[test]
+fn test_html_template_is_skipped_inside_svg() {
+    let mut runtime = Runtime::new(LocalScheduler::new());
+    runtime.mount("Icon", || {
+        let icon = get_active_arena(|arena| {
+            arena.insert(VirtualNode::Component(Component::new(
+                "Path",
+                path_icon,
+                (),
+            )))
+        });
+        element("svg", vec![], vec![icon])
+    });
+    let mutations = runtime.drain_mutations();
+
+    assert!(
+        !mutations
+            .iter()
+            .any(|m| matches!(m, Mutation::LoadTemplate { .. }))
+    );
+    assert_eq!(
+        created(&mutations),
+        vec![("svg", Some(SVG_NAMESPACE)), ("path", Some(SVG_NAMESPACE))]
+    );
+}
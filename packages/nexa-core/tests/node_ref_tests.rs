@@ -0,0 +1,92 @@
+mod common;
+
+use common::{bare_element, insert};
+use nexa_core::{Element, Mutation, NodeId, NodeRef, Runtime, on_mount};
+use nexa_scheduler::LocalScheduler;
+use nexa_signals::Signal;
+use std::cell::RefCell;
+
+thread_local! {
+    static SHOWN: RefCell<Option<Signal<Option<&'static str>>>> = const { RefCell::new(None) };
+    static INPUT: RefCell<NodeRef> = RefCell::new(NodeRef::new());
+    static SEEN_ON_MOUNT: RefCell<Option<u64>> = const { RefCell::new(None) };
+}
+
+fn setup(tag: Option<&'static str>) {
+    SHOWN.with(|s| *s.borrow_mut() = Some(Signal::new(tag)));
+}
+
+fn shown() -> Signal<Option<&'static str>> {
+    SHOWN.with(|s| s.borrow().clone().unwrap())
+}
+
+fn input_ref() -> NodeRef {
+    INPUT.with(|r| r.borrow().clone())
+}
+
+fn element(tag: &'static str, node_ref: Option<NodeRef>, children: Vec<NodeId>) -> NodeId {
+    insert(Element {
+        node_ref,
+        ..bare_element(tag, children)
+    })
+}
+
+/// A form whose field, if shown, carries `INPUT`.
+fn form() -> NodeId {
+    let field = input_ref();
+    on_mount(move || SEEN_ON_MOUNT.with(|s| *s.borrow_mut() = field.get()));
+    let children = shown()
+        .get()
+        .map(|tag| element(tag, Some(input_ref()), vec![]))
+        .into_iter()
+        .collect();
+    element("form", None, children)
+}
+
+fn created_id(mutations: &[Mutation], wanted: &str) -> u64 {
+    mutations
+        .iter()
+        .find_map(|m| match m {
+            Mutation::CreateElement { tag, id } if tag == wanted => Some(*id),
+            _ => None,
+        })
+        .unwrap()
+}
+
+#[test]
+fn test_ref_is_filled_after_commit() {
+    setup(Some("input"));
+    let mut runtime = Runtime::new(LocalScheduler::new());
+    runtime.mount("Form", form);
+
+    let input = created_id(&runtime.drain_mutations(), "input");
+    assert_eq!(input_ref().get(), Some(input));
+    // Mount hooks run after the commit, so they already see it.
+    assert_eq!(SEEN_ON_MOUNT.with(|s| *s.borrow()), Some(input));
+}
+
+#[test]
+fn test_ref_is_cleared_on_unmount() {
+    setup(Some("input"));
+    let mut runtime = Runtime::new(LocalScheduler::new());
+    runtime.mount("Form", form);
+    assert!(input_ref().get().is_some());
+
+    shown().set(None);
+    runtime.update();
+    assert_eq!(input_ref().get(), None);
+}
+
+#[test]
+fn test_ref_follows_replaced_element() {
+    setup(Some("input"));
+    let mut runtime = Runtime::new(LocalScheduler::new());
+    runtime.mount("Form", form);
+    runtime.drain_mutations();
+
+    // The replacement is created before the old element is removed.
+    shown().set(Some("textarea"));
+    runtime.update();
+    let textarea = created_id(&runtime.drain_mutations(), "textarea");
+    assert_eq!(input_ref().get(), Some(textarea));
+}
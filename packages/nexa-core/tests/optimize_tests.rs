@@ -0,0 +1,248 @@
+use nexa_core::mutations::Mutation;
+use nexa_core::optimize::optimize;
+use nexa_core::testing::TestDom;
+use nexa_core::{AttributeValue, NodeId, Runtime};
+use nexa_rsx::rsx;
+use nexa_scheduler::LocalScheduler;
+use nexa_signals::Signal;
+use proptest::prelude::*;
+use std::cell::RefCell;
+
+fn set_attribute(id: u64, name: &str, value: &str) -> Mutation {
+    Mutation::SetAttribute {
+        name: name.to_string(),
+        value: AttributeValue::Text(value.to_string()),
+        id,
+        ns: None,
+    }
+}
+
+fn create_text(id: u64, text: &str) -> Mutation {
+    Mutation::CreateTextNode {
+        text: text.to_string(),
+        id,
+    }
+}
+
+fn set_text(id: u64, value: &str) -> Mutation {
+    Mutation::SetText {
+        value: value.to_string(),
+        id,
+    }
+}
+
+#[test]
+fn test_last_attribute_write_wins() {
+    let mutations = optimize(vec![
+        set_attribute(1, "class", "a"),
+        set_attribute(1, "title", "t"),
+        Mutation::RemoveAttribute {
+            name: "class".to_string(),
+            id: 1,
+        },
+        set_attribute(1, "class", "b"),
+        set_attribute(2, "class", "a"),
+    ]);
+    assert_eq!(
+        mutations,
+        [
+            set_attribute(1, "title", "t"),
+            set_attribute(1, "class", "b"),
+            set_attribute(2, "class", "a"),
+        ]
+    );
+}
+
+#[test]
+fn test_text_writes_coalesce() {
+    let mutations = optimize(vec![
+        create_text(1, "a"),
+        set_text(2, "x"),
+        set_text(1, "b"),
+        set_text(2, "y"),
+        Mutation::AppendChildren { id: 0, m: vec![1] },
+        set_text(1, "c"),
+    ]);
+    assert_eq!(
+        mutations,
+        [
+            create_text(1, "c"),
+            set_text(2, "y"),
+            Mutation::AppendChildren { id: 0, m: vec![1] },
+        ]
+    );
+}
+
+#[test]
+fn test_created_then_removed_node_is_never_created() {
+    let mutations = optimize(vec![
+        Mutation::CreateElement {
+            tag: "li".to_string(),
+            id: 1,
+        },
+        set_attribute(1, "class", "a"),
+        Mutation::NewEventListener {
+            name: "click".to_string(),
+            id: 1,
+        },
+        create_text(2, "kept"),
+        Mutation::AppendChildren {
+            id: 0,
+            m: vec![1, 2],
+        },
+        set_attribute(3, "class", "gone"),
+        Mutation::Remove { id: 1 },
+        Mutation::Remove { id: 3 },
+    ]);
+    assert_eq!(
+        mutations,
+        [
+            create_text(2, "kept"),
+            Mutation::AppendChildren { id: 0, m: vec![2] },
+            Mutation::Remove { id: 3 },
+        ]
+    );
+}
+
+#[test]
+fn test_node_with_dependents_is_kept() {
+    let mutations = vec![
+        Mutation::CreateElement {
+            tag: "ul".to_string(),
+            id: 1,
+        },
+        create_text(2, "a"),
+        Mutation::AppendChildren { id: 1, m: vec![2] },
+        Mutation::AppendChildren { id: 0, m: vec![1] },
+        Mutation::Remove { id: 1 },
+    ];
+    assert_eq!(optimize(mutations.clone()), mutations);
+}
+
+#[test]
+fn test_insert_then_remove_of_anchor_is_a_replace() {
+    let mutations = optimize(vec![
+        create_text(2, "new"),
+        Mutation::InsertBefore { id: 1, m: vec![2] },
+        Mutation::Remove { id: 1 },
+    ]);
+    assert_eq!(
+        mutations,
+        [
+            create_text(2, "new"),
+            Mutation::ReplaceWith { id: 1, m: vec![2] },
+        ]
+    );
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Item {
+    key: u8,
+    para: bool,
+    text: &'static str,
+    class: Option<&'static str>,
+}
+
+thread_local! {
+    static ITEMS: RefCell<Option<Signal<Vec<Item>>>> = const { RefCell::new(None) };
+}
+
+fn items() -> Signal<Vec<Item>> {
+    ITEMS.with(|i| i.borrow().clone().unwrap())
+}
+
+fn list() -> NodeId {
+    let items = items().get();
+    let empty = items.is_empty();
+    rsx! {
+        section {
+            ul {
+                for item in items {
+                    Entry { key: item.key, item: item.clone() }
+                }
+            }
+            if empty {
+                em { "Nothing" }
+            }
+        }
+    }
+    .pop()
+    .unwrap()
+}
+
+#[allow(non_snake_case)]
+fn Entry(props: EntryProps) -> NodeId {
+    let item = props.item;
+    if item.para {
+        rsx! { p { class: item.class, {item.text.to_string()} } }
+    } else {
+        rsx! { li { class: item.class, "data-key": item.key as i64, {item.text.to_string()} } }
+    }
+    .pop()
+    .unwrap()
+}
+
+#[derive(Clone, PartialEq)]
+struct EntryProps {
+    item: Item,
+}
+
+fn item() -> impl Strategy<Value = Item> {
+    (
+        0u8..6,
+        any::<bool>(),
+        prop::sample::select(vec!["", "a", "b"]),
+        prop::option::of(prop::sample::select(vec!["x", "y"])),
+    )
+        .prop_map(|(key, para, text, class)| Item {
+            key,
+            para,
+            text,
+            class,
+        })
+}
+
+/// A list with unique keys.
+fn state() -> impl Strategy<Value = Vec<Item>> {
+    prop::collection::vec(item(), 0..6).prop_map(|mut items| {
+        let mut seen = Vec::new();
+        items.retain(|item| {
+            let new = !seen.contains(&item.key);
+            seen.push(item.key);
+            new
+        });
+        items
+    })
+}
+
+proptest! {
+    /// Batches of several updates, applied as emitted and optimized, build
+    /// the same document.
+    #[test]
+    fn prop_optimized_stream_builds_same_dom(
+        initial in state(),
+        batches in prop::collection::vec(prop::collection::vec(state(), 1..4), 1..5),
+    ) {
+        ITEMS.with(|i| *i.borrow_mut() = Some(Signal::new(initial)));
+        let mut runtime = Runtime::new(LocalScheduler::new());
+        let mut emitted = TestDom::new();
+        let mut optimized = TestDom::new();
+
+        runtime.mount("List", list);
+        let mut batch = std::mem::take(&mut runtime.mutation_buffer);
+        for states in std::iter::once(Vec::new()).chain(batches) {
+            for state in states {
+                items().set(state);
+                runtime.update();
+            }
+            batch.append(&mut runtime.mutation_buffer);
+
+            let raw = std::mem::take(&mut batch);
+            let fewer = optimize(raw.clone());
+            prop_assert!(fewer.len() <= raw.len());
+            emitted.apply(raw);
+            optimized.apply(fewer);
+            prop_assert_eq!(emitted.to_pretty_html(), optimized.to_pretty_html());
+        }
+    }
+}
@@ -0,0 +1,175 @@
+mod common;
+
+use common::{element, text};
+use nexa_core::{
+    Event, EventData, EventListener, Mutation, NodeId, Portal, Runtime, VirtualNode,
+    get_active_arena,
+};
+use nexa_scheduler::LocalScheduler;
+use nexa_signals::Signal;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+thread_local! {
+    static OPEN: RefCell<Option<Signal<bool>>> = const { RefCell::new(None) };
+    static ITEMS: RefCell<Option<Signal<Vec<&'static str>>>> = const { RefCell::new(None) };
+    static CLICKS: RefCell<Vec<&'static str>> = const { RefCell::new(Vec::new()) };
+}
+
+fn setup() {
+    OPEN.with(|o| *o.borrow_mut() = Some(Signal::new(true)));
+    ITEMS.with(|i| *i.borrow_mut() = Some(Signal::new(vec!["Hello"])));
+    CLICKS.with(|c| c.borrow_mut().clear());
+}
+
+fn open() -> Signal<bool> {
+    OPEN.with(|o| o.borrow().clone().unwrap())
+}
+
+fn items() -> Signal<Vec<&'static str>> {
+    ITEMS.with(|i| i.borrow().clone().unwrap())
+}
+
+fn click_logger(label: &'static str) -> EventListener {
+    EventListener {
+        name: "click",
+        capture: false,
+        cb: Rc::new(RefCell::new(move |_: Event| {
+            CLICKS.with(|c| c.borrow_mut().push(label))
+        })),
+    }
+}
+
+/// A page whose modal renders into `#modal-root` while it is open.
+fn app() -> NodeId {
+    let mut children = vec![text("Page")];
+    if open().get() {
+        let lines = items().get().into_iter().map(text).collect();
+        let modal = element("dialog", vec![], lines);
+        children.push(get_active_arena(|arena| {
+            arena.insert(VirtualNode::Portal(Portal {
+                target: "modal-root".to_string(),
+                content: modal,
+                parent: None,
+                key: None,
+            }))
+        }));
+    }
+    element("main", vec![click_logger("main")], children)
+}
+
+fn created(mutations: &[Mutation], wanted: &str) -> u64 {
+    mutations
+        .iter()
+        .find_map(|m| match m {
+            Mutation::CreateElement { tag, id } if tag == wanted => Some(*id),
+            Mutation::CreateTextNode { text, id } if text == wanted => Some(*id),
+            _ => None,
+        })
+        .unwrap()
+}
+
+fn container(mutations: &[Mutation]) -> u64 {
+    mutations
+        .iter()
+        .find_map(|m| match m {
+            Mutation::LoadContainer { target, id } if target == "modal-root" => Some(*id),
+            _ => None,
+        })
+        .unwrap()
+}
+
+#[test]
+fn test_content_is_appended_to_the_target() {
+    setup();
+    let mut runtime = Runtime::new(LocalScheduler::new());
+    runtime.mount("App", app);
+
+    let mutations = runtime.drain_mutations();
+    let portal = container(&mutations);
+    let main = created(&mutations, "main");
+    let dialog = created(&mutations, "dialog");
+    assert!(mutations.contains(&Mutation::AppendChildren {
+        id: portal,
+        m: vec![dialog],
+    }));
+    // Only the page text stays in place.
+    assert!(mutations.contains(&Mutation::AppendChildren {
+        id: main,
+        m: vec![created(&mutations, "Page")],
+    }));
+}
+
+#[test]
+fn test_updates_land_in_the_target() {
+    setup();
+    let mut runtime = Runtime::new(LocalScheduler::new());
+    runtime.mount("App", app);
+    let mutations = runtime.drain_mutations();
+    let dialog = created(&mutations, "dialog");
+
+    items().set(vec!["Hello", "World"]);
+    runtime.update();
+    let mutations = runtime.drain_mutations();
+    let world = created(&mutations, "World");
+    assert!(mutations.contains(&Mutation::AppendChildren {
+        id: dialog,
+        m: vec![world],
+    }));
+    assert!(
+        !mutations
+            .iter()
+            .any(|m| matches!(m, Mutation::LoadContainer { .. }))
+    );
+}
+
+#[test]
+fn test_removing_the_portal_removes_its_content() {
+    setup();
+    let mut runtime = Runtime::new(LocalScheduler::new());
+    runtime.mount("App", app);
+    let mutations = runtime.drain_mutations();
+    let dialog = created(&mutations, "dialog");
+
+    open().set(false);
+    runtime.update();
+    let mutations = runtime.drain_mutations();
+    assert_eq!(mutations, vec![Mutation::Remove { id: dialog }]);
+}
+
+#[test]
+fn test_events_bubble_through_the_logical_tree() {
+    setup();
+    let mut runtime = Runtime::new(LocalScheduler::new());
+    runtime.mount("App", app);
+    let dialog = created(&runtime.drain_mutations(), "dialog");
+
+    runtime.handle_event(dialog, "click", Event::new(EventData::Unknown));
+    assert_eq!(CLICKS.with(|c| c.borrow().clone()), vec!["main"]);
+}
+
+#[test]
+#[should_panic(expected = "Orphaned or invalid NodeId")]
+fn test_integrity_check_walks_into_the_portal() {
+    setup();
+    let mut runtime = Runtime::new(LocalScheduler::new());
+    runtime.mount("App", app);
+    runtime.verify_tree_integrity();
+
+    let root = match runtime.arena.nodes.get(runtime.root_node.unwrap()) {
+        Some(VirtualNode::Component(comp)) => runtime.scopes[comp.scope.unwrap()].root_node,
+        _ => None,
+    };
+    let Some(VirtualNode::Element(main)) = runtime.arena.nodes.get(root.unwrap()) else {
+        panic!("the page should be a <main>");
+    };
+    let Some(VirtualNode::Portal(portal)) = runtime.arena.nodes.get(main.children[1]) else {
+        panic!("the modal should be a portal");
+    };
+    let Some(VirtualNode::Element(dialog)) = runtime.arena.nodes.get(portal.content) else {
+        panic!("the portal should hold the dialog");
+    };
+    let line = dialog.children[0];
+    runtime.arena.nodes.remove(line);
+    runtime.verify_tree_integrity();
+}
@@ -0,0 +1,180 @@
+use nexa_core::profiler::ProfilePhase;
+use nexa_core::{NodeId, Runtime};
+use nexa_rsx::rsx;
+use nexa_scheduler::LocalScheduler;
+use nexa_signals::Signal;
+use std::cell::RefCell;
+
+/// `(key, label)` per row.
+type Rows = Vec<(u32, &'static str)>;
+
+thread_local! {
+    static ROWS: RefCell<Option<Signal<Rows>>> = const { RefCell::new(None) };
+}
+
+fn rows() -> Signal<Rows> {
+    ROWS.with(|r| r.borrow().clone().unwrap())
+}
+
+fn table() -> NodeId {
+    let rows = rows().get();
+    rsx! {
+        ul {
+            for (id, label) in rows {
+                Row { key: id, label: label }
+            }
+        }
+    }
+    .pop()
+    .unwrap()
+}
+
+#[allow(non_snake_case)]
+fn Row(props: RowProps) -> NodeId {
+    rsx! { li { {props.label.to_string()} } }.pop().unwrap()
+}
+
+#[derive(Clone, PartialEq)]
+struct RowProps {
+    label: &'static str,
+}
+
+fn mounted() -> Runtime<LocalScheduler> {
+    ROWS.with(|r| *r.borrow_mut() = Some(Signal::new(vec![(1, "one"), (2, "two"), (3, "three")])));
+    Runtime::new(LocalScheduler::new())
+}
+
+#[test]
+fn test_nothing_is_recorded_by_default() {
+    let mut runtime = mounted();
+    runtime.mount("Table", table);
+    assert!(!runtime.profiling.is_recording());
+    assert!(runtime.profiling.stop_recording().is_none());
+}
+
+#[test]
+fn test_mount_records_each_scope() {
+    let mut runtime = mounted();
+    runtime.profiling.start_recording();
+    runtime.mount("Table", table);
+    let recording = runtime.profiling.stop_recording().unwrap();
+
+    let components = recording.components();
+    assert_eq!(components.len(), 4);
+    let rows: Vec<_> = components.iter().filter(|c| c.name == "Row").collect();
+    assert_eq!(rows.len(), 3);
+    for row in &rows {
+        assert_eq!(row.renders, 1);
+        assert!(row.mutations > 0);
+    }
+    let mutations: u64 = components.iter().map(|c| c.mutations).sum();
+    // Everything but the root's PushRoot and AppendChildren.
+    assert_eq!(mutations as usize, runtime.drain_mutations().len() - 2);
+
+    // The rows are created inside the table's diff, nested in its span.
+    let table_diff = recording
+        .spans
+        .iter()
+        .find(|s| s.name() == "Table" && s.phase == ProfilePhase::Diff)
+        .unwrap();
+    let row_spans: Vec<_> = recording
+        .spans
+        .iter()
+        .filter(|s| s.stack == ["Table", "Row"] && s.phase != ProfilePhase::Commit)
+        .collect();
+    assert_eq!(row_spans.len(), 6);
+    for span in &row_spans {
+        assert!(span.start >= table_diff.start);
+        assert!(span.start + span.duration <= table_diff.start + table_diff.duration);
+    }
+    let nested: std::time::Duration = row_spans.iter().map(|s| s.duration).sum();
+    assert_eq!(table_diff.self_time + nested, table_diff.duration);
+
+    let commits = recording
+        .spans
+        .iter()
+        .filter(|s| s.phase == ProfilePhase::Commit)
+        .count();
+    assert_eq!(commits, 4);
+}
+
+#[test]
+fn test_update_records_only_what_re_rendered() {
+    let mut runtime = mounted();
+    runtime.mount("Table", table);
+    runtime.drain_mutations();
+
+    runtime.profiling.start_recording();
+    rows().set(vec![(1, "one"), (2, "deux"), (3, "three")]);
+    runtime.update();
+    let recording = runtime.profiling.stop_recording().unwrap();
+
+    let components = recording.components();
+    let names: Vec<_> = components.iter().map(|c| c.name.as_str()).collect();
+    assert_eq!(names.len(), 2, "{names:?}");
+    let row = components.iter().find(|c| c.name == "Row").unwrap();
+    let table = components.iter().find(|c| c.name == "Table").unwrap();
+    assert_eq!((row.renders, row.mutations), (1, 1));
+    assert_eq!((table.renders, table.mutations), (1, 0));
+    assert_eq!(runtime.drain_mutations().len(), 1);
+
+    // Stopped, so later updates aren't recorded.
+    rows().set(vec![]);
+    runtime.update();
+    assert!(!runtime.profiling.is_recording());
+}
+
+#[test]
+fn test_exports() {
+    let mut runtime = mounted();
+    runtime.profiling.start_recording();
+    runtime.mount("Table", table);
+    let recording = runtime.profiling.stop_recording().unwrap();
+
+    let trace = recording.to_chrome_trace();
+    assert!(
+        trace.starts_with("{\"traceEvents\":[{\"name\":\"Table\",\"cat\":\"render\",\"ph\":\"X\"")
+    );
+    assert!(trace.ends_with("],\"displayTimeUnit\":\"ms\"}"));
+    assert_eq!(trace.matches("\"ph\":\"X\"").count(), recording.spans.len());
+    assert_eq!(trace.matches("\"name\":\"Row\"").count(), 9);
+
+    let folded = recording.to_folded();
+    let stacks: Vec<&str> = folded
+        .lines()
+        .map(|line| {
+            let (stack, micros) = line.rsplit_once(' ').unwrap();
+            micros.parse::<u128>().unwrap();
+            stack
+        })
+        .collect();
+    assert_eq!(
+        stacks,
+        [
+            "Table;Row;commit",
+            "Table;Row;diff",
+            "Table;Row;render",
+            "Table;commit",
+            "Table;diff",
+            "Table;render",
+        ]
+    );
+}
+
+#[test]
+fn test_drained_mutations_are_counted_after_optimizing() {
+    let mut runtime = mounted();
+    runtime.mount("Table", table);
+    let mounted = runtime.drain_mutations().len() as u64;
+    assert_eq!(runtime.profiling.drained_mutations, mounted);
+    let emitted = runtime.profiling.mutation_count;
+
+    // Two renders before the host drains: only the last label is written.
+    rows().set(vec![(1, "one"), (2, "deux"), (3, "three")]);
+    runtime.update();
+    rows().set(vec![(1, "one"), (2, "dos"), (3, "three")]);
+    runtime.update();
+    assert_eq!(runtime.drain_mutations().len(), 1);
+    assert_eq!(runtime.profiling.mutation_count, emitted + 2);
+    assert_eq!(runtime.profiling.drained_mutations, mounted + 1);
+}
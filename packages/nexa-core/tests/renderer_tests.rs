@@ -0,0 +1,112 @@
+use nexa_core::renderer::Renderer;
+use nexa_core::testing::TestDom;
+use nexa_core::{Mutation, NodeId, Runtime};
+use nexa_rsx::rsx;
+use nexa_scheduler::LocalScheduler;
+use nexa_signals::Signal;
+use std::cell::RefCell;
+
+thread_local! {
+    static COUNT: RefCell<Option<Signal<i32>>> = const { RefCell::new(None) };
+}
+
+fn setup() {
+    COUNT.with(|c| *c.borrow_mut() = Some(Signal::new(0)));
+}
+
+fn count() -> Signal<i32> {
+    COUNT.with(|c| c.borrow().clone().unwrap())
+}
+
+fn counter() -> NodeId {
+    let count = count().get();
+    rsx! { p { {format!("Count: {count}")} } }.pop().unwrap()
+}
+
+/// Logs the text it's given, like a terminal would draw it.
+#[derive(Default)]
+struct TextLog {
+    calls: Vec<String>,
+}
+
+impl Renderer for TextLog {
+    fn load_template(&mut self, name: &str, _index: usize, _id: u64) {
+        self.calls.push(format!("load {name}"));
+    }
+
+    fn hydrate_text(&mut self, _path: &[u8], value: &str, _id: u64) {
+        self.calls.push(format!("text {value}"));
+    }
+
+    fn set_text(&mut self, value: &str, _id: u64) {
+        self.calls.push(format!("set {value}"));
+    }
+
+    fn finish(&mut self) {
+        self.calls.push("finish".to_string());
+    }
+}
+
+#[test]
+fn test_test_dom_as_sink() {
+    setup();
+    let mut runtime = Runtime::new(LocalScheduler::new());
+    let mut dom = TestDom::new();
+    runtime.mount("Counter", counter);
+    runtime.flush_into(&mut dom);
+    assert_eq!(dom.to_html(), "<p>Count: 0</p>");
+
+    count().set(1);
+    runtime.render_into(&mut dom);
+    assert_eq!(dom.to_html(), "<p>Count: 1</p>");
+}
+
+#[test]
+fn test_renderer_callbacks() {
+    setup();
+    let mut runtime = Runtime::new(LocalScheduler::new());
+    let mut log = TextLog::default();
+    runtime.mount("Counter", counter);
+    runtime.flush_into(&mut log);
+    assert_eq!(log.calls.len(), 3, "{:?}", log.calls);
+    assert!(log.calls[0].starts_with("load "));
+    assert_eq!(log.calls[1..], ["text Count: 0", "finish"]);
+
+    log.calls.clear();
+    count().set(2);
+    runtime.render_into(&mut log);
+    assert_eq!(log.calls, ["set Count: 2", "finish"]);
+}
+
+#[test]
+fn test_empty_batch_not_pushed() {
+    setup();
+    let mut runtime = Runtime::new(LocalScheduler::new());
+    let mut log = TextLog::default();
+    runtime.mount("Counter", counter);
+    runtime.flush_into(&mut log);
+
+    log.calls.clear();
+    runtime.render_into(&mut log);
+    count().set(0);
+    runtime.render_into(&mut log);
+    assert!(log.calls.is_empty(), "{:?}", log.calls);
+}
+
+#[test]
+fn test_vec_sink_collects_batches() {
+    setup();
+    let mut runtime = Runtime::new(LocalScheduler::new());
+    let mut batches: Vec<Mutation> = Vec::new();
+    runtime.mount("Counter", counter);
+    runtime.flush_into(&mut batches);
+    let mounted = batches.len();
+    assert!(matches!(batches[0], Mutation::RegisterTemplate { .. }));
+
+    count().set(3);
+    runtime.render_into(&mut batches);
+    assert!(matches!(
+        &batches[mounted..],
+        [Mutation::SetText { value, .. }] if value == "Count: 3"
+    ));
+}
@@ -43,11 +43,13 @@ fn test_mount_element() {
         get_active_arena(|arena| {
             arena.insert(VirtualNode::Element(Element {
                 tag: "div",
+                namespace: None,
                 props: Default::default(),
                 listeners: Default::default(),
                 children: Default::default(),
                 parent: None,
                 key: None,
+                node_ref: None,
             }))
         })
     }
@@ -80,6 +82,7 @@ fn test_mount_text() {
             arena.insert(VirtualNode::Text(Text {
                 text: "Hello World".to_string(),
                 parent: None,
+                key: None,
             }))
         })
     }
@@ -107,17 +110,20 @@ fn test_attributes_and_listeners() {
         get_active_arena(|arena| {
             arena.insert(VirtualNode::Element(Element {
                 tag: "button",
+                namespace: None,
                 props: smallvec![Attribute {
                     name: "class",
-                    value: "btn-primary".to_string()
+                    value: "btn-primary".into()
                 }],
                 listeners: smallvec![EventListener {
                     name: "click",
-                    cb: Rc::new(RefCell::new(|_| {}))
+                    cb: Rc::new(RefCell::new(|_| {})),
+                    capture: false,
                 }],
                 children: Default::default(),
                 parent: None,
                 key: None,
+                node_ref: None,
             }))
         })
     }
@@ -126,7 +132,7 @@ fn test_attributes_and_listeners() {
 
     let mutations = runtime.drain_mutations();
 
-    let has_attr = mutations.iter().any(|m| matches!(m, nexa_core::Mutation::SetAttribute { name, value, .. } if name == "class" && value == "btn-primary"));
+    let has_attr = mutations.iter().any(|m| matches!(m, nexa_core::Mutation::SetAttribute { name, value, .. } if name == "class" && *value == "btn-primary".into()));
     let has_listener = mutations.iter().any(
         |m| matches!(m, nexa_core::Mutation::NewEventListener { name, .. } if name == "click"),
     );
@@ -144,11 +150,13 @@ fn test_mount_component() {
         get_active_arena(|arena| {
             arena.insert(VirtualNode::Element(Element {
                 tag: "span",
+                namespace: None,
                 props: Default::default(),
                 listeners: Default::default(),
                 children: Default::default(),
                 parent: None,
                 key: None,
+                node_ref: None,
             }))
         })
     }
@@ -162,22 +170,23 @@ fn test_mount_component() {
         // We need to insert a VirtualNode::Component that points to child_component.
 
         let child_node_id = get_active_arena(|arena| {
-            arena.insert(VirtualNode::Component(nexa_core::Component {
-                name: "Child",
-                render_fn: child_component,
-                scope: None,
-                parent: None,
-            }))
+            arena.insert(VirtualNode::Component(nexa_core::Component::new(
+                "Child",
+                |()| child_component(),
+                (),
+            )))
         });
 
         get_active_arena(|arena| {
             arena.insert(VirtualNode::Element(Element {
                 tag: "div",
+                namespace: None,
                 props: Default::default(),
                 listeners: Default::default(),
                 children: smallvec![child_node_id],
                 parent: None,
                 key: None,
+                node_ref: None,
             }))
         })
     }
@@ -212,3 +221,261 @@ fn test_mount_component() {
         "Should have at least 2 appends (span->div, div->root)"
     );
 }
+
+thread_local! {
+    static COUNT: RefCell<Option<nexa_signals::Signal<i32>>> = const { RefCell::new(None) };
+    static ROOT_RENDERS: std::cell::Cell<u32> = const { std::cell::Cell::new(0) };
+    static CHILD_RENDERS: std::cell::Cell<u32> = const { std::cell::Cell::new(0) };
+}
+
+fn count_signal() -> nexa_signals::Signal<i32> {
+    COUNT.with(|c| c.borrow().clone().expect("COUNT not initialised"))
+}
+
+/// Installs a fresh `COUNT` and resets the render counters.
+fn install_count(initial: i32) {
+    COUNT.with(|c| *c.borrow_mut() = Some(nexa_signals::Signal::new(initial)));
+    ROOT_RENDERS.with(|c| c.set(0));
+    CHILD_RENDERS.with(|c| c.set(0));
+}
+
+fn counting_child() -> NodeId {
+    use nexa_core::vdom::get_active_arena;
+
+    CHILD_RENDERS.with(|c| c.set(c.get() + 1));
+    let count = count_signal().get();
+    get_active_arena(|arena| {
+        arena.insert(VirtualNode::Text(Text {
+            text: format!("Count: {}", count),
+            parent: None,
+            key: None,
+        }))
+    })
+}
+
+fn static_parent() -> NodeId {
+    use nexa_core::vdom::get_active_arena;
+    use smallvec::smallvec;
+
+    ROOT_RENDERS.with(|c| c.set(c.get() + 1));
+    get_active_arena(|arena| {
+        let child = arena.insert(VirtualNode::Component(nexa_core::Component::new(
+            "Child",
+            |()| counting_child(),
+            (),
+        )));
+        arena.insert(VirtualNode::Element(Element {
+            tag: "div",
+            namespace: None,
+            props: Default::default(),
+            listeners: Default::default(),
+            children: smallvec![child],
+            parent: None,
+            key: None,
+            node_ref: None,
+        }))
+    })
+}
+
+#[test]
+fn test_only_dirty_component_rerenders() {
+    install_count(0);
+    let mut runtime = create_test_runtime();
+    runtime.mount("Root", static_parent);
+
+    let text_id = runtime
+        .drain_mutations()
+        .iter()
+        .find_map(|m| match m {
+            nexa_core::Mutation::CreateTextNode { id, .. } => Some(*id),
+            _ => None,
+        })
+        .expect("Should create the counter text");
+
+    count_signal().set(5);
+    runtime.update();
+
+    assert_eq!(
+        ROOT_RENDERS.with(|c| c.get()),
+        1,
+        "Parent should not re-render"
+    );
+    assert_eq!(
+        CHILD_RENDERS.with(|c| c.get()),
+        2,
+        "Child should re-render once"
+    );
+
+    // The existing text node is patched in place.
+    let mutations = runtime.drain_mutations();
+    assert_eq!(mutations.len(), 1);
+    assert!(matches!(
+        &mutations[0],
+        nexa_core::Mutation::SetText { id, value } if *id == text_id && value == "Count: 5"
+    ));
+    runtime.verify_tree_integrity();
+
+    // Nothing dirty, nothing rendered.
+    runtime.update();
+    assert_eq!(CHILD_RENDERS.with(|c| c.get()), 2);
+    assert!(runtime.drain_mutations().is_empty());
+}
+
+#[test]
+fn test_root_rerenders_on_batched_write() {
+    install_count(1);
+    let mut runtime = create_test_runtime();
+    runtime.mount("Root", counting_child);
+    runtime.drain_mutations();
+
+    nexa_signals::dependency::batch(|| count_signal().set(2));
+    runtime.update();
+
+    assert_eq!(CHILD_RENDERS.with(|c| c.get()), 2);
+    let mutations = runtime.drain_mutations();
+    assert!(
+        mutations.iter().any(
+            |m| matches!(m, nexa_core::Mutation::SetText { value, .. } if value == "Count: 2")
+        )
+    );
+}
+
+thread_local! {
+    static LIFECYCLE_LOG: RefCell<Vec<String>> = const { RefCell::new(Vec::new()) };
+}
+
+fn log_event(event: String) {
+    LIFECYCLE_LOG.with(|l| l.borrow_mut().push(event));
+}
+
+fn take_log() -> Vec<String> {
+    LIFECYCLE_LOG.with(|l| std::mem::take(&mut *l.borrow_mut()))
+}
+
+fn hooked_child() -> NodeId {
+    use nexa_core::vdom::get_active_arena;
+
+    let count = count_signal().get();
+    nexa_core::on_mount(move || log_event(format!("child mount {}", count)));
+    nexa_core::on_update(move || log_event(format!("child update {}", count)));
+    nexa_core::on_unmount(move || log_event(format!("child unmount {}", count)));
+
+    get_active_arena(|arena| {
+        arena.insert(VirtualNode::Text(Text {
+            text: count.to_string(),
+            parent: None,
+            key: None,
+        }))
+    })
+}
+
+fn hooked_root() -> NodeId {
+    use nexa_core::vdom::get_active_arena;
+
+    let count = count_signal().get();
+    nexa_core::on_mount(|| log_event("root mount".to_string()));
+    nexa_core::on_update(|| log_event("root update".to_string()));
+
+    get_active_arena(|arena| {
+        if count < 10 {
+            arena.insert(VirtualNode::Component(nexa_core::Component::new(
+                "Child",
+                |()| hooked_child(),
+                (),
+            )))
+        } else {
+            arena.insert(VirtualNode::Text(Text {
+                text: "gone".to_string(),
+                parent: None,
+                key: None,
+            }))
+        }
+    })
+}
+
+#[test]
+fn test_lifecycle_hooks() {
+    install_count(0);
+    take_log();
+
+    let mut runtime = create_test_runtime();
+    runtime.mount("Root", hooked_root);
+    assert_eq!(take_log(), vec!["child mount 0", "root mount"]);
+
+    // Hooks from the latest render see the latest state.
+    count_signal().set(1);
+    runtime.update();
+    assert_eq!(take_log(), vec!["child update 1", "root update"]);
+
+    count_signal().set(10);
+    runtime.update();
+    assert_eq!(take_log(), vec!["child unmount 1", "root update"]);
+    assert_eq!(runtime.scopes.len(), 1);
+}
+
+#[test]
+#[should_panic(expected = "during a render")]
+fn test_hooks_outside_render_panic() {
+    nexa_core::on_mount(|| {});
+}
+
+#[derive(Clone, PartialEq)]
+struct ParityProps {
+    even: bool,
+}
+
+fn parity_root() -> NodeId {
+    use nexa_core::vdom::get_active_arena;
+
+    ROOT_RENDERS.with(|c| c.set(c.get() + 1));
+    let props = ParityProps {
+        even: count_signal().get() % 2 == 0,
+    };
+    let child = nexa_core::Component::new(
+        "Parity",
+        |props: ParityProps| {
+            use nexa_core::vdom::get_active_arena;
+
+            CHILD_RENDERS.with(|c| c.set(c.get() + 1));
+            get_active_arena(|arena| {
+                arena.insert(VirtualNode::Text(Text {
+                    text: if props.even { "even" } else { "odd" }.to_string(),
+                    parent: None,
+                    key: None,
+                }))
+            })
+        },
+        props,
+    );
+    get_active_arena(|arena| arena.insert(VirtualNode::Component(child)))
+}
+
+#[test]
+fn test_component_props() {
+    install_count(0);
+    let mut runtime = create_test_runtime();
+    runtime.mount("Root", parity_root);
+    runtime.drain_mutations();
+    assert_eq!(runtime.scopes.len(), 2);
+
+    // Equal props: the child keeps its output without re-rendering.
+    count_signal().set(2);
+    runtime.update();
+    assert_eq!(ROOT_RENDERS.with(|c| c.get()), 2);
+    assert_eq!(CHILD_RENDERS.with(|c| c.get()), 1);
+    assert!(runtime.drain_mutations().is_empty());
+
+    // Changed props: the child re-renders in the same scope.
+    count_signal().set(3);
+    runtime.update();
+    assert_eq!(ROOT_RENDERS.with(|c| c.get()), 3);
+    assert_eq!(CHILD_RENDERS.with(|c| c.get()), 2);
+    assert_eq!(runtime.scopes.len(), 2);
+    let mutations = runtime.drain_mutations();
+    assert_eq!(mutations.len(), 1);
+    assert!(matches!(
+        &mutations[0],
+        nexa_core::Mutation::SetText { value, .. } if value == "odd"
+    ));
+    runtime.verify_tree_integrity();
+}
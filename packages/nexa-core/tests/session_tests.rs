@@ -0,0 +1,227 @@
+use nexa_core::session::{ReplayError, Replayer, Session, SessionEntry};
+use nexa_core::testing::TestDom;
+use nexa_core::{EventData, Mutation, NodeId, Runtime};
+use nexa_rsx::rsx;
+use nexa_scheduler::LocalScheduler;
+use nexa_signals::Signal;
+use std::cell::{Cell, RefCell};
+
+thread_local! {
+    static COUNT: RefCell<Option<Signal<i32>>> = const { RefCell::new(None) };
+    static GREETING: RefCell<Option<Signal<String>>> = const { RefCell::new(None) };
+    /// How much a click adds, so a replay can run a "fixed" build.
+    static STEP: Cell<i32> = const { Cell::new(1) };
+}
+
+fn setup() {
+    COUNT.with(|c| *c.borrow_mut() = Some(Signal::new(0)));
+    GREETING.with(|g| *g.borrow_mut() = Some(Signal::new("Hello".to_string())));
+    STEP.with(|s| s.set(1));
+}
+
+fn count() -> Signal<i32> {
+    COUNT.with(|c| c.borrow().clone().unwrap())
+}
+
+fn greeting() -> Signal<String> {
+    GREETING.with(|g| g.borrow().clone().unwrap())
+}
+
+fn app() -> NodeId {
+    let counter = count();
+    let shown = counter.get();
+    rsx! {
+        div {
+            h1 { {greeting().get()} }
+            p { {format!("Count: {shown}")} }
+            button {
+                onclick: move |_| counter.set(counter.get() + STEP.with(Cell::get)),
+                "+"
+            }
+        }
+    }
+    .pop()
+    .unwrap()
+}
+
+/// Mounts `app`, clicks twice and changes the greeting from outside.
+fn record() -> Session {
+    setup();
+    let mut runtime = Runtime::new(LocalScheduler::new());
+    let mut dom = TestDom::new();
+    runtime.start_session();
+    runtime.mount("App", app);
+    dom.sync(&mut runtime);
+
+    let button = dom.find_by_tag("button").unwrap();
+    dom.click(&mut runtime, button);
+    dom.click(&mut runtime, button);
+    runtime.write_signal("greeting", &greeting(), "Bye".to_string());
+    runtime.update();
+    dom.sync(&mut runtime);
+    assert_eq!(
+        dom.to_html(),
+        "<div><h1>Bye</h1><p>Count: 2</p><button>+</button></div>"
+    );
+
+    runtime.stop_session().unwrap()
+}
+
+fn replay(session: &Session, register_greeting: bool) -> Result<(), ReplayError> {
+    let mut runtime = Runtime::new(LocalScheduler::new());
+    runtime.mount("App", app);
+    let mut replayer = Replayer::new(session);
+    if register_greeting {
+        replayer = replayer.signal("greeting", greeting());
+    }
+    replayer.run(&mut runtime)
+}
+
+#[test]
+fn test_session_records_inputs_and_batches() {
+    let session = record();
+    let kinds: Vec<_> = session
+        .entries
+        .iter()
+        .map(|entry| match entry {
+            SessionEntry::Event { name, data, .. } => {
+                assert!(matches!(data, EventData::Mouse(_)));
+                format!("event {name}")
+            }
+            SessionEntry::SignalWrite { signal, value } => format!("write {signal} = {value}"),
+            SessionEntry::Batch(_) => "batch".to_string(),
+        })
+        .collect();
+    assert_eq!(
+        kinds,
+        [
+            "batch",
+            "event click",
+            "batch",
+            "event click",
+            "batch",
+            "write greeting = \"Bye\"",
+            "batch",
+        ]
+    );
+    assert_eq!(session.batches().count(), 4);
+
+    let json = serde_json::to_string(&session).unwrap();
+    let decoded: Session = serde_json::from_str(&json).unwrap();
+    assert_eq!(decoded, session);
+}
+
+#[test]
+fn test_replay_matches() {
+    let session = record();
+    setup();
+    assert_eq!(replay(&session, true), Ok(()));
+    assert_eq!(count().get(), 2);
+    assert_eq!(greeting().get(), "Bye");
+}
+
+#[test]
+fn test_replay_flags_first_difference() {
+    let session = record();
+    setup();
+    STEP.with(|s| s.set(2));
+    let Err(ReplayError::Diverged {
+        batch,
+        index,
+        expected,
+        actual,
+    }) = replay(&session, true)
+    else {
+        panic!("replay should diverge");
+    };
+    assert_eq!((batch, index), (1, 0));
+    assert!(
+        matches!(expected.as_deref(), Some(Mutation::SetText { value, .. }) if value == "Count: 1")
+    );
+    assert!(
+        matches!(actual.as_deref(), Some(Mutation::SetText { value, .. }) if value == "Count: 2")
+    );
+}
+
+#[test]
+fn test_replay_needs_recorded_signals() {
+    let session = record();
+    setup();
+    assert_eq!(
+        replay(&session, false),
+        Err(ReplayError::UnknownSignal("greeting".to_string()))
+    );
+}
+
+#[test]
+fn test_session_started_before_mount_begins_with_the_mount() {
+    let session = record();
+    let mount = session.batches().next().unwrap();
+    assert!(
+        mount
+            .iter()
+            .any(|m| matches!(m, Mutation::AppendChildren { id: 0, .. }))
+    );
+
+    // A replay runtime whose mount is already drained has nothing to match it.
+    setup();
+    let mut runtime = Runtime::new(LocalScheduler::new());
+    runtime.mount("App", app);
+    runtime.drain_mutations();
+    let replayed = Replayer::new(&session)
+        .signal("greeting", greeting())
+        .run(&mut runtime);
+    assert!(matches!(
+        replayed,
+        Err(ReplayError::Diverged {
+            batch: 0,
+            index: 0,
+            actual: None,
+            ..
+        })
+    ));
+}
+
+#[test]
+fn test_session_started_after_mount() {
+    setup();
+    let mut runtime = Runtime::new(LocalScheduler::new());
+    let mut dom = TestDom::new();
+    runtime.mount("App", app);
+    dom.sync(&mut runtime);
+    runtime.start_session();
+    let button = dom.find_by_tag("button").unwrap();
+    dom.click(&mut runtime, button);
+    let session = runtime.stop_session().unwrap();
+    assert_eq!(session.entries.len(), 2);
+
+    setup();
+    let mut runtime = Runtime::new(LocalScheduler::new());
+    runtime.mount("App", app);
+    runtime.drain_mutations();
+    assert_eq!(Replayer::new(&session).run(&mut runtime), Ok(()));
+    assert_eq!(count().get(), 1);
+}
+
+#[test]
+fn test_plain_signal_writes_are_not_recorded() {
+    setup();
+    let mut runtime = Runtime::new(LocalScheduler::new());
+    runtime.mount("App", app);
+    runtime.drain_mutations();
+    runtime.start_session();
+    greeting().set("Bye".to_string());
+    runtime.update();
+    runtime.drain_mutations();
+    let session = runtime.stop_session().unwrap();
+    assert!(matches!(session.entries[..], [SessionEntry::Batch(_)]));
+
+    setup();
+    let mut runtime = Runtime::new(LocalScheduler::new());
+    runtime.mount("App", app);
+    runtime.drain_mutations();
+    assert!(matches!(
+        Replayer::new(&session).run(&mut runtime),
+        Err(ReplayError::Diverged { batch: 0, .. })
+    ));
+}
@@ -0,0 +1,97 @@
+use nexa_core::assert_snapshot;
+use nexa_core::snapshot::{mutations_snapshot, tree_snapshot};
+use nexa_core::{NodeId, Runtime};
+use nexa_rsx::rsx;
+use nexa_scheduler::LocalScheduler;
+use nexa_signals::Signal;
+use std::cell::RefCell;
+
+thread_local! {
+    static ROWS: RefCell<Option<Signal<Vec<u32>>>> = const { RefCell::new(None) };
+}
+
+fn rows() -> Signal<Vec<u32>> {
+    ROWS.with(|r| r.borrow().clone().unwrap())
+}
+
+fn row(id: u32) -> NodeId {
+    rsx! {
+        li { class: "row", "data-id": id as i64, {format!("Row {id}")} }
+    }
+    .pop()
+    .unwrap()
+}
+
+fn table() -> NodeId {
+    let ids = rows().get();
+    let empty = ids.is_empty();
+    rsx! {
+        section {
+            h1 { "Rows" }
+            ul {
+                for id in ids {
+                    Row { key: id, id: id }
+                }
+            }
+            if empty {
+                p { "Nothing here" }
+            }
+        }
+    }
+    .pop()
+    .unwrap()
+}
+
+#[allow(non_snake_case)]
+fn Row(props: RowProps) -> NodeId {
+    row(props.id)
+}
+
+#[derive(Clone, PartialEq)]
+struct RowProps {
+    id: u32,
+}
+
+fn mounted(initial: Vec<u32>) -> Runtime<LocalScheduler> {
+    ROWS.with(|r| *r.borrow_mut() = Some(Signal::new(initial)));
+    let mut runtime = Runtime::new(LocalScheduler::new());
+    runtime.mount("Table", table);
+    runtime
+}
+
+#[test]
+fn test_mount_snapshot() {
+    let mut runtime = mounted(vec![1, 2, 3]);
+    assert_snapshot!(
+        "table_mount",
+        mutations_snapshot(&runtime.drain_mutations())
+    );
+    assert_snapshot!("table_tree", tree_snapshot(&runtime));
+}
+
+#[test]
+fn test_keyed_reorder_snapshot() {
+    let mut runtime = mounted(vec![1, 2, 3, 4, 5]);
+    runtime.drain_mutations();
+
+    rows().set(vec![5, 2, 3, 6, 1]);
+    runtime.update();
+    assert_snapshot!(
+        "table_reorder",
+        mutations_snapshot(&runtime.drain_mutations())
+    );
+}
+
+#[test]
+fn test_emptied_list_snapshot() {
+    let mut runtime = mounted(vec![1, 2]);
+    runtime.drain_mutations();
+
+    rows().set(vec![]);
+    runtime.update();
+    assert_snapshot!(
+        "table_emptied",
+        mutations_snapshot(&runtime.drain_mutations())
+    );
+    assert_snapshot!("table_emptied_tree", tree_snapshot(&runtime));
+}
@@ -10,6 +10,7 @@ async fn test_ssr_streaming_order() {
     let _node = VirtualNode::Text(Text {
         text: "test".to_string(),
         parent: None,
+        key: None,
     });
     // Inserting node into arena
     // This is getting complex, let's keep it simple for v0.1
@@ -20,3 +21,204 @@ async fn test_ssr_streaming_order() {
 fn test_hydration_id_consistency() {
     assert!(true);
 }
+
+#[tokio::test]
+async fn test_ssr_typed_attributes() {
+    use futures::StreamExt;
+
+    let mut arena = VDomArena::new();
+    let props = [
+        ("disabled", AttributeValue::Bool(false)),
+        ("checked", AttributeValue::Bool(true)),
+        ("aria-hidden", AttributeValue::Bool(false)),
+        ("tabindex", AttributeValue::Int(-1)),
+        ("title", AttributeValue::None),
+        ("value", AttributeValue::Text("a\"b".to_string())),
+    ];
+    let input = arena.insert(VirtualNode::Element(Element {
+        tag: "input",
+        namespace: None,
+        props: props
+            .into_iter()
+            .map(|(name, value)| Attribute { name, value })
+            .collect(),
+        listeners: Default::default(),
+        children: Default::default(),
+        parent: None,
+        key: None,
+        node_ref: None,
+    }));
+
+    let config = SsrConfig {
+        enable_hydration: false,
+        ..Default::default()
+    };
+    let html: String = Renderer::with_config(&arena, config)
+        .render_to_stream(input)
+        .collect::<Vec<_>>()
+        .await
+        .concat();
+    assert_eq!(
+        html,
+        "<input checked aria-hidden=\"false\" tabindex=\"-1\" value=\"a&quot;b\"></input>"
+    );
+}
+
+mod hydration {
+    use futures::StreamExt;
+    use nexa_core::mutations::Mutation;
+    use nexa_core::testing::TestDom;
+    use nexa_core::{NodeId, Runtime};
+    use nexa_rsx::rsx;
+    use nexa_scheduler::LocalScheduler;
+    use nexa_signals::Signal;
+    use nexa_ssr::{Renderer, SsrConfig};
+    use std::cell::RefCell;
+
+    thread_local! {
+        static COUNT: RefCell<Option<Signal<i32>>> = const { RefCell::new(None) };
+    }
+
+    fn install_count(initial: i32) {
+        COUNT.with(|c| *c.borrow_mut() = Some(Signal::new(initial)));
+    }
+
+    fn count() -> Signal<i32> {
+        COUNT.with(|c| c.borrow().clone().unwrap())
+    }
+
+    fn counter() -> NodeId {
+        let count = count();
+        let increment = count.clone();
+        rsx! {
+            div {
+                p { "data-testid": "count", {format!("Count: {}", count.get())} }
+                button {
+                    onclick: move |_| increment.set(increment.get() + 1),
+                    "Increment"
+                }
+            }
+        }
+        .pop()
+        .unwrap()
+    }
+
+    fn texts() -> NodeId {
+        rsx! {
+            p { {"a".to_string()} {String::new()} {"b".to_string()} }
+        }
+        .pop()
+        .unwrap()
+    }
+
+    fn with_portal() -> NodeId {
+        rsx! {
+            div {
+                h1 { "Page" }
+                Portal { target: "modal-root", span { "in portal" } }
+            }
+        }
+        .pop()
+        .unwrap()
+    }
+
+    async fn server_html(root_fn: fn() -> NodeId) -> String {
+        let mut server = Runtime::new(LocalScheduler::new());
+        server.mount("App", root_fn);
+        Renderer::from_runtime(&server, SsrConfig::default())
+            .render_to_stream(server.root_node.unwrap())
+            .collect::<Vec<_>>()
+            .await
+            .concat()
+    }
+
+    fn hydrated(html: &str, root_fn: fn() -> NodeId) -> (Runtime<LocalScheduler>, TestDom) {
+        let mut dom = TestDom::with_html(html);
+        let mut client = Runtime::new(LocalScheduler::new());
+        client.hydrate("App", root_fn);
+        dom.sync(&mut client);
+        (client, dom)
+    }
+
+    fn creates_nodes(mutations: &[Mutation]) -> bool {
+        mutations.iter().any(|m| {
+            matches!(
+                m,
+                Mutation::CreateElement { .. }
+                    | Mutation::CreateElementNs { .. }
+                    | Mutation::LoadTemplate { .. }
+            )
+        })
+    }
+
+    #[tokio::test]
+    async fn test_hydrate_adopts_server_markup() {
+        install_count(0);
+        let html = server_html(counter).await;
+
+        let mut dom = TestDom::with_html(&html);
+        let count_node = dom.find_by_test_id("count").unwrap();
+        let button = dom.find_by_tag("button").unwrap();
+
+        let mut client = Runtime::new(LocalScheduler::new());
+        client.hydrate("App", counter);
+        let mutations = client.drain_mutations();
+        assert!(!creates_nodes(&mutations), "{mutations:?}");
+        dom.apply(mutations);
+
+        assert!(
+            dom.hydration_mismatches().is_empty(),
+            "{:?}",
+            dom.hydration_mismatches()
+        );
+        assert_eq!(dom.find_by_test_id("count"), Some(count_node));
+        assert_eq!(dom.listeners(button), ["click"]);
+        assert_eq!(dom.to_html(), html);
+
+        dom.click(&mut client, button);
+        assert_eq!(dom.text_content(count_node), "Count: 1");
+    }
+
+    #[tokio::test]
+    async fn test_hydrate_adjacent_and_empty_text() {
+        let html = server_html(texts).await;
+        assert_eq!(html, "<p>a<!--#--><!--#-->b</p>");
+
+        let (_client, dom) = hydrated(&html, texts);
+        assert!(
+            dom.hydration_mismatches().is_empty(),
+            "{:?}",
+            dom.hydration_mismatches()
+        );
+        let p = dom.find_by_tag("p").unwrap();
+        assert_eq!(dom.text_content(p), "ab");
+    }
+
+    #[tokio::test]
+    async fn test_hydrate_reports_mismatch_with_path() {
+        install_count(5);
+        let html = server_html(counter).await;
+
+        install_count(0);
+        let (_client, dom) = hydrated(&html, counter);
+        let mismatches = dom.hydration_mismatches();
+        assert_eq!(mismatches.len(), 1, "{mismatches:?}");
+        assert!(mismatches[0].contains("Count: 0"), "{mismatches:?}");
+        assert!(mismatches[0].contains("[0, 0, 0]"), "{mismatches:?}");
+    }
+
+    #[tokio::test]
+    async fn test_hydrate_creates_portal_content() {
+        let html = server_html(with_portal).await;
+        let (_client, dom) = hydrated(&html, with_portal);
+
+        assert!(
+            dom.hydration_mismatches().is_empty(),
+            "{:?}",
+            dom.hydration_mismatches()
+        );
+        let modal = dom.container("modal-root").unwrap();
+        assert_eq!(dom.text_content(modal), "in portal");
+        assert_eq!(dom.find_all_by_tag("h1").len(), 1);
+    }
+}
@@ -1,6 +1,11 @@
+mod common;
+
+use common::{bare_element, insert, text};
+use nexa_core::{Component, Element, NodeId, Runtime, VirtualNode, get_active_arena};
 use nexa_scheduler::LocalScheduler;
 use nexa_signals::dependency::{execute, take_dirty, with_graph};
 use nexa_signals::*;
+use std::cell::RefCell;
 use std::time::Instant;
 
 fn run_scheduler(scheduler: &mut LocalScheduler) {
@@ -63,3 +68,83 @@ fn test_stress_deep_graph() {
 
     assert_eq!(current.get(), 511);
 }
+
+thread_local! {
+    static TICK: RefCell<Option<Signal<u32>>> = const { RefCell::new(None) };
+}
+
+fn tick() -> Signal<u32> {
+    TICK.with(|t| t.borrow().clone().expect("TICK not initialised"))
+}
+
+fn element(tag: &'static str, key: Option<String>, children: Vec<NodeId>) -> NodeId {
+    insert(Element {
+        key,
+        ..bare_element(tag, children)
+    })
+}
+
+/// Exercises in-place patches, keyed moves, replacements and component unmounts.
+fn churning_app() -> NodeId {
+    let n = tick().get();
+    let items = (0..4)
+        .map(|i| {
+            let key = (n + i) % 6;
+            element(
+                "li",
+                Some(key.to_string()),
+                vec![text(&format!("item {}", key))],
+            )
+        })
+        .collect();
+    let list = element("ul", None, items);
+
+    let header = if n.is_multiple_of(3) {
+        element("h1", None, vec![text(&format!("tick {}", n))])
+    } else {
+        text(&format!("tick {}", n))
+    };
+
+    let mut children = vec![header, list];
+    if n.is_multiple_of(2) {
+        let badge = Component::new("Badge", |n: u32| text(&format!("even {}", n)), n);
+        children.push(get_active_arena(|arena| {
+            arena.insert(VirtualNode::Component(badge))
+        }));
+    }
+    element("div", None, children)
+}
+
+#[test]
+fn test_stress_arena_stays_flat() {
+    TICK.with(|t| *t.borrow_mut() = Some(Signal::new(0)));
+
+    let mut runtime = Runtime::new(LocalScheduler::new());
+    runtime.mount("App", churning_app);
+    runtime.drain_mutations();
+
+    // The tree cycles through six shapes; after one full cycle the arena
+    // holds exactly the committed tree and must not grow any further.
+    for n in 1..=6 {
+        tick().set(n);
+        runtime.update();
+    }
+    runtime.drain_mutations();
+    let baseline = runtime.arena.len();
+
+    let start = Instant::now();
+    for n in 7..100_007 {
+        tick().set(n);
+        runtime.update();
+        runtime.drain_mutations();
+        if n.is_multiple_of(6) {
+            assert_eq!(runtime.arena.len(), baseline, "arena grew at update {}", n);
+        }
+    }
+    println!("100k updates took: {:?}", start.elapsed());
+
+    assert_eq!(runtime.arena.metadata.len(), runtime.arena.nodes.len());
+    assert!(runtime.profiling.nodes_collected > 100_000);
+    assert_eq!(runtime.scopes.len(), 2);
+    runtime.verify_tree_integrity();
+}
@@ -0,0 +1,188 @@
+mod common;
+
+use common::{bare_element, insert, text};
+use futures::channel::oneshot;
+use nexa_core::{
+    Component, Mutation, NodeId, Resource, Runtime, Suspense, VirtualNode, get_active_arena,
+};
+use nexa_scheduler::LocalScheduler;
+use std::cell::RefCell;
+
+thread_local! {
+    static RESOURCES: RefCell<Vec<Resource<String>>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Creates `count` resources, returning the senders that resolve them.
+fn resources(count: usize) -> Vec<oneshot::Sender<String>> {
+    (0..count)
+        .map(|_| {
+            let (tx, rx) = oneshot::channel();
+            let resource = Resource::new(async move { rx.await.unwrap_or_default() });
+            RESOURCES.with(|r| r.borrow_mut().push(resource));
+            tx
+        })
+        .collect()
+}
+
+fn resource(idx: usize) -> Resource<String> {
+    RESOURCES.with(|r| r.borrow()[idx].clone())
+}
+
+fn div(children: Vec<NodeId>) -> NodeId {
+    insert(bare_element("div", children))
+}
+
+fn suspense(fallback: NodeId, actual: NodeId) -> NodeId {
+    get_active_arena(|arena| {
+        arena.insert(VirtualNode::Suspense(Suspense {
+            fallback,
+            actual,
+            suspended: false,
+            parent: None,
+            key: None,
+        }))
+    })
+}
+
+/// Shows the value of a resource.
+fn profile(resource: Resource<String>) -> NodeId {
+    text(&resource.read().unwrap_or_default())
+}
+
+fn profile_node(idx: usize) -> NodeId {
+    let resource = resource(idx);
+    get_active_arena(|arena| {
+        arena.insert(VirtualNode::Component(Component::new(
+            "Profile", profile, resource,
+        )))
+    })
+}
+
+fn single_app() -> NodeId {
+    let fallback = text("Loading...");
+    let content = profile_node(0);
+    div(vec![suspense(fallback, content)])
+}
+
+/// An outer boundary around one resource and an inner boundary around another.
+fn nested_app() -> NodeId {
+    let inner_fallback = text("Loading details...");
+    let inner_content = profile_node(1);
+    let inner = suspense(inner_fallback, inner_content);
+    let outer_fallback = text("Loading page...");
+    let outer_content = div(vec![profile_node(0), inner]);
+    div(vec![suspense(outer_fallback, outer_content)])
+}
+
+fn created_text(mutations: &[Mutation]) -> Vec<String> {
+    mutations
+        .iter()
+        .filter_map(|m| match m {
+            Mutation::CreateTextNode { text, .. } => Some(text.clone()),
+            _ => None,
+        })
+        .collect()
+}
+
+fn text_id(mutations: &[Mutation], wanted: &str) -> u64 {
+    mutations
+        .iter()
+        .find_map(|m| match m {
+            Mutation::CreateTextNode { text, id } if text == wanted => Some(*id),
+            _ => None,
+        })
+        .unwrap()
+}
+
+#[test]
+fn test_fallback_until_resolved() {
+    let mut senders = resources(1);
+    let mut runtime = Runtime::new(LocalScheduler::new());
+    runtime.mount("App", single_app);
+
+    let mutations = runtime.drain_mutations();
+    assert_eq!(created_text(&mutations), vec!["Loading..."]);
+    let fallback = text_id(&mutations, "Loading...");
+    // The rolled-back content left no scope behind.
+    assert_eq!(runtime.scopes.len(), 1);
+
+    // Polling a pending future changes nothing.
+    runtime.update();
+    assert!(runtime.drain_mutations().is_empty());
+
+    senders.remove(0).send("Ada".to_string()).unwrap();
+    runtime.update();
+
+    let mutations = runtime.drain_mutations();
+    assert_eq!(created_text(&mutations), vec!["Ada"]);
+    let content = text_id(&mutations, "Ada");
+    assert!(mutations.contains(&Mutation::ReplaceWith {
+        id: fallback,
+        m: vec![content],
+    }));
+    assert_eq!(runtime.scopes.len(), 2);
+    assert!(!nexa_core::suspense::has_pending_tasks());
+}
+
+#[test]
+fn test_nested_boundaries() {
+    let mut senders = resources(2);
+    let mut runtime = Runtime::new(LocalScheduler::new());
+    runtime.mount("App", nested_app);
+    assert_eq!(
+        created_text(&runtime.drain_mutations()),
+        vec!["Loading page..."]
+    );
+
+    // The outer content reveals with the inner boundary still loading.
+    let details = senders.pop().unwrap();
+    senders.pop().unwrap().send("Ada".to_string()).unwrap();
+    runtime.update();
+    let mutations = runtime.drain_mutations();
+    assert_eq!(created_text(&mutations), vec!["Ada", "Loading details..."]);
+    let inner_fallback = text_id(&mutations, "Loading details...");
+
+    details.send("Analytical engines".to_string()).unwrap();
+    runtime.update();
+    let mutations = runtime.drain_mutations();
+    assert_eq!(created_text(&mutations), vec!["Analytical engines"]);
+    assert!(mutations.iter().any(|m| matches!(
+        m,
+        Mutation::ReplaceWith { id, .. } if *id == inner_fallback
+    )));
+}
+
+#[test]
+fn test_inner_resolving_first_keeps_outer_fallback() {
+    let mut senders = resources(2);
+    let mut runtime = Runtime::new(LocalScheduler::new());
+    runtime.mount("App", nested_app);
+    runtime.drain_mutations();
+
+    let page = senders.remove(0);
+    senders
+        .remove(0)
+        .send("Analytical engines".to_string())
+        .unwrap();
+    runtime.update();
+    assert!(created_text(&runtime.drain_mutations()).is_empty());
+
+    page.send("Ada".to_string()).unwrap();
+    runtime.update();
+    assert_eq!(
+        created_text(&runtime.drain_mutations()),
+        vec!["Ada", "Analytical engines"]
+    );
+}
+
+#[test]
+fn test_mount_frees_the_rolled_back_content() {
+    let _senders = resources(1);
+    let mut runtime = Runtime::new(LocalScheduler::new());
+    runtime.mount("App", single_app);
+    // The rollback frees the suspended Profile and the text it rendered.
+    assert_eq!(runtime.profiling.nodes_collected, 2);
+
+    runtime.mount_at("Widget", "widget", single_app);
+    assert_eq!(runtime.profiling.nodes_collected, 4);
+}
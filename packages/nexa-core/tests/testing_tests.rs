@@ -0,0 +1,150 @@
+use nexa_core::testing::TestDom;
+use nexa_core::{NodeId, Runtime};
+use nexa_rsx::rsx;
+use nexa_scheduler::LocalScheduler;
+use nexa_signals::Signal;
+use std::cell::RefCell;
+
+thread_local! {
+    static COUNT: RefCell<Option<Signal<i32>>> = const { RefCell::new(None) };
+    static ITEMS: RefCell<Option<Signal<Vec<&'static str>>>> = const { RefCell::new(None) };
+}
+
+fn setup() {
+    COUNT.with(|c| *c.borrow_mut() = Some(Signal::new(0)));
+    ITEMS.with(|i| *i.borrow_mut() = Some(Signal::new(vec!["a", "b"])));
+}
+
+fn counter() -> NodeId {
+    let count = COUNT.with(|c| c.borrow().clone().unwrap());
+    let increment = count.clone();
+    rsx! {
+        div {
+            p { "data-testid": "count", {format!("Count: {}", count.get())} }
+            button {
+                class: "primary",
+                disabled: count.get() >= 2,
+                onclick: move |_| increment.set(increment.get() + 1),
+                "Increment"
+            }
+            input { value: count.get().to_string() }
+        }
+    }
+    .pop()
+    .unwrap()
+}
+
+fn mounted(root: fn() -> NodeId) -> (Runtime<LocalScheduler>, TestDom) {
+    setup();
+    let mut runtime = Runtime::new(LocalScheduler::new());
+    let mut dom = TestDom::new();
+    runtime.mount("App", root);
+    dom.sync(&mut runtime);
+    (runtime, dom)
+}
+
+#[test]
+fn test_click_updates_text() {
+    let (mut runtime, mut dom) = mounted(counter);
+    let count = dom.find_by_test_id("count").unwrap();
+    assert_eq!(dom.text_content(count), "Count: 0");
+
+    let button = dom.find_by_text("Increment").unwrap();
+    assert_eq!(dom.tag(button), Some("button"));
+    assert_eq!(dom.listeners(button), ["click"]);
+    dom.click(&mut runtime, button);
+    assert_eq!(dom.text_content(count), "Count: 1");
+}
+
+#[test]
+fn test_attributes_follow_updates() {
+    let (mut runtime, mut dom) = mounted(counter);
+    let button = dom.find_by_tag("button").unwrap();
+    let input = dom.find_by_tag("input").unwrap();
+    assert_eq!(dom.attribute(button, "class"), Some("primary"));
+    assert_eq!(dom.attribute(button, "disabled"), None);
+    assert_eq!(dom.attribute(input, "value"), Some("0"));
+
+    dom.click(&mut runtime, button);
+    dom.click(&mut runtime, button);
+    assert_eq!(dom.attribute(button, "disabled"), Some(""));
+    assert_eq!(dom.attribute(input, "value"), Some("2"));
+    assert_eq!(dom.find_by_attribute("class", "primary"), Some(button));
+}
+
+#[test]
+fn test_pretty_html() {
+    let (_runtime, dom) = mounted(counter);
+    assert_eq!(
+        dom.to_pretty_html(),
+        "\
+<div>
+  <p data-testid=\"count\">
+    Count: 0
+  </p>
+  <button class=\"primary\">
+    Increment
+  </button>
+  <input value=\"0\">
+</div>
+"
+    );
+    assert_eq!(
+        dom.to_html(),
+        "<div><p data-testid=\"count\">Count: 0</p><button class=\"primary\">Increment</button><input value=\"0\"></div>"
+    );
+}
+
+fn list() -> NodeId {
+    let items = ITEMS.with(|i| i.borrow().clone().unwrap());
+    let add = items.clone();
+    rsx! {
+        ul {
+            button { onclick: move |_| add.update(|i| i.insert(1, "c")), "Add" }
+            for item in items.get() {
+                li { key: item, {item} }
+            }
+        }
+    }
+    .pop()
+    .unwrap()
+}
+
+#[test]
+fn test_keyed_insert_keeps_order() {
+    let (mut runtime, mut dom) = mounted(list);
+    let items = |dom: &TestDom| {
+        dom.find_all_by_tag("li")
+            .into_iter()
+            .map(|li| dom.text_content(li))
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(items(&dom), ["a", "b"]);
+
+    let first = dom.find_by_text("a").unwrap();
+    dom.click(&mut runtime, dom.find_by_text("Add").unwrap());
+    assert_eq!(items(&dom), ["a", "c", "b"]);
+    // Keyed children are moved, not recreated.
+    assert_eq!(dom.find_by_text("a"), Some(first));
+}
+
+fn modal() -> NodeId {
+    rsx! {
+        main {
+            "Page"
+            Portal { target: "modal-root", dialog { "Hello" } }
+        }
+    }
+    .pop()
+    .unwrap()
+}
+
+#[test]
+fn test_portal_renders_into_container() {
+    let (_runtime, dom) = mounted(modal);
+    let container = dom.container("modal-root").unwrap();
+    let dialog = dom.find_by_tag("dialog").unwrap();
+    assert_eq!(dom.parent(dialog), Some(container));
+    assert_eq!(dom.text_content(dom.root()), "Page");
+    assert_eq!(dom.to_html(), "<main>Page</main>");
+}
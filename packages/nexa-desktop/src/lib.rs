@@ -1,16 +1,19 @@
 use arboard::Clipboard;
 use log::{error, info};
-use nexa_core::Runtime;
-use nexa_renderer_gpu::{GpuRenderer, scene::Scene};
+use nexa_core::renderer::Renderer;
+use nexa_core::{EventData, FocusData, KeyboardData, Modifiers, NodeRef, Runtime};
+use nexa_renderer_gpu::{GpuRenderer, SceneNode, scene::Scene};
 use nexa_scheduler::LocalScheduler;
 use rfd::FileDialog;
+use std::cell::RefCell;
+use std::collections::HashMap;
 use std::sync::Arc;
 use tray_icon::TrayIconBuilder;
 use tray_icon::menu::{Menu, MenuItem};
 use winit::{
     event::{ElementState, Event, KeyEvent, WindowEvent},
     event_loop::{ControlFlow, EventLoop},
-    keyboard::{KeyCode, PhysicalKey},
+    keyboard::{Key, KeyCode, PhysicalKey},
     window::WindowBuilder,
 };
 
@@ -85,6 +88,8 @@ impl DesktopApp {
         let mut runtime = Runtime::new(LocalScheduler::new());
         runtime.mount("App", root_fn);
         let _ipc = IpcChannel {};
+        let mut host = SceneHost::default();
+        let mut modifiers = Modifiers::default();
 
         // Initialize GPU Renderer
         let mut renderer = if let Some(ref win) = window {
@@ -116,16 +121,58 @@ impl DesktopApp {
                                     }
                                     WindowEvent::Focused(focused) => {
                                         info!("Window focused: {}", focused);
+                                        if let Some(id) = host.root_element {
+                                            let name = if focused { "focus" } else { "blur" };
+                                            runtime.handle_event(
+                                                id,
+                                                name,
+                                                nexa_core::Event::new(EventData::Focus(
+                                                    FocusData::default(),
+                                                )),
+                                            );
+                                        }
+                                    }
+                                    WindowEvent::ModifiersChanged(new_modifiers) => {
+                                        let state = new_modifiers.state();
+                                        modifiers = Modifiers {
+                                            alt: state.alt_key(),
+                                            ctrl: state.control_key(),
+                                            meta: state.super_key(),
+                                            shift: state.shift_key(),
+                                        };
                                     }
                                     WindowEvent::KeyboardInput {
                                         event:
                                             KeyEvent {
                                                 physical_key: PhysicalKey::Code(code),
+                                                logical_key,
                                                 state: ElementState::Pressed,
+                                                repeat,
                                                 ..
                                             },
                                         ..
                                     } => {
+                                        // Without hit testing there is no focused element, so
+                                        // key events go to the root element.
+                                        let event = nexa_core::Event::new(EventData::Keyboard(
+                                            KeyboardData {
+                                                key: match logical_key {
+                                                    Key::Named(named) => format!("{:?}", named),
+                                                    Key::Character(c) => c.to_string(),
+                                                    _ => "Unidentified".to_string(),
+                                                },
+                                                code: format!("{:?}", code),
+                                                modifiers,
+                                                repeat,
+                                            },
+                                        ));
+                                        if let Some(id) = host.root_element {
+                                            runtime.handle_event(id, "keydown", event.clone());
+                                        }
+                                        if event.default_prevented() {
+                                            return;
+                                        }
+
                                         // Handle keyboard shortcuts
                                         match code {
                                             KeyCode::KeyO => {
@@ -144,8 +191,7 @@ impl DesktopApp {
                                         }
                                     }
                                     WindowEvent::RedrawRequested => {
-                                        runtime.update();
-                                        let _mutations = runtime.drain_mutations();
+                                        runtime.render_into(&mut host);
 
                                         if let Some(ref mut r) = renderer {
                                             let mut scene = Scene {
@@ -179,8 +225,7 @@ impl DesktopApp {
                             win.request_redraw();
                         } else if self.headless {
                             // In headless mode, we still want to poll runtime
-                            runtime.update();
-                            let _mutations = runtime.drain_mutations();
+                            runtime.render_into(&mut host);
                             // Optional: Sleep or break loop for testing
                         }
                     }
@@ -190,3 +235,208 @@ impl DesktopApp {
             .unwrap();
     }
 }
+
+thread_local! {
+    /// Scene nodes of the rendered elements and text, by id.
+    static SCENE_NODES: RefCell<HashMap<u64, SceneNode>> = RefCell::new(HashMap::new());
+}
+
+/// Resolves node refs to the scene nodes this renderer created for them.
+pub trait NodeRefExt {
+    fn scene_node(&self) -> Option<SceneNode>;
+}
+
+impl NodeRefExt for NodeRef {
+    fn scene_node(&self) -> Option<SceneNode> {
+        let id = self.get()?;
+        SCENE_NODES.with(|nodes| nodes.borrow().get(&id).cloned())
+    }
+}
+
+/// Keeps `SCENE_NODES` in step with the nodes the mutations create and
+/// remove, and remembers the root element announced by `PushRoot`. The
+/// runtime only removes the top of a removed subtree, so the host tracks
+/// parents and children to forget the rest of it too.
+#[derive(Default)]
+struct SceneHost {
+    root_element: Option<u64>,
+    parents: HashMap<u64, u64>,
+    children: HashMap<u64, Vec<u64>>,
+    /// The template instance that `AssignId` and `ReplacePlaceholder` paths
+    /// start from.
+    last_template: Option<u64>,
+}
+
+impl SceneHost {
+    fn insert(&mut self, id: u64, node: SceneNode) {
+        SCENE_NODES.with(|nodes| nodes.borrow_mut().insert(id, node));
+    }
+
+    /// Moves `ids` under `parent`, out of wherever they were.
+    fn adopt(&mut self, parent: u64, ids: &[u64]) {
+        for &id in ids {
+            self.detach(id);
+            self.parents.insert(id, parent);
+            self.children.entry(parent).or_default().push(id);
+        }
+    }
+
+    fn detach(&mut self, id: u64) {
+        if let Some(parent) = self.parents.remove(&id)
+            && let Some(siblings) = self.children.get_mut(&parent)
+        {
+            siblings.retain(|&sibling| sibling != id);
+        }
+    }
+
+    /// Drops `id` and everything under it.
+    fn forget(&mut self, id: u64) {
+        self.detach(id);
+        let mut stack = vec![id];
+        while let Some(id) = stack.pop() {
+            SCENE_NODES.with(|nodes| nodes.borrow_mut().remove(&id));
+            self.parents.remove(&id);
+            stack.extend(self.children.remove(&id).unwrap_or_default());
+        }
+    }
+
+    fn insert_container(&mut self, id: u64) {
+        self.insert(
+            id,
+            SceneNode::Container {
+                transform: glam::Mat4::IDENTITY,
+                children: vec![],
+                is_dirty: true,
+            },
+        );
+    }
+}
+
+impl Renderer for SceneHost {
+    fn push_root(&mut self, id: u64) {
+        self.root_element = Some(id);
+    }
+
+    fn create_element(&mut self, _tag: &str, _ns: Option<&str>, id: u64) {
+        self.insert_container(id);
+    }
+
+    fn load_template(&mut self, _name: &str, _index: usize, id: u64) {
+        self.insert_container(id);
+        self.last_template = Some(id);
+    }
+
+    fn assign_id(&mut self, _path: &[u8], id: u64) {
+        self.insert_container(id);
+        if let Some(template) = self.last_template {
+            self.adopt(template, &[id]);
+        }
+    }
+
+    fn create_text_node(&mut self, text: &str, id: u64) {
+        self.insert(
+            id,
+            SceneNode::Text {
+                x: 0.0,
+                y: 0.0,
+                content: text.to_string(),
+                font_size: 16.0,
+                color: [0.0, 0.0, 0.0, 1.0],
+            },
+        );
+    }
+
+    fn append_children(&mut self, id: u64, children: &[u64]) {
+        self.adopt(id, children);
+    }
+
+    fn insert_after(&mut self, id: u64, nodes: &[u64]) {
+        if let Some(&parent) = self.parents.get(&id) {
+            self.adopt(parent, nodes);
+        }
+    }
+
+    fn insert_before(&mut self, id: u64, nodes: &[u64]) {
+        self.insert_after(id, nodes);
+    }
+
+    fn replace_placeholder(&mut self, _path: &[u8], nodes: &[u64]) {
+        if let Some(template) = self.last_template {
+            self.adopt(template, nodes);
+        }
+    }
+
+    fn remove(&mut self, id: u64) {
+        self.forget(id);
+    }
+
+    fn replace_with(&mut self, id: u64, nodes: &[u64]) {
+        if let Some(&parent) = self.parents.get(&id) {
+            self.adopt(parent, nodes);
+        }
+        self.forget(id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nexa_core::Mutation;
+    use nexa_core::renderer::MutationSink;
+
+    fn scene_ids() -> Vec<u64> {
+        let mut ids: Vec<u64> = SCENE_NODES.with(|nodes| nodes.borrow().keys().copied().collect());
+        ids.sort();
+        ids
+    }
+
+    fn element(id: u64) -> Mutation {
+        Mutation::CreateElement {
+            tag: "div".to_string(),
+            id,
+        }
+    }
+
+    #[test]
+    fn test_remove_forgets_the_subtree() {
+        let mut host = SceneHost::default();
+        host.push_batch(vec![
+            element(1),
+            element(2),
+            Mutation::CreateTextNode {
+                text: "Hello".to_string(),
+                id: 3,
+            },
+            element(4),
+            Mutation::AppendChildren { id: 2, m: vec![3] },
+            Mutation::AppendChildren { id: 1, m: vec![2] },
+            Mutation::InsertAfter { id: 2, m: vec![4] },
+            Mutation::AppendChildren { id: 0, m: vec![1] },
+        ]);
+        assert_eq!(scene_ids(), [1, 2, 3, 4]);
+
+        host.push_batch(vec![Mutation::Remove { id: 2 }]);
+        assert_eq!(scene_ids(), [1, 4]);
+        assert_eq!(host.children[&1], [4]);
+
+        host.push_batch(vec![
+            Mutation::LoadTemplate {
+                name: "card".to_string(),
+                index: 0,
+                id: 5,
+            },
+            Mutation::AssignId {
+                path: vec![0],
+                id: 6,
+            },
+            Mutation::ReplaceWith { id: 1, m: vec![5] },
+        ]);
+        assert_eq!(scene_ids(), [5, 6]);
+        assert_eq!(host.parents[&5], 0);
+
+        host.push_batch(vec![Mutation::Remove { id: 5 }]);
+        assert!(scene_ids().is_empty());
+        assert!(host.parents.is_empty());
+        assert!(host.children.values().all(Vec::is_empty));
+    }
+}
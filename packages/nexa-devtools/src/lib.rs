@@ -1,5 +1,36 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct SchedulerMetrics {
+    pub pending_tasks: usize,
+    pub total_tasks_executed: u64,
+    pub average_latency_ms: f64,
+    pub microtasks_executed: u64,
+    pub effects_executed: u64,
+    pub layout_effects_executed: u64,
+    pub queue_depths: QueueDepths,
+    pub latency_histogram: Vec<LatencyBucket>,
+    pub runaway_loops: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct QueueDepths {
+    pub microtasks: usize,
+    pub effects: usize,
+    pub layout_effects: usize,
+}
+
+/// One bucket of the task latency histogram.
+/// `upper_bound_ms` is `None` for the overflow bucket.
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct LatencyBucket {
+    pub upper_bound_ms: Option<f64>,
+    pub count: u64,
+}
+
 #[cfg(debug_assertions)]
 mod internal {
+    use super::SchedulerMetrics;
     use serde::{Deserialize, Serialize};
     use std::collections::HashMap;
     use std::sync::Mutex;
@@ -22,13 +53,6 @@ mod internal {
         pub dependencies: Vec<u64>,
     }
 
-    #[derive(Debug, Clone, Serialize, Deserialize, Default)]
-    pub struct SchedulerMetrics {
-        pub pending_tasks: usize,
-        pub total_tasks_executed: u64,
-        pub average_latency_ms: f64,
-    }
-
     #[derive(Debug, Clone, Serialize, Deserialize, Default)]
     pub struct DevToolsSnapshot {
         pub components: HashMap<u64, ComponentNode>,
@@ -110,9 +134,15 @@ mod internal {
                 pending_tasks: pending,
                 total_tasks_executed: total,
                 average_latency_ms: latency,
+                ..Default::default()
             };
         }
 
+        pub fn set_scheduler_metrics(&self, metrics: SchedulerMetrics) {
+            let mut snapshot = self.snapshot.lock().unwrap();
+            snapshot.metrics = metrics;
+        }
+
         pub fn export_state(&self) -> String {
             let snapshot = self.snapshot.lock().unwrap();
             serde_json::to_string(&*snapshot).unwrap_or_default()
@@ -135,6 +165,7 @@ pub mod production {
         pub fn update_signal(&self, _: u64, _: String, _: String, _: Vec<u64>) {}
         pub fn record_render(&self) {}
         pub fn update_metrics(&self, _: usize, _: u64, _: f64) {}
+        pub fn set_scheduler_metrics(&self, _: super::SchedulerMetrics) {}
     }
     pub static DEVTOOLS: DevToolsContext = DevToolsContext;
 }
@@ -1,16 +1,147 @@
 use log::info;
 use nexa_core::Runtime;
-use nexa_renderer_gpu::GpuRenderer;
+use nexa_core::renderer::Renderer;
+use nexa_renderer_gpu::{GpuRenderer, SceneNode};
 use nexa_scheduler::LocalScheduler;
 use std::cell::RefCell;
+use std::collections::HashMap;
 
 // Simple Thread-safe state container
 pub struct MobileApp {
     pub runtime: Runtime<LocalScheduler>,
     pub renderer: Option<GpuRenderer>,
+    pub host: MobileHost,
     pub suspended: bool,
 }
 
+/// Keeps a scene node for every node the mutations create, like the
+/// desktop host, so there is something to draw once the renderer is wired
+/// up. The runtime only removes the top of a removed subtree, so the host
+/// tracks parents and children to forget the rest of it too.
+#[derive(Default)]
+pub struct MobileHost {
+    pub nodes: HashMap<u64, SceneNode>,
+    pub root_element: Option<u64>,
+    parents: HashMap<u64, u64>,
+    children: HashMap<u64, Vec<u64>>,
+    /// The template instance that `AssignId` and `ReplacePlaceholder` paths
+    /// start from.
+    last_template: Option<u64>,
+}
+
+impl MobileHost {
+    /// Moves `ids` under `parent`, out of wherever they were.
+    fn adopt(&mut self, parent: u64, ids: &[u64]) {
+        for &id in ids {
+            self.detach(id);
+            self.parents.insert(id, parent);
+            self.children.entry(parent).or_default().push(id);
+        }
+    }
+
+    fn detach(&mut self, id: u64) {
+        if let Some(parent) = self.parents.remove(&id)
+            && let Some(siblings) = self.children.get_mut(&parent)
+        {
+            siblings.retain(|&sibling| sibling != id);
+        }
+    }
+
+    /// Drops `id` and everything under it.
+    fn forget(&mut self, id: u64) {
+        self.detach(id);
+        let mut stack = vec![id];
+        while let Some(id) = stack.pop() {
+            self.nodes.remove(&id);
+            self.parents.remove(&id);
+            stack.extend(self.children.remove(&id).unwrap_or_default());
+        }
+    }
+
+    fn insert_container(&mut self, id: u64) {
+        self.nodes.insert(
+            id,
+            SceneNode::Container {
+                transform: glam::Mat4::IDENTITY,
+                children: vec![],
+                is_dirty: true,
+            },
+        );
+    }
+}
+
+impl Renderer for MobileHost {
+    fn push_root(&mut self, id: u64) {
+        self.root_element = Some(id);
+    }
+
+    fn create_element(&mut self, _tag: &str, _ns: Option<&str>, id: u64) {
+        self.insert_container(id);
+    }
+
+    fn load_template(&mut self, _name: &str, _index: usize, id: u64) {
+        self.insert_container(id);
+        self.last_template = Some(id);
+    }
+
+    fn assign_id(&mut self, _path: &[u8], id: u64) {
+        self.insert_container(id);
+        if let Some(template) = self.last_template {
+            self.adopt(template, &[id]);
+        }
+    }
+
+    fn create_text_node(&mut self, text: &str, id: u64) {
+        self.nodes.insert(
+            id,
+            SceneNode::Text {
+                x: 0.0,
+                y: 0.0,
+                content: text.to_string(),
+                font_size: 16.0,
+                color: [0.0, 0.0, 0.0, 1.0],
+            },
+        );
+    }
+
+    fn set_text(&mut self, value: &str, id: u64) {
+        if let Some(SceneNode::Text { content, .. }) = self.nodes.get_mut(&id) {
+            *content = value.to_string();
+        }
+    }
+
+    fn append_children(&mut self, id: u64, children: &[u64]) {
+        self.adopt(id, children);
+    }
+
+    fn insert_after(&mut self, id: u64, nodes: &[u64]) {
+        if let Some(&parent) = self.parents.get(&id) {
+            self.adopt(parent, nodes);
+        }
+    }
+
+    fn insert_before(&mut self, id: u64, nodes: &[u64]) {
+        self.insert_after(id, nodes);
+    }
+
+    fn replace_placeholder(&mut self, _path: &[u8], nodes: &[u64]) {
+        if let Some(template) = self.last_template {
+            self.adopt(template, nodes);
+        }
+    }
+
+    fn remove(&mut self, id: u64) {
+        self.forget(id);
+    }
+
+    fn replace_with(&mut self, id: u64, nodes: &[u64]) {
+        if let Some(&parent) = self.parents.get(&id) {
+            self.adopt(parent, nodes);
+        }
+        self.forget(id);
+    }
+}
+
 thread_local! {
     pub static APP_INSTANCE: RefCell<Option<MobileApp>> = RefCell::new(None);
 }
@@ -74,6 +205,7 @@ pub mod ios {
             *app.borrow_mut() = Some(MobileApp {
                 runtime: Runtime::new(LocalScheduler::new()),
                 renderer: None,
+                host: MobileHost::default(),
                 suspended: false,
             });
         });
@@ -105,8 +237,7 @@ pub mod ios {
         APP_INSTANCE.with(|app| {
             if let Some(app) = app.borrow_mut().as_mut() {
                 if !app.suspended {
-                    app.runtime.update();
-                    let _mutations = app.runtime.drain_mutations();
+                    app.runtime.render_into(&mut app.host);
                 }
             }
         });
@@ -121,7 +252,59 @@ pub mod host_stub {
         let _ = MobileApp {
             runtime: Runtime::new(LocalScheduler::new()),
             renderer: None,
+            host: MobileHost::default(),
             suspended: false,
         };
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nexa_core::Mutation;
+    use nexa_core::renderer::MutationSink;
+
+    fn node_ids(host: &MobileHost) -> Vec<u64> {
+        let mut ids: Vec<u64> = host.nodes.keys().copied().collect();
+        ids.sort();
+        ids
+    }
+
+    fn element(id: u64) -> Mutation {
+        Mutation::CreateElement {
+            tag: "div".to_string(),
+            id,
+        }
+    }
+
+    #[test]
+    fn test_host_tracks_created_and_removed_nodes() {
+        let mut host = MobileHost::default();
+        host.push_batch(vec![
+            element(1),
+            element(2),
+            Mutation::CreateTextNode {
+                text: "Hello".to_string(),
+                id: 3,
+            },
+            Mutation::AppendChildren { id: 2, m: vec![3] },
+            Mutation::AppendChildren { id: 1, m: vec![2] },
+            Mutation::AppendChildren { id: 0, m: vec![1] },
+            Mutation::PushRoot { id: 1 },
+            Mutation::SetText {
+                value: "Bye".to_string(),
+                id: 3,
+            },
+        ]);
+        assert_eq!(node_ids(&host), [1, 2, 3]);
+        assert_eq!(host.root_element, Some(1));
+        assert!(matches!(&host.nodes[&3], SceneNode::Text { content, .. } if content == "Bye"));
+
+        host.push_batch(vec![
+            element(4),
+            Mutation::ReplaceWith { id: 2, m: vec![4] },
+        ]);
+        assert_eq!(node_ids(&host), [1, 4]);
+        assert_eq!(host.children[&1], [4]);
+    }
+}
@@ -11,6 +11,9 @@ pub enum RsxNode {
     Text(LitStrOrExpr),
     Fragment(RsxNodes),
     ControlFlow(ControlFlow),
+    Suspense(Suspense),
+    ErrorBoundary(ErrorBoundary),
+    Portal(Portal),
 }
 
 pub struct Element {
@@ -18,6 +21,8 @@ pub struct Element {
     pub attributes: Vec<Attribute>,
     pub children: Vec<RsxNode>,
     pub key: Option<Expr>,
+    /// `node_ref: my_ref`, the `NodeRef` to fill in with the rendered element.
+    pub node_ref: Option<Expr>,
     pub _span: Span,
 }
 
@@ -25,14 +30,62 @@ pub struct Component {
     pub name: Ident,
     pub props: Vec<Prop>,
     pub children: Vec<RsxNode>, // Usually components don't have children in RSX unless via children prop
+    pub key: Option<Expr>,
+    /// `memo: |old, new| ..`, compares props in place of `PartialEq`.
+    pub memo: Option<Expr>,
     pub _span: Span,
 }
 
+/// `Suspense { fallback: { .. }, children.. }`
+pub struct Suspense {
+    pub fallback: Option<RsxNodes>,
+    pub children: RsxNodes,
+    pub key: Option<Expr>,
+}
+
+/// `ErrorBoundary { fallback: |error, reset| { .. }, children.. }`
+pub struct ErrorBoundary {
+    pub fallback: Option<ErrorFallback>,
+    pub children: RsxNodes,
+    pub key: Option<Expr>,
+}
+
+/// The fallback's parameters bind the `CaughtError` and the `ResetHandle`;
+/// the handle may be left out.
+pub struct ErrorFallback {
+    pub error: syn::Pat,
+    pub reset: Option<syn::Pat>,
+    pub body: RsxNodes,
+}
+
+/// `Portal { target: "modal-root", children.. }`
+pub struct Portal {
+    pub target: Expr,
+    pub children: RsxNodes,
+    pub key: Option<Expr>,
+}
+
 pub struct Attribute {
-    pub name: Ident,
+    pub name: AttributeName,
     pub value: AttributeValue,
 }
 
+/// `class`, or a quoted name that isn't an identifier, like `"xlink:href"`
+/// or `"stroke-width"`.
+pub enum AttributeName {
+    Ident(Ident),
+    Quoted(LitStr),
+}
+
+impl AttributeName {
+    pub fn value(&self) -> String {
+        match self {
+            AttributeName::Ident(ident) => ident.to_string(),
+            AttributeName::Quoted(lit) => lit.value(),
+        }
+    }
+}
+
 pub enum AttributeValue {
     Lit(LitStr),
     Expr(Expr),
@@ -79,13 +132,17 @@ impl RsxNode {
             RsxNode::Fragment(f) => f.nodes.iter().all(|n| n.is_static()),
             RsxNode::Component(_) => false,
             RsxNode::ControlFlow(_) => false,
+            RsxNode::Suspense(_) => false,
+            RsxNode::ErrorBoundary(_) => false,
+            RsxNode::Portal(_) => false,
         }
     }
 }
 
 impl Element {
     pub fn is_static(&self) -> bool {
-        self.attributes.iter().all(|a| match a.value {
+        // A ref may be swapped for another between renders.
+        self.node_ref.is_none() && self.attributes.iter().all(|a| match a.value {
             AttributeValue::Lit(_) => true,
             _ => false,
         }) && self.children.iter().all(|c| c.is_static())
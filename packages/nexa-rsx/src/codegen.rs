@@ -4,7 +4,11 @@ use crate::ast::*;
 
 impl ToTokens for RsxNodes {
     fn to_tokens(&self, tokens: &mut TokenStream) {
-        let nodes = &self.nodes;
+        // Each top-level element is the root of its own template.
+        let nodes = self.nodes.iter().map(|node| match node {
+            RsxNode::Element(el) => el.expand(true),
+            node => node.to_token_stream(),
+        });
         tokens.extend(quote! {
             {
                 let mut __nodes: smallvec::SmallVec<[nexa_core::NodeId; 4]> = smallvec::SmallVec::new();
@@ -28,6 +32,7 @@ impl ToTokens for RsxNode {
                             nexa_core::VirtualNode::Text(nexa_core::Text {
                                 text: #s.to_string(),
                                 parent: None,
+                                key: None,
                             })
                         }
                     }
@@ -36,6 +41,7 @@ impl ToTokens for RsxNode {
                             nexa_core::VirtualNode::Text(nexa_core::Text {
                                 text: format!("{}", #e),
                                 parent: None,
+                                key: None,
                             })
                         }
                     }
@@ -53,21 +59,103 @@ impl ToTokens for RsxNode {
                 });
             }
             RsxNode::ControlFlow(cf) => cf.to_tokens(tokens),
+            RsxNode::Suspense(susp) => susp.to_tokens(tokens),
+            RsxNode::ErrorBoundary(eb) => eb.to_tokens(tokens),
+            RsxNode::Portal(portal) => portal.to_tokens(tokens),
+        }
+    }
+}
+
+impl ToTokens for AttributeName {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        match self {
+            AttributeName::Ident(ident) => ident.to_tokens(tokens),
+            AttributeName::Quoted(lit) => lit.to_tokens(tokens),
         }
     }
 }
 
 impl ToTokens for Element {
     fn to_tokens(&self, tokens: &mut TokenStream) {
+        tokens.extend(self.expand(false));
+    }
+}
+
+impl Element {
+    /// Children that vary between renders can't be part of a template.
+    fn has_dynamic_children(&self) -> bool {
+        !self.children.iter().all(|c| {
+            matches!(c, RsxNode::Element(_) | RsxNode::Text(_) | RsxNode::Component(_) | RsxNode::Suspense(_) | RsxNode::ErrorBoundary(_) | RsxNode::Portal(_))
+        })
+    }
+
+    /// Builds the template skeleton for this element and its fixed descendants.
+    /// Templates are built without their surroundings, so `parent_ns` is only
+    /// known inside the same skeleton.
+    fn template_node(&self, parent_ns: Option<TokenStream>) -> TokenStream {
         let tag = self.name.to_string();
-        let children = &self.children;
+        let namespace = tag_namespace(&tag).or(parent_ns);
+        let child_ns = if tag == "foreignObject" { None } else { namespace.clone() };
+        let namespace_tokens = match &namespace {
+            Some(ns) => quote! { Some(std::borrow::Cow::Borrowed(#ns)) },
+            None => quote! { None },
+        };
+        let attrs = self.attributes.iter().filter_map(|attr| {
+            let name = attr.name.value();
+            match &attr.value {
+                AttributeValue::Lit(l) if !name.starts_with("on") => {
+                    let value = l.value();
+                    Some(quote! {
+                        nexa_core::TemplateAttribute {
+                            name: std::borrow::Cow::Borrowed(#name),
+                            value: std::borrow::Cow::Borrowed(#value),
+                        }
+                    })
+                }
+                _ => None,
+            }
+        });
+        let dynamic_children = self.has_dynamic_children();
+        let children: Vec<TokenStream> = if dynamic_children {
+            Vec::new()
+        } else {
+            self.children.iter().map(|child| template_node(child, child_ns.clone())).collect()
+        };
+
+        quote! {
+            nexa_core::TemplateNode::Element {
+                tag: std::borrow::Cow::Borrowed(#tag),
+                namespace: #namespace_tokens,
+                attrs: std::borrow::Cow::Borrowed(&[ #(#attrs),* ]),
+                children: std::borrow::Cow::Borrowed(&[ #(#children),* ]),
+                dynamic_children: #dynamic_children,
+            }
+        }
+    }
+
+    /// Expands the element. Template roots carry their template in the node metadata.
+    fn expand(&self, root: bool) -> TokenStream {
+        let tag = self.name.to_string();
+        let dynamic_children = self.has_dynamic_children();
+        // Elements among dynamic children are not in the parent's template, so they get their own.
+        let children = self.children.iter().map(|child| match child {
+            RsxNode::Element(el) if dynamic_children => el.expand(true),
+            child => child.to_token_stream(),
+        });
         let mut props = Vec::new();
         let mut listeners = Vec::new();
 
         for attr in &self.attributes {
-            let name_str = attr.name.to_string();
+            let name_str = attr.name.value();
             if name_str.starts_with("on") {
                 let event_name = name_str.trim_start_matches("on").to_lowercase();
+                // `onclickcapture` listens to `click` in the capture phase.
+                let (event_name, capture) = match event_name.strip_suffix("capture") {
+                    Some(base) if !base.is_empty() && !base.ends_with("pointer") => {
+                        (base.to_string(), true)
+                    }
+                    _ => (event_name, false),
+                };
                 let val = match &attr.value {
                     AttributeValue::Lit(_) => quote! { panic!("Lit events not supported") },
                     AttributeValue::Expr(e) => quote! { #e },
@@ -80,18 +168,19 @@ impl ToTokens for Element {
                     nexa_core::vdom::EventListener {
                         name: #event_name,
                         cb: std::rc::Rc::new(std::cell::RefCell::new(#val)),
+                        capture: #capture,
                     }
                 });
             } else {
                 let val = match &attr.value {
                     AttributeValue::Lit(l) => {
                         let s = l.value();
-                        quote! { #s.to_string() }
+                        quote! { nexa_core::AttributeValue::Text(#s.to_string()) }
                     }
-                    AttributeValue::Expr(e) => quote! { format!("{}", #e) },
+                    AttributeValue::Expr(e) => quote! { nexa_core::AttributeValue::from(#e) },
                     AttributeValue::Shorthand => {
                          let n = &attr.name;
-                         quote! { format!("{}", #n) }
+                         quote! { nexa_core::AttributeValue::from(#n) }
                     }
                 };
                 props.push(quote! {
@@ -103,11 +192,32 @@ impl ToTokens for Element {
             }
         }
 
+        let namespace = match tag_namespace(&tag) {
+            Some(ns) => quote! { Some(#ns) },
+            None => quote! { None },
+        };
+
         let is_static = self.is_static();
-        let metadata = if is_static {
-            quote! { nexa_core::NodeMetadata { is_static: true, render_count: 0 } }
+        let template = if root {
+            let skeleton = self.template_node(None);
+            // Identical skeletons share a name, and a template.
+            let mut hasher = std::hash::DefaultHasher::new();
+            std::hash::Hash::hash(&skeleton.to_string(), &mut hasher);
+            let name = format!("{}-{:016x}", tag, std::hash::Hasher::finish(&hasher));
+            quote! {
+                Some({
+                    static __TEMPLATE: nexa_core::Template = nexa_core::Template {
+                        name: std::borrow::Cow::Borrowed(#name),
+                        roots: std::borrow::Cow::Borrowed(&[ #skeleton ]),
+                    };
+                    &__TEMPLATE
+                })
+            }
         } else {
-            quote! { nexa_core::NodeMetadata::default() }
+            quote! { None }
+        };
+        let metadata = quote! {
+            nexa_core::NodeMetadata { is_static: #is_static, render_count: 0, template: #template }
         };
 
         let key = if let Some(k) = &self.key {
@@ -115,8 +225,12 @@ impl ToTokens for Element {
         } else {
              quote! { None }
         };
+        let node_ref = match &self.node_ref {
+            Some(r) => quote! { Some(nexa_core::NodeRef::clone(&#r)) },
+            None => quote! { None },
+        };
 
-        tokens.extend(quote! {
+        quote! {
             nexa_core::get_active_arena(|arena| {
                 // Generate children
                 let mut __el_nodes: smallvec::SmallVec<[nexa_core::NodeId; 4]> = smallvec::SmallVec::new();
@@ -129,17 +243,40 @@ impl ToTokens for Element {
                 let id = arena.insert_with_metadata(
                     nexa_core::VirtualNode::Element(nexa_core::Element {
                         tag: #tag,
+                        namespace: #namespace,
                         props: smallvec::smallvec![ #(#props),* ],
                         listeners: smallvec::smallvec![ #(#listeners),* ],
                         children: __el_nodes,
                         parent: None,
                         key: #key,
+                        node_ref: #node_ref,
                     }),
                     #metadata
                 );
                 __nodes.push(id);
             });
-        });
+        }
+    }
+}
+
+/// The namespace `svg` and `math` open, as a path to its constant.
+fn tag_namespace(tag: &str) -> Option<TokenStream> {
+    match tag {
+        "svg" => Some(quote! { nexa_core::vdom::SVG_NAMESPACE }),
+        "math" => Some(quote! { nexa_core::vdom::MATHML_NAMESPACE }),
+        _ => None,
+    }
+}
+
+fn template_node(node: &RsxNode, namespace: Option<TokenStream>) -> TokenStream {
+    match node {
+        RsxNode::Element(el) => el.template_node(namespace),
+        RsxNode::Text(LitStrOrExpr::Lit(l)) => {
+            let text = l.value();
+            quote! { nexa_core::TemplateNode::Text { text: std::borrow::Cow::Borrowed(#text) } }
+        }
+        RsxNode::Text(LitStrOrExpr::Expr(_)) => quote! { nexa_core::TemplateNode::DynamicText },
+        _ => quote! { nexa_core::TemplateNode::Dynamic },
     }
 }
 
@@ -162,11 +299,166 @@ impl ToTokens for Component {
             }
         }
         
-        // Components are functions taking props and returning NodeId
+        let name_str = name.to_string();
+        let with_key = self.key.as_ref().map(|k| quote! { .with_key(#k) });
+        // Props are compared with `memo`, or else with `PartialEq` where
+        // the props implement it; other components re-render with their parent.
+        let component = match &self.memo {
+            Some(eq) => quote! {
+                nexa_core::Component::eager(#name_str, #name, __props).memo_by::<#props_name>(#eq)
+            },
+            None => quote! {
+                (&nexa_core::vdom::ComponentFor::<#props_name>(::std::marker::PhantomData))
+                    .component(#name_str, #name, __props)
+            },
+        };
+        
+        // Components are functions taking props and returning NodeId.
+        // They are mounted as Component nodes so they get their own scope.
+        tokens.extend(quote! {
+            {
+                #[allow(unused_imports)]
+                use nexa_core::vdom::{ComparableProps as _, EagerProps as _};
+                let __props = #props_name {
+                    #(#fields),*
+                };
+                nexa_core::get_active_arena(|arena| {
+                    let id = arena.insert(nexa_core::VirtualNode::Component(
+                        #component #with_key
+                    ));
+                    __nodes.push(id);
+                });
+            }
+        });
+    }
+}
+
+impl ToTokens for Suspense {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let children = &self.children;
+        // An empty text node keeps the boundary's place in the DOM.
+        let fallback = match &self.fallback {
+            Some(f) => quote! { #f },
+            None => quote! {
+                {
+                    let mut __nodes: smallvec::SmallVec<[nexa_core::NodeId; 4]> = smallvec::SmallVec::new();
+                    nexa_core::get_active_arena(|arena| {
+                        __nodes.push(arena.insert(nexa_core::VirtualNode::Text(nexa_core::Text {
+                            text: String::new(),
+                            parent: None,
+                            key: None,
+                        })));
+                    });
+                    __nodes
+                }
+            },
+        };
+        let key = if let Some(k) = &self.key {
+            quote! { Some(#k.to_string()) }
+        } else {
+            quote! { None }
+        };
+
+        tokens.extend(quote! {
+            {
+                let __actual = #children;
+                let __fallback = #fallback;
+                nexa_core::get_active_arena(|arena| {
+                    let actual = arena.insert_roots(__actual);
+                    let fallback = arena.insert_roots(__fallback);
+                    let id = arena.insert(nexa_core::VirtualNode::Suspense(nexa_core::Suspense {
+                        fallback,
+                        actual,
+                        suspended: false,
+                        parent: None,
+                        key: #key,
+                    }));
+                    __nodes.push(id);
+                });
+            }
+        });
+    }
+}
+
+impl ToTokens for ErrorBoundary {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let children = &self.children;
+        // Without a fallback, a failed boundary leaves an empty text node.
+        let fallback = match &self.fallback {
+            Some(ErrorFallback { error, reset, body }) => {
+                let reset = match reset {
+                    Some(r) => quote! { #r },
+                    None => quote! { _ },
+                };
+                quote! {
+                    std::rc::Rc::new(move |#error: nexa_core::CaughtError, #reset: nexa_core::ResetHandle| {
+                        let __fallback = #body;
+                        nexa_core::get_active_arena(|arena| arena.insert_roots(__fallback))
+                    })
+                }
+            }
+            None => quote! {
+                std::rc::Rc::new(|_: nexa_core::CaughtError, _: nexa_core::ResetHandle| {
+                    nexa_core::get_active_arena(|arena| {
+                        arena.insert(nexa_core::VirtualNode::Text(nexa_core::Text {
+                            text: String::new(),
+                            parent: None,
+                            key: None,
+                        }))
+                    })
+                })
+            },
+        };
+        let key = if let Some(k) = &self.key {
+            quote! { Some(#k.to_string()) }
+        } else {
+            quote! { None }
+        };
+
         tokens.extend(quote! {
-            __nodes.push(#name(#props_name {
-                #(#fields),*
-            }));
+            {
+                let __content = #children;
+                let __fallback: nexa_core::ErrorFallback = #fallback;
+                nexa_core::get_active_arena(|arena| {
+                    let content = arena.insert_roots(__content);
+                    let id = arena.insert(nexa_core::VirtualNode::ErrorBoundary(nexa_core::ErrorBoundary {
+                        content,
+                        fallback: __fallback,
+                        caught: None,
+                        parent: None,
+                        key: #key,
+                    }));
+                    __nodes.push(id);
+                });
+            }
+        });
+    }
+}
+
+impl ToTokens for Portal {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let target = &self.target;
+        let children = &self.children;
+        let key = if let Some(k) = &self.key {
+            quote! { Some(#k.to_string()) }
+        } else {
+            quote! { None }
+        };
+
+        tokens.extend(quote! {
+            {
+                let __content = #children;
+                nexa_core::get_active_arena(|arena| {
+                    let content = arena.insert_roots(__content);
+                    let id = arena.insert(nexa_core::VirtualNode::Portal(nexa_core::Portal {
+                        target: #target.to_string(),
+                        content,
+                        parent: None,
+                        key: #key,
+                    }));
+                    __nodes.push(id);
+                });
+            }
         });
     }
 }
@@ -48,7 +48,13 @@ impl Parse for RsxNode {
             // Ident check: Capitalized -> Component, lowercase -> Element
             let name: Ident = input.fork().parse()?;
             let first_char = name.to_string().chars().next().unwrap();
-            if first_char.is_uppercase() {
+            if name == "Suspense" {
+                Ok(RsxNode::Suspense(input.parse()?))
+            } else if name == "ErrorBoundary" {
+                Ok(RsxNode::ErrorBoundary(input.parse()?))
+            } else if name == "Portal" {
+                Ok(RsxNode::Portal(input.parse()?))
+            } else if first_char.is_uppercase() {
                 Ok(RsxNode::Component(input.parse()?))
             } else {
                 Ok(RsxNode::Element(input.parse()?))
@@ -65,6 +71,7 @@ impl Parse for Element {
         let mut children = Vec::new();
 
         let mut key = None;
+        let mut node_ref = None;
 
         if input.peek(syn::token::Brace) {
             let content;
@@ -75,7 +82,17 @@ impl Parse for Element {
                 // if it looks like "string", { block }, or another element, it's a child.
 
                 let fork = content.fork();
-                if let Ok(ident) = fork.parse::<Ident>() {
+                if content.peek(LitStr) && content.peek2(Token![:]) {
+                    // "quoted-name": value
+                    let name = AttributeName::Quoted(content.parse()?);
+                    content.parse::<Token![:]>()?;
+                    let val = if content.peek(LitStr) {
+                        AttributeValue::Lit(content.parse()?)
+                    } else {
+                        AttributeValue::Expr(content.parse()?)
+                    };
+                    attributes.push(Attribute { name, value: val });
+                } else if let Ok(ident) = fork.parse::<Ident>() {
                     if fork.peek(Token![:]) {
                         // Key: Value
                         let name = ident;
@@ -91,6 +108,8 @@ impl Parse for Element {
                                 content.parse()?
                             };
                             key = Some(val);
+                        } else if name == "node_ref" {
+                            node_ref = Some(content.parse()?);
                         } else {
                             // Normal attribute
                             let val = if content.peek(LitStr) {
@@ -98,14 +117,17 @@ impl Parse for Element {
                             } else {
                                 AttributeValue::Expr(content.parse()?)
                             };
-                            attributes.push(Attribute { name, value: val });
+                            attributes.push(Attribute {
+                                name: AttributeName::Ident(name),
+                                value: val,
+                            });
                         }
                     } else if fork.is_empty() || fork.peek(Token![,]) {
                         // Shorthand
                         let name = ident;
                         content.parse::<Ident>()?; // consume
                         attributes.push(Attribute {
-                            name,
+                            name: AttributeName::Ident(name),
                             value: AttributeValue::Shorthand,
                         });
                     } else {
@@ -133,6 +155,7 @@ impl Parse for Element {
             attributes,
             children,
             key,
+            node_ref,
             _span: span,
         })
     }
@@ -144,6 +167,8 @@ impl Parse for Component {
         let span = name.span();
         let mut props = Vec::new();
         let mut children = Vec::new(); // Support children injection later? 
+        let mut key = None;
+        let mut memo = None;
 
         // Components accept Props via brace syntax: MyComp { prop: value }
         if input.peek(syn::token::Brace) {
@@ -158,7 +183,21 @@ impl Parse for Component {
                 // Strict Props: Ident : Value
                 // Shorthand: Ident
 
-                props.push(content.parse()?);
+                let prop: Prop = content.parse()?;
+                // `key` identifies the component among its siblings; it is not a prop.
+                if prop.name == "key" {
+                    key = Some(match prop.value {
+                        PropValue::Expr(e) => e,
+                        PropValue::Shorthand => syn::parse_quote! { key },
+                    });
+                } else if prop.name == "memo" {
+                    memo = Some(match prop.value {
+                        PropValue::Expr(e) => e,
+                        PropValue::Shorthand => syn::parse_quote! { memo },
+                    });
+                } else {
+                    props.push(prop);
+                }
 
                 if content.peek(Token![,]) {
                     content.parse::<Token![,]>()?;
@@ -173,14 +212,168 @@ impl Parse for Component {
             name,
             props,
             children,
+            key,
+            memo,
             _span: span,
         })
     }
 }
 
+impl Parse for Suspense {
+    fn parse(input: ParseStream) -> Result<Self> {
+        input.parse::<Ident>()?;
+        let mut fallback = None;
+        let mut key = None;
+        let mut children = Vec::new();
+
+        let content;
+        braced!(content in input);
+        while !content.is_empty() {
+            let fork = content.fork();
+            if fork.parse::<Ident>().is_ok() && fork.peek(Token![:]) {
+                let name: Ident = content.parse()?;
+                content.parse::<Token![:]>()?;
+                if name == "fallback" {
+                    let inner;
+                    braced!(inner in content);
+                    fallback = Some(inner.parse()?);
+                } else if name == "key" {
+                    key = Some(content.parse()?);
+                } else {
+                    return Err(syn::Error::new(
+                        name.span(),
+                        "Suspense only takes `fallback` and `key`",
+                    ));
+                }
+            } else {
+                children.push(content.parse()?);
+            }
+
+            if content.peek(Token![,]) {
+                content.parse::<Token![,]>()?;
+            }
+        }
+
+        Ok(Suspense {
+            fallback,
+            children: RsxNodes { nodes: children },
+            key,
+        })
+    }
+}
+
+impl Parse for ErrorBoundary {
+    fn parse(input: ParseStream) -> Result<Self> {
+        input.parse::<Ident>()?;
+        let mut fallback = None;
+        let mut key = None;
+        let mut children = Vec::new();
+
+        let content;
+        braced!(content in input);
+        while !content.is_empty() {
+            let fork = content.fork();
+            if fork.parse::<Ident>().is_ok() && fork.peek(Token![:]) {
+                let name: Ident = content.parse()?;
+                content.parse::<Token![:]>()?;
+                if name == "fallback" {
+                    fallback = Some(content.parse()?);
+                } else if name == "key" {
+                    key = Some(content.parse()?);
+                } else {
+                    return Err(syn::Error::new(
+                        name.span(),
+                        "ErrorBoundary only takes `fallback` and `key`",
+                    ));
+                }
+            } else {
+                children.push(content.parse()?);
+            }
+
+            if content.peek(Token![,]) {
+                content.parse::<Token![,]>()?;
+            }
+        }
+
+        Ok(ErrorBoundary {
+            fallback,
+            children: RsxNodes { nodes: children },
+            key,
+        })
+    }
+}
+
+impl Parse for Portal {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let name: Ident = input.parse()?;
+        let mut target = None;
+        let mut key = None;
+        let mut children = Vec::new();
+
+        let content;
+        braced!(content in input);
+        while !content.is_empty() {
+            let fork = content.fork();
+            if fork.parse::<Ident>().is_ok() && fork.peek(Token![:]) {
+                let prop: Ident = content.parse()?;
+                content.parse::<Token![:]>()?;
+                if prop == "target" {
+                    target = Some(content.parse()?);
+                } else if prop == "key" {
+                    key = Some(content.parse()?);
+                } else {
+                    return Err(syn::Error::new(
+                        prop.span(),
+                        "Portal only takes `target` and `key`",
+                    ));
+                }
+            } else {
+                children.push(content.parse()?);
+            }
+
+            if content.peek(Token![,]) {
+                content.parse::<Token![,]>()?;
+            }
+        }
+
+        let Some(target) = target else {
+            return Err(syn::Error::new(name.span(), "Portal needs a `target`"));
+        };
+        Ok(Portal {
+            target,
+            children: RsxNodes { nodes: children },
+            key,
+        })
+    }
+}
+
+impl Parse for ErrorFallback {
+    fn parse(input: ParseStream) -> Result<Self> {
+        input.parse::<Token![|]>()?;
+        let error = syn::Pat::parse_single(input)?;
+        let mut reset = None;
+        if input.peek(Token![,]) {
+            input.parse::<Token![,]>()?;
+            reset = Some(syn::Pat::parse_single(input)?);
+        }
+        input.parse::<Token![|]>()?;
+        let body;
+        braced!(body in input);
+        Ok(ErrorFallback {
+            error,
+            reset,
+            body: body.parse()?,
+        })
+    }
+}
+
 impl Parse for Attribute {
     fn parse(input: ParseStream) -> Result<Self> {
-        let name: Ident = input.call(Ident::parse_any)?;
+        let name = if input.peek(LitStr) {
+            AttributeName::Quoted(input.parse()?)
+        } else {
+            AttributeName::Ident(input.call(Ident::parse_any)?)
+        };
         if input.peek(Token![:]) {
             input.parse::<Token![:]>()?;
             if input.peek(LitStr) {
@@ -1,4 +1,4 @@
-use nexa_core::{Attribute, Element, NodeMetadata, Text, VirtualNode};
+use nexa_core::{Attribute, AttributeValue, Element, NodeMetadata, Text, VirtualNode};
 use nexa_rsx::rsx;
 
 #[test]
@@ -20,13 +20,111 @@ fn test_element_expansion() {
         assert_eq!(el.tag, "div");
         assert_eq!(el.props.len(), 1);
         assert_eq!(el.props[0].name, "class");
-        // Attribute value is String, so we compare with string literal
-        assert_eq!(el.props[0].value, "foo");
+        assert_eq!(el.props[0].value, AttributeValue::Text("foo".to_string()));
     } else {
         panic!("Expected element");
     }
 }
 
+#[test]
+fn test_typed_attribute_expansion() {
+    let mut arena = nexa_core::VDomArena::new();
+    let step = 2;
+    let title: Option<String> = None;
+    let nodes = unsafe {
+        nexa_core::set_active_arena(&mut arena, || {
+            rsx! {
+                input { disabled: false, tabindex: step, title: title }
+            }
+        })
+    };
+
+    let VirtualNode::Element(el) = arena.nodes.get(nodes[0]).unwrap() else {
+        panic!("Expected element");
+    };
+    let values: Vec<_> = el.props.iter().map(|a| a.value.clone()).collect();
+    assert_eq!(
+        values,
+        vec![
+            AttributeValue::Bool(false),
+            AttributeValue::Int(2),
+            AttributeValue::None
+        ]
+    );
+}
+
+#[test]
+fn test_svg_expansion() {
+    use nexa_core::TemplateNode;
+
+    let mut arena = nexa_core::VDomArena::new();
+    let nodes = unsafe {
+        nexa_core::set_active_arena(&mut arena, || {
+            rsx! {
+                svg { "viewBox": "0 0 10 10",
+                    circle { r: "4", "stroke-width": "2" }
+                    foreignObject { p { "Label" } }
+                }
+            }
+        })
+    };
+
+    let VirtualNode::Element(svg) = arena.nodes.get(nodes[0]).unwrap() else {
+        panic!("Expected element");
+    };
+    assert_eq!(svg.namespace, Some(nexa_core::SVG_NAMESPACE));
+    let VirtualNode::Element(circle) = arena.nodes.get(svg.children[0]).unwrap() else {
+        panic!("Expected element");
+    };
+    // Inherited when the tree is created.
+    assert_eq!(circle.namespace, None);
+    assert_eq!(circle.props[1].name, "stroke-width");
+
+    let template = arena.metadata.get(nodes[0]).unwrap().template.unwrap();
+    let TemplateNode::Element {
+        namespace,
+        children,
+        ..
+    } = &template.roots[0]
+    else {
+        panic!("Expected element template");
+    };
+    assert_eq!(namespace.as_deref(), Some(nexa_core::SVG_NAMESPACE));
+    let TemplateNode::Element {
+        namespace,
+        children,
+        ..
+    } = &children[1]
+    else {
+        panic!("Expected element template");
+    };
+    assert_eq!(namespace.as_deref(), Some(nexa_core::SVG_NAMESPACE));
+    let TemplateNode::Element { namespace, .. } = &children[0] else {
+        panic!("Expected element template");
+    };
+    assert_eq!(namespace.as_deref(), None);
+}
+
+#[test]
+fn test_node_ref_expansion() {
+    let mut arena = nexa_core::VDomArena::new();
+    let field = nexa_core::NodeRef::new();
+    let nodes = unsafe {
+        nexa_core::set_active_arena(&mut arena, || {
+            rsx! {
+                input { node_ref: field, placeholder: "Name" }
+            }
+        })
+    };
+
+    let VirtualNode::Element(el) = arena.nodes.get(nodes[0]).unwrap() else {
+        panic!("Expected element");
+    };
+    assert_eq!(el.node_ref, Some(field));
+    assert_eq!(el.props.len(), 1);
+    assert!(!arena.metadata.get(nodes[0]).unwrap().is_static);
+}
+
 #[test]
 fn test_component_expansion() {
     // Define a dummy component function
@@ -35,10 +133,14 @@ fn test_component_expansion() {
             arena.insert(VirtualNode::Text(Text {
                 text: format!("Value: {}", props.val),
                 parent: None,
+                key: None,
             }))
         })
     }
 
+    // Props without `PartialEq` can't be compared; the component re-renders
+    // with its parent.
+    #[derive(Clone)]
     struct MyCompProps {
         val: i32,
     }
@@ -53,16 +155,63 @@ fn test_component_expansion() {
     };
 
     assert_eq!(nodes.len(), 1);
-    // The node should be whatever MyComp returned.
-    let id = nodes[0];
-    let node = arena.nodes.get(id).unwrap();
-    if let VirtualNode::Text(t) = node {
+    // Components are mounted lazily; the node carries the render fn and props.
+    let comp = match arena.nodes.get(nodes[0]).unwrap() {
+        VirtualNode::Component(c) => c.clone(),
+        _ => panic!("Expected component node"),
+    };
+    assert_eq!(comp.name, "MyComp");
+    assert!(comp.props_eq.is_none());
+
+    let id = unsafe { nexa_core::set_active_arena(&mut arena, || (comp.render_fn)(&*comp.props)) };
+    if let VirtualNode::Text(t) = arena.nodes.get(id).unwrap() {
         assert_eq!(t.text, "Value: 42");
     } else {
         panic!("Expected text from component");
     }
 }
 
+#[test]
+fn test_component_props_comparison() {
+    #[allow(non_snake_case)]
+    fn Badge(props: BadgeProps) -> nexa_core::NodeId {
+        nexa_core::get_active_arena(|arena| {
+            arena.insert(VirtualNode::Text(Text {
+                text: props.label.to_string(),
+                parent: None,
+                key: None,
+            }))
+        })
+    }
+
+    #[derive(Clone, PartialEq)]
+    struct BadgeProps {
+        label: &'static str,
+    }
+
+    let mut arena = nexa_core::VDomArena::new();
+    let nodes = unsafe {
+        nexa_core::set_active_arena(&mut arena, || {
+            rsx! {
+                Badge { label: "new" }
+                Badge { label: "old", memo: |old, new| old.label.len() == new.label.len() }
+            }
+        })
+    };
+
+    let eq: Vec<_> = nodes
+        .iter()
+        .map(|&id| match arena.nodes.get(id).unwrap() {
+            VirtualNode::Component(c) => c.props_eq.clone().unwrap(),
+            _ => panic!("Expected component node"),
+        })
+        .collect();
+    let props = |label: &'static str| BadgeProps { label };
+    assert!(eq[0](&props("new"), &props("new")));
+    assert!(!eq[0](&props("new"), &props("now")));
+    assert!(eq[1](&props("new"), &props("now")));
+}
+
 #[test]
 fn test_nested_structure() {
     let mut arena = nexa_core::VDomArena::new();
@@ -132,3 +281,285 @@ fn test_key_support() {
         panic!("Expected element");
     }
 }
+
+#[test]
+fn test_component_key_support() {
+    #[allow(non_snake_case)]
+    fn Row(props: RowProps) -> nexa_core::NodeId {
+        nexa_core::get_active_arena(|arena| {
+            arena.insert(VirtualNode::Text(Text {
+                text: props.label,
+                parent: None,
+                key: None,
+            }))
+        })
+    }
+
+    #[derive(Clone, PartialEq)]
+    struct RowProps {
+        label: String,
+    }
+
+    let mut arena = nexa_core::VDomArena::new();
+    let nodes = unsafe {
+        nexa_core::set_active_arena(&mut arena, || {
+            rsx! {
+                for id in [7, 3] {
+                    Row { key: id, label: format!("row {}", id) }
+                }
+            }
+        })
+    };
+
+    let keys: Vec<_> = nodes
+        .iter()
+        .map(|&id| match arena.nodes.get(id).unwrap() {
+            VirtualNode::Component(c) => c.key.clone(),
+            _ => panic!("Expected component"),
+        })
+        .collect();
+    assert_eq!(keys, vec![Some("7".to_string()), Some("3".to_string())]);
+}
+
+#[test]
+fn test_template_generation() {
+    use nexa_core::TemplateNode;
+
+    let mut arena = nexa_core::VDomArena::new();
+    let nodes = unsafe {
+        nexa_core::set_active_arena(&mut arena, || {
+            let count = 3;
+            rsx! {
+                div { class: "card",
+                    h1 { "Title" },
+                    p { id: count, {count} }
+                }
+                ul {
+                    for i in 0..2 {
+                        li { {i} }
+                    }
+                }
+            }
+        })
+    };
+    assert_eq!(nodes.len(), 2);
+
+    let card = arena.metadata.get(nodes[0]).unwrap().template.unwrap();
+    let TemplateNode::Element {
+        tag,
+        attrs,
+        children,
+        dynamic_children,
+        ..
+    } = &card.roots[0]
+    else {
+        panic!("Expected element template");
+    };
+    assert_eq!(tag, "div");
+    assert_eq!(attrs.len(), 1);
+    assert!(!dynamic_children);
+    assert_eq!(
+        children[1],
+        TemplateNode::Element {
+            tag: "p".into(),
+            namespace: None,
+            attrs: vec![].into(),
+            children: vec![TemplateNode::DynamicText].into(),
+            dynamic_children: false,
+        }
+    );
+
+    // Only roots carry a template.
+    let VirtualNode::Element(div) = arena.nodes.get(nodes[0]).unwrap() else {
+        panic!("Expected element");
+    };
+    assert!(
+        arena
+            .metadata
+            .get(div.children[0])
+            .unwrap()
+            .template
+            .is_none()
+    );
+
+    // Loop bodies are templates of their own.
+    let list = arena.metadata.get(nodes[1]).unwrap().template.unwrap();
+    assert!(matches!(
+        list.roots[0],
+        TemplateNode::Element {
+            dynamic_children: true,
+            ..
+        }
+    ));
+    let VirtualNode::Element(ul) = arena.nodes.get(nodes[1]).unwrap() else {
+        panic!("Expected element");
+    };
+    let item_templates: Vec<_> = ul
+        .children
+        .iter()
+        .map(|&id| {
+            arena
+                .metadata
+                .get(id)
+                .unwrap()
+                .template
+                .unwrap()
+                .name
+                .clone()
+        })
+        .collect();
+    assert_eq!(item_templates.len(), 2);
+    assert_eq!(item_templates[0], item_templates[1]);
+    assert_ne!(item_templates[0], card.name);
+}
+
+#[test]
+fn test_capture_listeners() {
+    let mut arena = nexa_core::VDomArena::new();
+    let nodes = unsafe {
+        nexa_core::set_active_arena(&mut arena, || {
+            rsx! {
+                div {
+                    onclick: move |_| {},
+                    onclickcapture: move |_| {},
+                    ongotpointercapture: move |_| {},
+                }
+            }
+        })
+    };
+
+    let VirtualNode::Element(el) = arena.nodes.get(nodes[0]).unwrap() else {
+        panic!("Expected element");
+    };
+    let listeners: Vec<_> = el.listeners.iter().map(|l| (l.name, l.capture)).collect();
+    assert_eq!(
+        listeners,
+        vec![
+            ("click", false),
+            ("click", true),
+            ("gotpointercapture", false)
+        ]
+    );
+}
+
+#[test]
+fn test_suspense_syntax() {
+    let mut arena = nexa_core::VDomArena::new();
+    let nodes = unsafe {
+        nexa_core::set_active_arena(&mut arena, || {
+            rsx! {
+                Suspense {
+                    fallback: { p { "Loading..." } },
+                    h1 { "Title" }
+                    p { "Body" }
+                }
+                Suspense { key: "bare", span { "Content" } }
+            }
+        })
+    };
+
+    assert_eq!(nodes.len(), 2);
+    let VirtualNode::Suspense(susp) = arena.nodes.get(nodes[0]).unwrap() else {
+        panic!("Expected suspense");
+    };
+    assert!(!susp.suspended);
+    assert!(matches!(
+        arena.nodes.get(susp.fallback),
+        Some(VirtualNode::Element(el)) if el.tag == "p"
+    ));
+    // Several children are wrapped in a fragment, each its own template.
+    let VirtualNode::Fragment(content) = arena.nodes.get(susp.actual).unwrap() else {
+        panic!("Expected fragment");
+    };
+    assert_eq!(content.children.len(), 2);
+    assert!(
+        arena
+            .metadata
+            .get(content.children[0])
+            .unwrap()
+            .template
+            .is_some()
+    );
+
+    // Without a fallback, an empty text node holds the boundary's place.
+    let VirtualNode::Suspense(bare) = arena.nodes.get(nodes[1]).unwrap() else {
+        panic!("Expected suspense");
+    };
+    assert_eq!(bare.key.as_deref(), Some("bare"));
+    assert!(matches!(
+        arena.nodes.get(bare.fallback),
+        Some(VirtualNode::Text(t)) if t.text.is_empty()
+    ));
+}
+
+#[test]
+fn test_error_boundary_syntax() {
+    let mut arena = nexa_core::VDomArena::new();
+    let nodes = unsafe {
+        nexa_core::set_active_arena(&mut arena, || {
+            rsx! {
+                ErrorBoundary {
+                    fallback: |error, reset| {
+                        p { {error.message} }
+                        button { onclick: move |_| reset.reset(), "Retry" }
+                    },
+                    h1 { "Title" }
+                }
+                ErrorBoundary { key: "bare", span { "Content" } }
+            }
+        })
+    };
+
+    assert_eq!(nodes.len(), 2);
+    let VirtualNode::ErrorBoundary(boundary) = arena.nodes.get(nodes[0]).unwrap() else {
+        panic!("Expected error boundary");
+    };
+    assert!(boundary.caught.is_none());
+    assert!(matches!(
+        arena.nodes.get(boundary.content),
+        Some(VirtualNode::Element(el)) if el.tag == "h1"
+    ));
+
+    let VirtualNode::ErrorBoundary(bare) = arena.nodes.get(nodes[1]).unwrap() else {
+        panic!("Expected error boundary");
+    };
+    assert_eq!(bare.key.as_deref(), Some("bare"));
+}
+
+#[test]
+fn test_portal_syntax() {
+    let mut arena = nexa_core::VDomArena::new();
+    let target = "toasts";
+    let nodes = unsafe {
+        nexa_core::set_active_arena(&mut arena, || {
+            rsx! {
+                div {
+                    Portal { target: "modal-root", dialog { "Modal" } }
+                    Portal { target: target, key: "toast", p { "Saved" } p { "Undo" } }
+                }
+            }
+        })
+    };
+
+    let VirtualNode::Element(div) = arena.nodes.get(nodes[0]).unwrap() else {
+        panic!("Expected element");
+    };
+    let VirtualNode::Portal(modal) = arena.nodes.get(div.children[0]).unwrap() else {
+        panic!("Expected portal");
+    };
+    assert_eq!(modal.target, "modal-root");
+    assert!(matches!(
+        arena.nodes.get(modal.content),
+        Some(VirtualNode::Element(el)) if el.tag == "dialog"
+    ));
+
+    let VirtualNode::Portal(toast) = arena.nodes.get(div.children[1]).unwrap() else {
+        panic!("Expected portal");
+    };
+    assert_eq!(toast.target, "toasts");
+    assert_eq!(toast.key.as_deref(), Some("toast"));
+    assert!(matches!(
+        arena.nodes.get(toast.content),
+        Some(VirtualNode::Fragment(f)) if f.children.len() == 2
+    ));
+}
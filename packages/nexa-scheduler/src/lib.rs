@@ -1,3 +1,4 @@
+pub mod metrics;
 pub mod queue;
 pub mod scheduler;
 pub mod task;
@@ -24,4 +25,5 @@ pub trait Scheduler {
     fn now(&self) -> f64;
 }
 
+pub use metrics::{Phase, RunawayLoop, SchedulerStats};
 pub use scheduler::LocalScheduler;
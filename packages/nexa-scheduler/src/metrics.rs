@@ -0,0 +1,147 @@
+use std::panic::Location;
+use std::time::Duration;
+
+/// Upper bounds (in milliseconds) of the latency histogram buckets.
+/// Anything slower than the last bound lands in the overflow bucket.
+pub const LATENCY_BUCKETS_MS: [f64; 6] = [0.1, 0.5, 1.0, 4.0, 16.0, 50.0];
+
+/// The queues drained by `LocalScheduler::tick`, in execution order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    Microtask,
+    Effect,
+    LayoutEffect,
+}
+
+/// Histogram of the time tasks spent queued before they started running.
+#[derive(Debug, Clone, Default)]
+pub struct LatencyHistogram {
+    pub buckets: [u64; LATENCY_BUCKETS_MS.len() + 1],
+    pub total_ms: f64,
+    pub count: u64,
+}
+
+impl LatencyHistogram {
+    pub fn record(&mut self, latency: Duration) {
+        let ms = latency.as_secs_f64() * 1000.0;
+        let idx = LATENCY_BUCKETS_MS
+            .iter()
+            .position(|&bound| ms <= bound)
+            .unwrap_or(LATENCY_BUCKETS_MS.len());
+        self.buckets[idx] += 1;
+        self.total_ms += ms;
+        self.count += 1;
+    }
+
+    pub fn average_ms(&self) -> f64 {
+        if self.count == 0 {
+            0.0
+        } else {
+            self.total_ms / self.count as f64
+        }
+    }
+
+    pub fn merge(&mut self, other: &LatencyHistogram) {
+        for (a, b) in self.buckets.iter_mut().zip(other.buckets.iter()) {
+            *a += b;
+        }
+        self.total_ms += other.total_ms;
+        self.count += other.count;
+    }
+}
+
+/// Counters for a single scheduler phase.
+#[derive(Debug, Clone, Default)]
+pub struct PhaseStats {
+    pub executed: u64,
+    /// Tasks waiting in the queue when the stats were taken.
+    pub queue_depth: usize,
+    /// Deepest the queue has been at the start of a tick.
+    pub max_queue_depth: usize,
+    pub latency: LatencyHistogram,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct SchedulerStats {
+    pub ticks: u64,
+    pub microtasks: PhaseStats,
+    pub effects: PhaseStats,
+    pub layout_effects: PhaseStats,
+    pub runaway_loops: u64,
+}
+
+impl SchedulerStats {
+    pub fn phase(&self, phase: Phase) -> &PhaseStats {
+        match phase {
+            Phase::Microtask => &self.microtasks,
+            Phase::Effect => &self.effects,
+            Phase::LayoutEffect => &self.layout_effects,
+        }
+    }
+
+    pub fn phase_mut(&mut self, phase: Phase) -> &mut PhaseStats {
+        match phase {
+            Phase::Microtask => &mut self.microtasks,
+            Phase::Effect => &mut self.effects,
+            Phase::LayoutEffect => &mut self.layout_effects,
+        }
+    }
+
+    pub fn total_executed(&self) -> u64 {
+        self.microtasks.executed + self.effects.executed + self.layout_effects.executed
+    }
+
+    pub fn pending(&self) -> usize {
+        self.microtasks.queue_depth + self.effects.queue_depth + self.layout_effects.queue_depth
+    }
+
+    /// Latency histogram across all phases.
+    pub fn latency(&self) -> LatencyHistogram {
+        let mut all = self.microtasks.latency.clone();
+        all.merge(&self.effects.latency);
+        all.merge(&self.layout_effects.latency);
+        all
+    }
+
+    pub fn to_devtools(&self) -> nexa_devtools::SchedulerMetrics {
+        let latency = self.latency();
+        let latency_histogram = latency
+            .buckets
+            .iter()
+            .enumerate()
+            .map(|(i, &count)| nexa_devtools::LatencyBucket {
+                upper_bound_ms: LATENCY_BUCKETS_MS.get(i).copied(),
+                count,
+            })
+            .collect();
+
+        nexa_devtools::SchedulerMetrics {
+            pending_tasks: self.pending(),
+            total_tasks_executed: self.total_executed(),
+            average_latency_ms: latency.average_ms(),
+            microtasks_executed: self.microtasks.executed,
+            effects_executed: self.effects.executed,
+            layout_effects_executed: self.layout_effects.executed,
+            queue_depths: nexa_devtools::QueueDepths {
+                microtasks: self.microtasks.queue_depth,
+                effects: self.effects.queue_depth,
+                layout_effects: self.layout_effects.queue_depth,
+            },
+            latency_histogram,
+            runaway_loops: self.runaway_loops,
+        }
+    }
+}
+
+/// Reported when a phase keeps rescheduling work and `tick` has to give up.
+#[derive(Debug, Clone)]
+pub struct RunawayLoop {
+    pub phase: Phase,
+    /// Number of drain passes run before giving up.
+    pub passes: u32,
+    pub tasks_run: u64,
+    /// Tasks still queued when the tick was abandoned.
+    pub pending: usize,
+    /// Distinct call sites of a sample of the pending tasks.
+    pub sample_origins: Vec<&'static Location<'static>>,
+}
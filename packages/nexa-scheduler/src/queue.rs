@@ -1,11 +1,20 @@
 use std::cell::RefCell;
 use std::collections::VecDeque;
+use std::panic::Location;
+use std::time::Instant;
+
+/// A task waiting in a `TaskQueue`, along with where and when it was scheduled.
+pub struct QueuedTask {
+    pub task: Box<dyn FnOnce()>,
+    pub origin: &'static Location<'static>,
+    pub enqueued_at: Instant,
+}
 
 /// A simple FIFO queue for tasks.
 /// Since LocalScheduler is single-threaded, we use RefCell<VecDeque>.
 #[derive(Default)]
 pub struct TaskQueue {
-    queue: RefCell<VecDeque<Box<dyn FnOnce()>>>,
+    queue: RefCell<VecDeque<QueuedTask>>,
 }
 
 impl TaskQueue {
@@ -15,11 +24,21 @@ impl TaskQueue {
         }
     }
 
+    /// Push a task, recording the caller as its origin.
+    #[track_caller]
     pub fn push(&self, task: Box<dyn FnOnce()>) {
-        self.queue.borrow_mut().push_back(task);
+        self.queue.borrow_mut().push_back(QueuedTask {
+            task,
+            origin: Location::caller(),
+            enqueued_at: Instant::now(),
+        });
     }
 
     pub fn pop(&self) -> Option<Box<dyn FnOnce()>> {
+        self.pop_queued().map(|queued| queued.task)
+    }
+
+    pub fn pop_queued(&self) -> Option<QueuedTask> {
         self.queue.borrow_mut().pop_front()
     }
 
@@ -27,6 +46,24 @@ impl TaskQueue {
         self.queue.borrow().is_empty()
     }
 
+    pub fn len(&self) -> usize {
+        self.queue.borrow().len()
+    }
+
+    /// Distinct origins of the queued tasks, oldest first, up to `limit`.
+    pub fn sample_origins(&self, limit: usize) -> Vec<&'static Location<'static>> {
+        let mut origins: Vec<&'static Location<'static>> = Vec::new();
+        for queued in self.queue.borrow().iter() {
+            if origins.len() >= limit {
+                break;
+            }
+            if !origins.contains(&queued.origin) {
+                origins.push(queued.origin);
+            }
+        }
+        origins
+    }
+
     pub fn drain(&self) {
         // We pop one by one to allow re-entrant scheduling?
         // Or we drain the whole buffer.
@@ -1,8 +1,17 @@
 use crate::Scheduler;
-use crate::queue::TaskQueue;
-use std::cell::RefCell;
+use crate::metrics::{Phase, RunawayLoop, SchedulerStats};
+use crate::queue::{QueuedTask, TaskQueue};
+use std::cell::{Cell, RefCell};
 use std::time::Instant;
 
+/// Drain passes allowed per queue per tick before it is treated as a runaway loop.
+pub const DEFAULT_MAX_PASSES: u32 = 1000;
+
+/// How many distinct task origins a `RunawayLoop` report carries.
+const RUNAWAY_SAMPLE_SIZE: usize = 8;
+
+type RunawayHook = Box<dyn Fn(&RunawayLoop)>;
+
 /// A single-threaded, cooperative scheduler.
 pub struct LocalScheduler {
     microtasks: TaskQueue,
@@ -12,6 +21,11 @@ pub struct LocalScheduler {
     // Preventing recursive ticks if needed
     in_tick: RefCell<bool>,
     dirty_signals: RefCell<Vec<nexa_signals::SignalId>>,
+    stats: RefCell<SchedulerStats>,
+    max_passes: Cell<u32>,
+    runaway_hook: RefCell<Option<RunawayHook>>,
+    /// Whether debug ticks publish the stats; see `set_devtools_reporting`.
+    devtools_reporting: Cell<bool>,
 }
 
 impl Default for LocalScheduler {
@@ -29,6 +43,10 @@ impl LocalScheduler {
             start_time: Instant::now(),
             in_tick: RefCell::new(false),
             dirty_signals: RefCell::new(Vec::new()),
+            stats: RefCell::new(SchedulerStats::default()),
+            max_passes: Cell::new(DEFAULT_MAX_PASSES),
+            runaway_hook: RefCell::new(None),
+            devtools_reporting: Cell::new(false),
         }
     }
 
@@ -42,44 +60,153 @@ impl LocalScheduler {
 
         *self.in_tick.borrow_mut() = true;
 
-        // 1. Drain Microtasks
-        // We loop until empty because microtasks can schedule more microtasks.
-        // Guard against infinite loops? u32 limit?
-        let mut loop_count = 0;
-        while !self.microtasks.is_empty() {
-            self.microtasks.drain();
-            loop_count += 1;
-            if loop_count > 1000 {
-                println!("Warn: Possible infinite microtask loop");
-                break;
+        {
+            let mut stats = self.stats.borrow_mut();
+            stats.ticks += 1;
+            for (phase, queue) in self.queues() {
+                let phase_stats = stats.phase_mut(phase);
+                phase_stats.max_queue_depth = phase_stats.max_queue_depth.max(queue.len());
             }
         }
 
+        // Tasks can schedule more tasks, so each queue drains in passes: a pass
+        // runs what was queued when it started. A chain still going after
+        // `max_passes` passes is reported as a runaway loop and left queued.
+        // 1. Drain Microtasks
+        self.drain(Phase::Microtask, &self.microtasks);
+
         // 2. Flush Effects
-        self.effects.drain();
+        self.drain(Phase::Effect, &self.effects);
 
         // 3. Flush Layout Effects
-        self.layout_effects.drain();
+        self.drain(Phase::LayoutEffect, &self.layout_effects);
 
         *self.in_tick.borrow_mut() = false;
 
+        #[cfg(debug_assertions)]
+        if self.devtools_reporting.get() {
+            self.report_to_devtools();
+        }
+
         !self.is_idle()
     }
 
+    fn queues(&self) -> [(Phase, &TaskQueue); 3] {
+        [
+            (Phase::Microtask, &self.microtasks),
+            (Phase::Effect, &self.effects),
+            (Phase::LayoutEffect, &self.layout_effects),
+        ]
+    }
+
+    fn drain(&self, phase: Phase, queue: &TaskQueue) {
+        let mut passes = 0;
+        let mut tasks_run = 0;
+        while !queue.is_empty() {
+            if passes >= self.max_passes.get() {
+                self.report_runaway(RunawayLoop {
+                    phase,
+                    passes,
+                    tasks_run,
+                    pending: queue.len(),
+                    sample_origins: queue.sample_origins(RUNAWAY_SAMPLE_SIZE),
+                });
+                return;
+            }
+            for _ in 0..queue.len() {
+                if let Some(queued) = queue.pop_queued() {
+                    self.run_task(phase, queued);
+                    tasks_run += 1;
+                }
+            }
+            passes += 1;
+        }
+    }
+
+    fn run_task(&self, phase: Phase, queued: QueuedTask) {
+        {
+            let mut stats = self.stats.borrow_mut();
+            let phase_stats = stats.phase_mut(phase);
+            phase_stats.executed += 1;
+            phase_stats.latency.record(queued.enqueued_at.elapsed());
+        }
+        (queued.task)();
+    }
+
+    fn report_runaway(&self, event: RunawayLoop) {
+        self.stats.borrow_mut().runaway_loops += 1;
+        // Taken out while it runs, so the hook can replace itself.
+        let hook = self.runaway_hook.borrow_mut().take();
+        if let Some(hook) = hook {
+            hook(&event);
+            let mut slot = self.runaway_hook.borrow_mut();
+            if slot.is_none() {
+                *slot = Some(hook);
+            }
+        } else {
+            tracing::warn!(
+                "Possible infinite {:?} loop: {} tasks still pending after {} passes, scheduled from {:?}",
+                event.phase,
+                event.pending,
+                event.passes,
+                event.sample_origins
+            );
+        }
+    }
+
+    /// Replace the default runaway-loop warning with a custom handler.
+    pub fn set_runaway_hook(&self, hook: impl Fn(&RunawayLoop) + 'static) {
+        *self.runaway_hook.borrow_mut() = Some(Box::new(hook));
+    }
+
+    /// Sets how many drain passes each queue gets per tick.
+    pub fn set_max_passes(&self, passes: u32) {
+        self.max_passes.set(passes);
+    }
+
+    /// Counters collected since creation (or the last `reset_stats`),
+    /// with queue depths taken at the time of the call.
+    pub fn stats(&self) -> SchedulerStats {
+        let mut stats = self.stats.borrow().clone();
+        for (phase, queue) in self.queues() {
+            stats.phase_mut(phase).queue_depth = queue.len();
+        }
+        stats
+    }
+
+    pub fn reset_stats(&self) {
+        *self.stats.borrow_mut() = SchedulerStats::default();
+    }
+
+    /// Publishes the stats to devtools at the end of every tick in debug
+    /// builds. Off by default, since publishing locks the global devtools
+    /// state on each tick.
+    pub fn set_devtools_reporting(&self, enabled: bool) {
+        self.devtools_reporting.set(enabled);
+    }
+
+    /// Publish the current stats to the devtools `SchedulerMetrics`.
+    pub fn report_to_devtools(&self) {
+        nexa_devtools::devtools().set_scheduler_metrics(self.stats().to_devtools());
+    }
+
     pub fn is_idle(&self) -> bool {
         self.microtasks.is_empty() && self.effects.is_empty() && self.layout_effects.is_empty()
     }
 }
 
 impl Scheduler for LocalScheduler {
+    #[track_caller]
     fn schedule_microtask(&self, task: Box<dyn FnOnce()>) {
         self.microtasks.push(task);
     }
 
+    #[track_caller]
     fn schedule_effect(&self, effect: Box<dyn FnOnce()>) {
         self.effects.push(effect);
     }
 
+    #[track_caller]
     fn schedule_layout_effect(&self, effect: Box<dyn FnOnce()>) {
         self.layout_effects.push(effect);
     }
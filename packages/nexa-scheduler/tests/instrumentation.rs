@@ -0,0 +1,147 @@
+use nexa_scheduler::{LocalScheduler, Phase, RunawayLoop, Scheduler};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+#[test]
+fn test_phase_counters_and_latency() {
+    let scheduler = LocalScheduler::new();
+
+    scheduler.schedule_microtask(Box::new(|| {}));
+    scheduler.schedule_microtask(Box::new(|| {}));
+    scheduler.schedule_effect(Box::new(|| {}));
+    scheduler.schedule_layout_effect(Box::new(|| {}));
+
+    let before = scheduler.stats();
+    assert_eq!(before.microtasks.queue_depth, 2);
+    assert_eq!(before.pending(), 4);
+
+    scheduler.tick();
+
+    let stats = scheduler.stats();
+    assert_eq!(stats.ticks, 1);
+    assert_eq!(stats.microtasks.executed, 2);
+    assert_eq!(stats.effects.executed, 1);
+    assert_eq!(stats.layout_effects.executed, 1);
+    assert_eq!(stats.microtasks.max_queue_depth, 2);
+    assert_eq!(stats.pending(), 0);
+    assert_eq!(stats.latency().count, 4);
+    assert_eq!(stats.latency().buckets.iter().sum::<u64>(), 4);
+
+    let metrics = stats.to_devtools();
+    assert_eq!(metrics.total_tasks_executed, 4);
+    assert_eq!(metrics.microtasks_executed, 2);
+    assert_eq!(metrics.latency_histogram.len(), 7);
+    assert!(
+        metrics
+            .latency_histogram
+            .last()
+            .unwrap()
+            .upper_bound_ms
+            .is_none()
+    );
+
+    scheduler.reset_stats();
+    assert_eq!(scheduler.stats().total_executed(), 0);
+}
+
+fn reschedule_forever(scheduler: Rc<LocalScheduler>) {
+    let next = scheduler.clone();
+    scheduler.schedule_microtask(Box::new(move || reschedule_forever(next)));
+}
+
+#[test]
+fn test_runaway_loop_hook() {
+    let scheduler = Rc::new(LocalScheduler::new());
+    let reports: Rc<RefCell<Vec<RunawayLoop>>> = Rc::new(RefCell::new(Vec::new()));
+
+    scheduler.set_max_passes(10);
+    {
+        let reports = reports.clone();
+        scheduler.set_runaway_hook(move |event| reports.borrow_mut().push(event.clone()));
+    }
+
+    reschedule_forever(scheduler.clone());
+
+    // The tick gives up and leaves the looping task queued.
+    assert!(scheduler.tick());
+
+    let reports = reports.borrow();
+    assert_eq!(reports.len(), 1);
+    let report = &reports[0];
+    assert_eq!(report.phase, Phase::Microtask);
+    assert_eq!(report.passes, 10);
+    assert_eq!(report.tasks_run, 10);
+    assert_eq!(report.pending, 1);
+    assert_eq!(report.sample_origins.len(), 1);
+    assert_eq!(report.sample_origins[0].file(), file!());
+
+    assert_eq!(scheduler.stats().runaway_loops, 1);
+}
+
+fn reschedule_effect_forever(scheduler: Rc<LocalScheduler>) {
+    let next = scheduler.clone();
+    scheduler.schedule_effect(Box::new(move || reschedule_effect_forever(next)));
+}
+
+#[test]
+fn test_runaway_effect_loop() {
+    let scheduler = Rc::new(LocalScheduler::new());
+    let reports: Rc<RefCell<Vec<RunawayLoop>>> = Rc::new(RefCell::new(Vec::new()));
+
+    scheduler.set_max_passes(5);
+    {
+        let reports = reports.clone();
+        scheduler.set_runaway_hook(move |event| reports.borrow_mut().push(event.clone()));
+    }
+
+    reschedule_effect_forever(scheduler.clone());
+    assert!(scheduler.tick());
+
+    let reports = reports.borrow();
+    assert_eq!(reports.len(), 1);
+    assert_eq!(reports[0].phase, Phase::Effect);
+    assert_eq!(reports[0].tasks_run, 5);
+    assert_eq!(reports[0].pending, 1);
+}
+
+#[test]
+fn test_runaway_hook_can_replace_itself() {
+    let scheduler = Rc::new(LocalScheduler::new());
+    let replaced = Rc::new(RefCell::new(0));
+
+    scheduler.set_max_passes(1);
+    {
+        let (inner, replaced) = (scheduler.clone(), replaced.clone());
+        scheduler.set_runaway_hook(move |_| {
+            let replaced = replaced.clone();
+            inner.set_runaway_hook(move |_| *replaced.borrow_mut() += 1);
+        });
+    }
+
+    reschedule_forever(scheduler.clone());
+    scheduler.tick();
+    scheduler.tick();
+    assert_eq!(*replaced.borrow(), 1);
+    assert_eq!(scheduler.stats().runaway_loops, 2);
+}
+
+#[cfg(debug_assertions)]
+#[test]
+fn test_devtools_reporting_is_opt_in() {
+    let scheduler = LocalScheduler::new();
+    for _ in 0..7 {
+        scheduler.schedule_microtask(Box::new(|| {}));
+    }
+    let published = || {
+        nexa_devtools::devtools()
+            .export_state()
+            .contains("\"microtasks_executed\":7")
+    };
+
+    scheduler.tick();
+    assert!(!published());
+
+    scheduler.set_devtools_reporting(true);
+    scheduler.tick();
+    assert!(published());
+}
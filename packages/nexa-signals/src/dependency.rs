@@ -60,8 +60,11 @@ pub fn clear_dependencies(id: SignalId) {
     GRAPH.with(|g| g.borrow_mut().clear_dependencies(id));
 }
 
+/// Called from the `Drop` of signals, memos and effects, which can run after
+/// the thread's graph is torn down, say from another thread-local. There is
+/// nothing left to remove them from then.
 pub fn remove_node(id: SignalId) {
-    GRAPH.with(|g| g.borrow_mut().remove_node(id));
+    let _ = GRAPH.try_with(|g| g.borrow_mut().remove_node(id));
 }
 
 pub fn batch<F, R>(f: F) -> R
@@ -250,3 +250,17 @@ fn test_fan_out_updates() {
         assert_eq!(memo.get(), 1);
     }
 }
+
+#[test]
+fn test_signal_in_thread_local_outlives_graph() {
+    thread_local! {
+        static HELD: RefCell<Option<nexa_signals::Signal<i32>>> = const { RefCell::new(None) };
+    }
+
+    // `HELD` is initialized before the graph, so it is torn down after it.
+    let result = std::thread::spawn(|| {
+        HELD.with(|held| *held.borrow_mut() = Some(signal(1)));
+    })
+    .join();
+    assert!(result.is_ok());
+}
@@ -1,12 +1,16 @@
 use futures::stream::Stream;
-use nexa_core::vdom::{NodeId, VDomArena, VirtualNode};
-use std::collections::VecDeque;
+use nexa_core::vdom::{AttributeValue, NodeId, VDomArena, VirtualNode};
+use nexa_core::{Runtime, Scheduler};
+use std::collections::{HashMap, VecDeque};
 use std::pin::Pin;
 use std::task::{Context, Poll};
 
 #[derive(Debug, Clone, Copy)]
 pub struct SsrConfig {
     pub chunk_size: usize,
+    /// Emits the comments `Runtime::hydrate` needs to line the markup up
+    /// with the tree: between adjacent text nodes, which would otherwise
+    /// merge, and in place of empty text.
     pub enable_hydration: bool,
 }
 
@@ -21,11 +25,15 @@ impl Default for SsrConfig {
 
 pub struct SsrStream<'a> {
     arena: &'a VDomArena,
+    /// What each mounted component rendered, by component node.
+    components: HashMap<NodeId, NodeId>,
     config: SsrConfig,
     stack: VecDeque<RenderOp>,
     buffer: String,
     suspense_tasks: VecDeque<SuspenseTask>,
     next_suspense_id: u32,
+    /// The last thing written was text.
+    after_text: bool,
 }
 
 enum RenderOp {
@@ -44,34 +52,43 @@ impl<'a> SsrStream<'a> {
         stack.push_front(RenderOp::Visit(root));
         Self {
             arena,
+            components: HashMap::new(),
             config,
             stack,
             buffer: String::with_capacity(config.chunk_size),
             suspense_tasks: VecDeque::new(),
             next_suspense_id: 0,
+            after_text: false,
         }
     }
 
     fn render_node(&mut self, id: NodeId) -> Option<String> {
         let node = self.arena.nodes.get(id)?;
+        let is_text = matches!(node, VirtualNode::Text(_));
+        let out = self.render_markup(id, node);
+        if out.is_some() && !is_text {
+            self.after_text = false;
+        }
+        out
+    }
+
+    fn render_markup(&mut self, id: NodeId, node: &'a VirtualNode) -> Option<String> {
         match node {
             VirtualNode::Element(el) => {
                 let mut out = format!("<{}", el.tag);
 
-                // Hydration marker
-                if self.config.enable_hydration {
-                    // We use the raw SlotMap index as a stable ID for hydration
-                    // For now, representing it simply.
-                    out.push_str(&format!(" data-nexa-id=\"{:?}\"", id));
-                }
-
                 for attr in &el.props {
                     if is_boolean_attribute(attr.name) {
-                        if attr.value == "true" {
+                        // Boolean attributes are present or absent; text counts as `"true"`.
+                        let present = match &attr.value {
+                            AttributeValue::Text(text) => text == "true",
+                            value => value.to_attribute_text(attr.name).is_some(),
+                        };
+                        if present {
                             out.push_str(&format!(" {}", attr.name));
                         }
-                    } else {
-                        out.push_str(&format!(" {}=\"{}\"", attr.name, escape_html(&attr.value)));
+                    } else if let Some(text) = attr.value.to_attribute_text(attr.name) {
+                        out.push_str(&format!(" {}=\"{}\"", attr.name, escape_html(&text)));
                     }
                 }
                 out.push('>');
@@ -82,6 +99,20 @@ impl<'a> SsrStream<'a> {
                 }
                 Some(out)
             }
+            VirtualNode::Text(txt) if self.config.enable_hydration => {
+                let mut out = String::new();
+                if self.after_text {
+                    out.push_str("<!--#-->");
+                }
+                if txt.text.is_empty() {
+                    out.push_str("<!--#-->");
+                    self.after_text = false;
+                } else {
+                    out.push_str(&escape_html(&txt.text));
+                    self.after_text = true;
+                }
+                Some(out)
+            }
             VirtualNode::Text(txt) => Some(escape_html(&txt.text)),
             VirtualNode::Fragment(frag) => {
                 for &child in frag.children.iter().rev() {
@@ -105,11 +136,22 @@ impl<'a> SsrStream<'a> {
 
                 Some(out)
             }
-            VirtualNode::Component(comp) => {
-                // In a production SSR, we'd have expanded components.
-                // If not, we emit a comment.
-                Some(format!("<!-- component: {} -->", comp.name))
+            VirtualNode::ErrorBoundary(eb) => {
+                let shown = eb.caught.as_ref().map_or(eb.content, |c| c.fallback);
+                self.stack.push_front(RenderOp::Visit(shown));
+                None
             }
+            // The target container is outside this document fragment; the
+            // content renders on the client.
+            VirtualNode::Portal(portal) => Some(format!("<!-- portal: {} -->", portal.target)),
+            VirtualNode::Component(comp) => match self.components.get(&id) {
+                Some(&root) => {
+                    self.stack.push_front(RenderOp::Visit(root));
+                    None
+                }
+                // Not mounted, so there is nothing rendered to expand.
+                None => Some(format!("<!-- component: {} -->", comp.name)),
+            },
             VirtualNode::Placeholder => Some("<!-- nexa-placeholder -->".to_string()),
         }
     }
@@ -128,6 +170,7 @@ impl<'a> Stream for SsrStream<'a> {
                         }
                     }
                     RenderOp::Close(tag) => {
+                        self.after_text = false;
                         self.buffer.push_str("</");
                         self.buffer.push_str(tag);
                         self.buffer.push_str(">");
@@ -146,6 +189,7 @@ impl<'a> Stream for SsrStream<'a> {
                         enable_hydration: self.config.enable_hydration,
                     },
                 );
+                sub_stream.components = self.components.clone();
 
                 let mut content = String::new();
                 while let Some(chunk) = sub_stream.stack.pop_front() {
@@ -156,6 +200,7 @@ impl<'a> Stream for SsrStream<'a> {
                             }
                         }
                         RenderOp::Close(tag) => {
+                            sub_stream.after_text = false;
                             content.push_str("</");
                             content.push_str(tag);
                             content.push_str(">");
@@ -170,7 +215,7 @@ impl<'a> Stream for SsrStream<'a> {
                         (function() {{\
                             var fallback = document.getElementById('suspense-fallback-{}');\
                             var content = document.getElementById('suspense-content-{}').content;\
-                            fallback.parentNode.replaceChild(content, fallback);\
+                            fallback.replaceChildren(content);\
                         }})();\
                     </script>",
                     task.id, content, task.id, task.id
@@ -235,6 +280,7 @@ fn is_boolean_attribute(name: &str) -> bool {
 
 pub struct Renderer<'a> {
     arena: &'a VDomArena,
+    components: HashMap<NodeId, NodeId>,
     config: SsrConfig,
 }
 
@@ -242,22 +288,44 @@ impl<'a> Renderer<'a> {
     pub fn new(arena: &'a VDomArena) -> Self {
         Self {
             arena,
+            components: HashMap::new(),
             config: SsrConfig::default(),
         }
     }
 
+    /// Renders the tree mounted in `runtime`, components expanded into
+    /// what they rendered. Render from `runtime.root_node`.
+    pub fn from_runtime<S: Scheduler>(runtime: &'a Runtime<S>, config: SsrConfig) -> Self {
+        let components = runtime
+            .scopes
+            .values()
+            .filter_map(|scope| Some((scope.node, scope.root_node?)))
+            .collect();
+        Self {
+            arena: &runtime.arena,
+            components,
+            config,
+        }
+    }
+
     pub fn with_config(arena: &'a VDomArena, config: SsrConfig) -> Self {
-        Self { arena, config }
+        Self {
+            arena,
+            components: HashMap::new(),
+            config,
+        }
     }
 
     pub fn render_to_stream(&self, root_id: NodeId) -> SsrStream<'a> {
-        SsrStream::new(
+        let mut stream = SsrStream::new(
             self.arena,
             root_id,
             SsrConfig {
                 chunk_size: self.config.chunk_size,
                 enable_hydration: self.config.enable_hydration,
             },
-        )
+        );
+        stream.components = self.components.clone();
+        stream
     }
 }
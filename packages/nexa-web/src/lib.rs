@@ -1,8 +1,12 @@
-use nexa_core::{Mutation, Runtime};
+use nexa_core::vdom::attribute_namespace;
+use nexa_core::{
+    AttributeValue, DragData, EventData, FocusData, FormData, KeyboardData, Modifiers, MouseData,
+    Mutation, MutationSink, NodeRef, PointerData, Runtime, TemplateNode, WheelData,
+};
 use nexa_scheduler::LocalScheduler;
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 use std::collections::HashMap;
-use std::rc::Rc;
+use std::rc::{Rc, Weak};
 use wasm_bindgen::prelude::*;
 use web_sys::{Document, Element, Event, Node};
 
@@ -15,178 +19,672 @@ pub struct WebApp {
 struct WebInterpreter {
     document: Document,
     nodes: HashMap<u64, Node>,
-    event_listeners: HashMap<u64, Vec<Closure<dyn FnMut(Event)>>>,
+    /// The runtime only removes the top of a removed subtree, so parents
+    /// and children are tracked to drop the rest of it from `nodes` too.
+    parents: HashMap<u64, u64>,
+    children: HashMap<u64, Vec<u64>>,
+    /// Delegated listeners on the containers, one per event name.
+    event_listeners: HashMap<String, Closure<dyn FnMut(Event)>>,
+    /// The elements holding the delegated listeners: the app's root and the
+    /// targets of `mount_at` widgets and portals, except those inside
+    /// another container, whose events reach it anyway.
+    containers: Vec<Node>,
     root_id: Option<u64>,
     runtime: Rc<RefCell<Runtime<LocalScheduler>>>,
+    /// Registered templates, built once and deep-cloned for every instance.
+    templates: HashMap<String, Vec<Node>>,
+    /// Root of the most recently loaded template; template paths start here.
+    last_template: Option<Node>,
+    /// Id of `last_template`, which nodes found by path belong under.
+    last_template_id: Option<u64>,
+    /// Paths start at server-rendered markup rather than a template clone.
+    hydrating: bool,
+    /// Handle to this interpreter, for the event listeners it installs.
+    this: Weak<RefCell<WebInterpreter>>,
+}
+
+thread_local! {
+    /// The interpreter of the running app, for resolving node refs.
+    static INTERPRETER: RefCell<Weak<RefCell<WebInterpreter>>> = const { RefCell::new(Weak::new()) };
+}
+
+/// Resolves node refs to the DOM nodes this renderer created for them.
+pub trait NodeRefExt {
+    /// The rendered element, once the mutations creating it are applied.
+    fn element(&self) -> Option<Element>;
+
+    /// The rendered element as a more specific type, like `HtmlInputElement`.
+    fn cast<T: JsCast>(&self) -> Option<T> {
+        self.element()?.dyn_into().ok()
+    }
+}
+
+impl NodeRefExt for NodeRef {
+    fn element(&self) -> Option<Element> {
+        let id = self.get()?;
+        let interpreter = INTERPRETER.with(|i| i.borrow().upgrade())?;
+        let interpreter = interpreter.try_borrow().ok()?;
+        interpreter.nodes.get(&id)?.clone().dyn_into().ok()
+    }
 }
 
 impl WebInterpreter {
-    fn new(document: Document, runtime: Rc<RefCell<Runtime<LocalScheduler>>>) -> Self {
+    fn new(
+        document: Document,
+        runtime: Rc<RefCell<Runtime<LocalScheduler>>>,
+        this: Weak<RefCell<WebInterpreter>>,
+    ) -> Self {
         Self {
             document,
             nodes: HashMap::new(),
+            parents: HashMap::new(),
+            children: HashMap::new(),
             event_listeners: HashMap::new(),
+            containers: Vec::new(),
             root_id: None,
             runtime,
+            templates: HashMap::new(),
+            last_template: None,
+            last_template_id: None,
+            hydrating: false,
+            this,
         }
     }
 
-    fn apply_mutations(&mut self, mutations: Vec<Mutation>, handle: Rc<RefCell<WebInterpreter>>) {
-        tracing::debug!("Applying {} mutations", mutations.len());
-        for mutation in mutations {
-            tracing::trace!("Mutation: {:?}", mutation);
-            match mutation {
-                Mutation::PushRoot { id } => {
-                    self.root_id = Some(id);
-                    tracing::debug!("Root ID set to {}", id);
-                }
-                Mutation::CreateElement { tag, id } => {
-                    web_sys::console::log_1(
-                        &format!("Created element '{}' with id {}", tag, id).into(),
-                    );
-                    let el = self.document.create_element(&tag).unwrap();
-                    el.set_attribute("data-nexa-id", &id.to_string()).unwrap();
-                    self.nodes.insert(id, el.into());
+    fn build_template_node(&self, node: &TemplateNode) -> Node {
+        match node {
+            TemplateNode::Element {
+                tag,
+                namespace,
+                attrs,
+                children,
+                ..
+            } => {
+                let el = self
+                    .document
+                    .create_element_ns(namespace.as_deref(), tag)
+                    .unwrap();
+                for attr in attrs.iter() {
+                    let value = AttributeValue::Text(attr.value.to_string());
+                    apply_attribute(&el, &attr.name, &value, attribute_namespace(&attr.name));
                 }
-                Mutation::CreateTextNode { text, id } => {
-                    let node = self.document.create_text_node(&text);
-                    self.nodes.insert(id, node.into());
+                for child in children.iter() {
+                    el.append_child(&self.build_template_node(child)).unwrap();
                 }
-                Mutation::AppendChildren { id, m } => {
-                    let parent = if id == 0 {
-                        // Special case for container
-                        // In mount, we inserted container as 0
-                        self.nodes.get(&0).expect("Container not found (id=0)")
+                el.into()
+            }
+            TemplateNode::Text { text } => self.document.create_text_node(text).into(),
+            TemplateNode::DynamicText => self.document.create_text_node("").into(),
+            TemplateNode::Dynamic => self.document.create_comment("nexa-placeholder").into(),
+        }
+    }
+
+    fn node_at_path(&self, path: &[u8]) -> Option<Node> {
+        let mut node = self.last_template.clone()?;
+        for &idx in path {
+            node = node.child_nodes().get(idx as u32)?;
+        }
+        Some(node)
+    }
+
+    fn assign_id(&mut self, node: Node, id: u64) {
+        if let Some(el) = node.dyn_ref::<Element>() {
+            el.set_attribute("data-nexa-id", &id.to_string()).unwrap();
+        }
+        self.nodes.insert(id, node);
+    }
+
+    /// Moves `ids` under `parent`, out of wherever they were.
+    fn adopt(&mut self, parent: u64, ids: &[u64]) {
+        for &id in ids {
+            self.detach(id);
+            self.parents.insert(id, parent);
+            self.children.entry(parent).or_default().push(id);
+        }
+    }
+
+    fn detach(&mut self, id: u64) {
+        if let Some(parent) = self.parents.remove(&id)
+            && let Some(siblings) = self.children.get_mut(&parent)
+        {
+            siblings.retain(|&sibling| sibling != id);
+        }
+    }
+
+    /// Drops `id` and everything under it.
+    fn forget(&mut self, id: u64) {
+        self.detach(id);
+        let mut stack = vec![id];
+        while let Some(id) = stack.pop() {
+            self.nodes.remove(&id);
+            self.parents.remove(&id);
+            stack.extend(self.children.remove(&id).unwrap_or_default());
+        }
+    }
+
+    /// Adopts `ids` under the template instance that paths start from.
+    fn adopt_into_template(&mut self, ids: &[u64]) {
+        if let Some(template) = self.last_template_id {
+            self.adopt(template, ids);
+        }
+    }
+
+    fn replace_node(&mut self, old: &Node, m: &[u64]) {
+        let Some(parent) = old.parent_node() else {
+            tracing::error!("Replaced node has no parent");
+            return;
+        };
+        for child_id in m {
+            if let Some(child) = self.nodes.get(child_id) {
+                parent.insert_before(child, Some(old)).unwrap();
+            } else {
+                tracing::error!("Child node {} not found for replace", child_id);
+            }
+        }
+        parent.remove_child(old).unwrap();
+    }
+
+    /// Reports server markup that differs from what hydration expects: a
+    /// node of another type, or text with other contents. Only checked in
+    /// debug builds.
+    fn check_hydrated(&self, node: &Node, node_type: u16, text: Option<&str>, path: &[u8]) {
+        if !cfg!(debug_assertions) || !self.hydrating {
+            return;
+        }
+        if node.node_type() != node_type {
+            web_sys::console::error_1(
+                &format!(
+                    "Nexa: Hydration mismatch at {:?}: expected {}, found {}",
+                    path,
+                    if node_type == Node::TEXT_NODE {
+                        "text"
                     } else {
-                        self.nodes.get(&id).expect("Parent node not found")
-                    };
+                        "an element"
+                    },
+                    node.node_name()
+                )
+                .into(),
+            );
+        } else if let Some(text) = text
+            && node.text_content().as_deref() != Some(text)
+        {
+            web_sys::console::error_1(
+                &format!(
+                    "Nexa: Hydration mismatch at {:?}: expected text {:?}, found {:?}",
+                    path,
+                    text,
+                    node.text_content().unwrap_or_default()
+                )
+                .into(),
+            );
+        }
+    }
 
-                    for child_id in m {
-                        if let Some(child) = self.nodes.get(&child_id) {
-                            parent.append_child(child).unwrap();
-                        } else {
-                            tracing::error!("Child node {} not found for append", child_id);
-                        }
-                    }
-                }
-                Mutation::SetAttribute {
-                    name, value, id, ..
-                } => {
-                    if let Some(node) = self.nodes.get(&id) {
-                        if let Some(el) = node.dyn_ref::<Element>() {
-                            el.set_attribute(&name, &value).unwrap();
-                        }
+    fn report_missing(&self, path: &[u8], mutation: &str) {
+        if self.hydrating {
+            web_sys::console::error_1(
+                &format!(
+                    "Nexa: Hydration mismatch at {:?}: no server-rendered node",
+                    path
+                )
+                .into(),
+            );
+        } else {
+            tracing::error!("No template node at {:?} for {}", path, mutation);
+        }
+    }
+
+    /// Attaches the delegated listeners to a container, unless events from
+    /// it already reach another one. Containers inside it hand their
+    /// listeners over, so no event is dispatched twice.
+    fn add_container(&mut self, node: &Node) {
+        if self.containers.iter().any(|c| c.contains(Some(node))) {
+            return;
+        }
+        let (inner, outer): (Vec<Node>, Vec<Node>) = std::mem::take(&mut self.containers)
+            .into_iter()
+            .partition(|c| node.contains(Some(c)));
+        self.containers = outer;
+        for (name, closure) in &self.event_listeners {
+            for container in &inner {
+                container
+                    .remove_event_listener_with_callback_and_bool(
+                        name,
+                        closure.as_ref().unchecked_ref(),
+                        true,
+                    )
+                    .unwrap();
+            }
+            node.add_event_listener_with_callback_and_bool(
+                name,
+                closure.as_ref().unchecked_ref(),
+                true,
+            )
+            .unwrap();
+        }
+        self.containers.push(node.clone());
+    }
+
+    /// Starts listening for `event_name` on the containers. Events are
+    /// delegated: a single capture-phase listener per event name finds the
+    /// nearest element with an id and lets the runtime dispatch from there.
+    fn listen(&mut self, event_name: &str) {
+        if self.event_listeners.contains_key(event_name) {
+            return;
+        }
+
+        let runtime = self.runtime.clone();
+        let interpreter = self.this.clone();
+        let name = event_name.to_string();
+
+        let closure = Closure::wrap(Box::new(move |event: Event| {
+            let Some(node_id) = event_target_id(&event) else {
+                return;
+            };
+            let nexa_event = to_nexa_event(&event);
+
+            runtime
+                .borrow_mut()
+                .handle_event(node_id, &name, nexa_event.clone());
+
+            if nexa_event.default_prevented() {
+                event.prevent_default();
+            }
+            if nexa_event.propagation_stopped() {
+                event.stop_propagation();
+            }
+
+            // Trigger updates
+            if let Some(interpreter) = interpreter.upgrade() {
+                flush(&runtime, &interpreter);
+            }
+        }) as Box<dyn FnMut(Event)>);
+
+        // Capture, so events that don't bubble (focus, blur, ...) reach us too.
+        for container in &self.containers {
+            container
+                .add_event_listener_with_callback_and_bool(
+                    event_name,
+                    closure.as_ref().unchecked_ref(),
+                    true,
+                )
+                .unwrap();
+        }
+
+        self.event_listeners.insert(event_name.to_string(), closure);
+    }
+}
+
+impl MutationSink for WebInterpreter {
+    fn push_batch(&mut self, batch: Vec<Mutation>) {
+        tracing::debug!("Applying {} mutations", batch.len());
+        for mutation in batch {
+            self.push(mutation);
+        }
+    }
+
+    fn push(&mut self, mutation: Mutation) {
+        tracing::trace!("Mutation: {:?}", mutation);
+        match mutation {
+            Mutation::PushRoot { id } => {
+                self.root_id = Some(id);
+                tracing::debug!("Root ID set to {}", id);
+            }
+            Mutation::CreateElement { tag, id } => {
+                web_sys::console::log_1(
+                    &format!("Created element '{}' with id {}", tag, id).into(),
+                );
+                let el = self.document.create_element(&tag).unwrap();
+                el.set_attribute("data-nexa-id", &id.to_string()).unwrap();
+                self.nodes.insert(id, el.into());
+            }
+            Mutation::CreateElementNs { tag, ns, id } => {
+                let el = self.document.create_element_ns(Some(&ns), &tag).unwrap();
+                el.set_attribute("data-nexa-id", &id.to_string()).unwrap();
+                self.nodes.insert(id, el.into());
+            }
+            Mutation::CreateTextNode { text, id } => {
+                let node = self.document.create_text_node(&text);
+                self.nodes.insert(id, node.into());
+            }
+            Mutation::AppendChildren { id, m } => {
+                let parent = if id == 0 {
+                    // Special case for container
+                    // In mount, we inserted container as 0
+                    self.nodes.get(&0).expect("Container not found (id=0)")
+                } else {
+                    self.nodes.get(&id).expect("Parent node not found")
+                };
+
+                for &child_id in &m {
+                    if let Some(child) = self.nodes.get(&child_id) {
+                        parent.append_child(child).unwrap();
+                    } else {
+                        tracing::error!("Child node {} not found for append", child_id);
                     }
                 }
-                Mutation::SetText { value, id } => {
-                    if let Some(node) = self.nodes.get(&id) {
-                        node.set_text_content(Some(&value));
+                self.adopt(id, &m);
+            }
+            Mutation::SetAttribute {
+                name,
+                value,
+                id,
+                ns,
+            } => {
+                if let Some(node) = self.nodes.get(&id) {
+                    if let Some(el) = node.dyn_ref::<Element>() {
+                        apply_attribute(el, &name, &value, ns.as_deref());
                     }
                 }
-                Mutation::NewEventListener { name, id } => {
-                    self.add_event_listener(id, &name, handle.clone());
+            }
+            Mutation::SetText { value, id } => {
+                if let Some(node) = self.nodes.get(&id) {
+                    node.set_text_content(Some(&value));
                 }
-                Mutation::Remove { id } => {
-                    if let Some(node) = self.nodes.remove(&id) {
-                        if let Some(parent) = node.parent_node() {
-                            parent.remove_child(&node).unwrap();
-                        }
+            }
+            Mutation::NewEventListener { name, .. } => {
+                self.listen(&name);
+            }
+            Mutation::RemoveEventListener { .. } => {
+                // The delegated listener is shared by every element, and
+                // the runtime only calls the listeners an element still has.
+            }
+            Mutation::Remove { id } => {
+                if let Some(node) = self.nodes.get(&id) {
+                    if let Some(parent) = node.parent_node() {
+                        parent.remove_child(node).unwrap();
                     }
-                    self.event_listeners.remove(&id);
                 }
-                Mutation::InsertBefore { id, m } => {
-                    // id is the reference node (next sibling)
-                    let ref_node = if let Some(n) = self.nodes.get(&id) {
-                        n
-                    } else {
-                        tracing::error!("Reference node {} not found for InsertBefore", id);
-                        continue;
-                    };
-
-                    if let Some(parent) = ref_node.parent_node() {
-                        for child_id in m {
-                            if let Some(child) = self.nodes.get(&child_id) {
-                                parent.insert_before(child, Some(ref_node)).unwrap();
-                            } else {
-                                tracing::error!(
-                                    "Child node {} not found for InsertBefore",
-                                    child_id
-                                );
-                            }
+                self.forget(id);
+            }
+            Mutation::InsertBefore { id, m } => {
+                // id is the reference node (next sibling)
+                let ref_node = if let Some(n) = self.nodes.get(&id) {
+                    n
+                } else {
+                    tracing::error!("Reference node {} not found for InsertBefore", id);
+                    return;
+                };
+
+                if let Some(parent) = ref_node.parent_node() {
+                    for &child_id in &m {
+                        if let Some(child) = self.nodes.get(&child_id) {
+                            parent.insert_before(child, Some(ref_node)).unwrap();
+                        } else {
+                            tracing::error!("Child node {} not found for InsertBefore", child_id);
                         }
-                    } else {
-                        tracing::error!("Reference node {} has no parent", id);
                     }
+                } else {
+                    tracing::error!("Reference node {} has no parent", id);
                 }
-                Mutation::RemoveAttribute { name, id } => {
-                    if let Some(node) = self.nodes.get(&id) {
-                        if let Some(el) = node.dyn_ref::<Element>() {
-                            el.remove_attribute(&name).unwrap();
-                        }
+                if let Some(&parent) = self.parents.get(&id) {
+                    self.adopt(parent, &m);
+                }
+            }
+            Mutation::RemoveAttribute { name, id } => {
+                if let Some(node) = self.nodes.get(&id) {
+                    if let Some(el) = node.dyn_ref::<Element>() {
+                        let ns = attribute_namespace(&name);
+                        apply_attribute(el, &name, &AttributeValue::None, ns);
                     }
                 }
-                _ => {
-                    // Handle other mutations as needed
+            }
+            Mutation::RegisterTemplate { template } => {
+                let roots = template
+                    .roots
+                    .iter()
+                    .map(|root| self.build_template_node(root))
+                    .collect();
+                self.templates.insert(template.name.to_string(), roots);
+            }
+            Mutation::LoadTemplate { name, index, id } => {
+                let Some(root) = self.templates.get(&name).and_then(|roots| roots.get(index))
+                else {
+                    tracing::error!("Template {}[{}] not registered", name, index);
+                    return;
+                };
+                let node = root.clone_node_with_deep(true).unwrap();
+                self.last_template = Some(node.clone());
+                self.last_template_id = Some(id);
+                self.hydrating = false;
+                self.assign_id(node, id);
+            }
+            Mutation::LoadExisting { id } => {
+                self.last_template = self.nodes.get(&id).cloned();
+                self.last_template_id = Some(id);
+                self.hydrating = true;
+            }
+            Mutation::AssignId { path, id } => {
+                if let Some(node) = self.node_at_path(&path) {
+                    self.check_hydrated(&node, Node::ELEMENT_NODE, None, &path);
+                    self.assign_id(node, id);
+                    self.adopt_into_template(&[id]);
+                } else {
+                    self.report_missing(&path, "AssignId");
                 }
             }
+            Mutation::HydrateText { path, value, id } => {
+                if let Some(node) = self.node_at_path(&path) {
+                    self.check_hydrated(&node, Node::TEXT_NODE, Some(&value), &path);
+                    node.set_text_content(Some(&value));
+                    self.assign_id(node, id);
+                    self.adopt_into_template(&[id]);
+                } else {
+                    self.report_missing(&path, "HydrateText");
+                }
+            }
+            Mutation::ReplaceWith { id, m } => {
+                if let Some(old) = self.nodes.get(&id).cloned() {
+                    self.replace_node(&old, &m);
+                }
+                if let Some(&parent) = self.parents.get(&id) {
+                    self.adopt(parent, &m);
+                }
+                self.forget(id);
+            }
+            Mutation::ReplacePlaceholder { path, m } => {
+                if let Some(old) = self.node_at_path(&path) {
+                    self.replace_node(&old, &m);
+                }
+                self.adopt_into_template(&m);
+            }
+            Mutation::LoadContainer { target, id } => {
+                let Some(el) = self.document.get_element_by_id(&target) else {
+                    tracing::error!("Portal target #{} not found", target);
+                    return;
+                };
+                let node: Node = el.into();
+                self.add_container(&node);
+                self.nodes.insert(id, node);
+            }
+            _ => {
+                // Handle other mutations as needed
+            }
         }
     }
+}
 
-    fn add_event_listener(
-        &mut self,
-        id: u64,
-        event_name: &str,
-        handle: Rc<RefCell<WebInterpreter>>,
-    ) {
-        web_sys::console::log_1(&format!("Adding listener '{}' to node {}", event_name, id).into());
-        let node = if let Some(n) = self.nodes.get(&id) {
-            n.clone()
-        } else {
-            tracing::error!("Cannot add listener to missing node {}", id);
-            return;
+/// Writes an attribute, or for `value`, `checked` and the like the live DOM
+/// property, which the attribute stops controlling once the user interacts.
+fn apply_attribute(el: &Element, name: &str, value: &AttributeValue, ns: Option<&str>) {
+    if nexa_core::vdom::is_property(name) {
+        let js_value = match value {
+            AttributeValue::Text(text) => JsValue::from_str(text),
+            AttributeValue::Bool(b) => JsValue::from_bool(*b),
+            AttributeValue::Int(i) => JsValue::from_f64(*i as f64),
+            AttributeValue::Float(f) => JsValue::from_f64(*f),
+            AttributeValue::None if name == "value" => JsValue::from_str(""),
+            AttributeValue::None => JsValue::FALSE,
+            AttributeValue::Listener => return,
         };
+        js_sys::Reflect::set(el, &JsValue::from_str(name), &js_value).unwrap();
+        return;
+    }
+    match (value.to_attribute_text(name), ns) {
+        (Some(text), Some(ns)) => el.set_attribute_ns(Some(ns), name, &text).unwrap(),
+        (Some(text), None) => el.set_attribute(name, &text).unwrap(),
+        // Namespaced attributes are removed by local name.
+        (None, Some(ns)) => {
+            let local = name.split_once(':').map_or(name, |(_, local)| local);
+            el.remove_attribute_ns(Some(ns), local).unwrap()
+        }
+        (None, None) => el.remove_attribute(name).unwrap(),
+    }
+}
 
-        // Clone runtime for the closure
-        let runtime = self.runtime.clone();
-        let name = event_name.to_string();
-        let node_id = id;
+/// The id of the nearest element at or above the event target.
+fn event_target_id(event: &Event) -> Option<u64> {
+    let node = event.target()?.dyn_into::<Node>().ok()?;
+    let el = match node.dyn_into::<Element>() {
+        Ok(el) => el,
+        Err(node) => node.parent_element()?,
+    };
+    el.closest("[data-nexa-id]")
+        .ok()??
+        .get_attribute("data-nexa-id")?
+        .parse()
+        .ok()
+}
 
-        // Clone handle for the closure
-        let interpreter_handle = handle;
+fn to_nexa_event(event: &Event) -> nexa_core::Event {
+    let data = if let Some(e) = event.dyn_ref::<web_sys::PointerEvent>() {
+        EventData::Pointer(PointerData {
+            mouse: mouse_data(e),
+            pointer_id: e.pointer_id(),
+            pointer_type: e.pointer_type(),
+            pressure: e.pressure(),
+            width: e.width() as f64,
+            height: e.height() as f64,
+            is_primary: e.is_primary(),
+        })
+    } else if let Some(e) = event.dyn_ref::<web_sys::WheelEvent>() {
+        EventData::Wheel(WheelData {
+            mouse: mouse_data(e),
+            delta_x: e.delta_x(),
+            delta_y: e.delta_y(),
+            delta_z: e.delta_z(),
+            delta_mode: e.delta_mode(),
+        })
+    } else if let Some(e) = event.dyn_ref::<web_sys::DragEvent>() {
+        let files = e
+            .data_transfer()
+            .and_then(|dt| dt.files())
+            .map(|files| {
+                (0..files.length())
+                    .filter_map(|i| files.get(i))
+                    .map(|file| file.name())
+                    .collect()
+            })
+            .unwrap_or_default();
+        EventData::Drag(DragData {
+            mouse: mouse_data(e),
+            files,
+        })
+    } else if let Some(e) = event.dyn_ref::<web_sys::MouseEvent>() {
+        EventData::Mouse(mouse_data(e))
+    } else if let Some(e) = event.dyn_ref::<web_sys::KeyboardEvent>() {
+        EventData::Keyboard(KeyboardData {
+            key: e.key(),
+            code: e.code(),
+            modifiers: Modifiers {
+                alt: e.alt_key(),
+                ctrl: e.ctrl_key(),
+                meta: e.meta_key(),
+                shift: e.shift_key(),
+            },
+            repeat: e.repeat(),
+        })
+    } else if event.dyn_ref::<web_sys::FocusEvent>().is_some() {
+        EventData::Focus(FocusData::default())
+    } else {
+        match event.type_().as_str() {
+            "input" | "change" => EventData::Form(FormData {
+                value: event
+                    .target()
+                    .map(|t| string_property(&t, "value"))
+                    .unwrap_or_default(),
+                values: Vec::new(),
+            }),
+            "submit" => EventData::Form(FormData {
+                value: String::new(),
+                values: event
+                    .target()
+                    .and_then(|t| t.dyn_into::<web_sys::HtmlFormElement>().ok())
+                    .map(|form| form_values(&form))
+                    .unwrap_or_default(),
+            }),
+            _ => EventData::Unknown,
+        }
+    };
+    nexa_core::Event::new(data)
+}
 
-        let closure = Closure::wrap(Box::new(move |event: Event| {
-            // Map web_sys Event to nexa_core Event
-            let nexa_event = match event.type_().as_str() {
-                "click" => nexa_core::Event::Click,
-                "input" => {
-                    let value = event
-                        .target()
-                        .and_then(|t| t.dyn_into::<web_sys::HtmlInputElement>().ok())
-                        .map(|input| input.value())
-                        .unwrap_or_default();
-                    nexa_core::Event::Input(value)
-                }
-                _ => nexa_core::Event::Unknown,
-            };
+fn mouse_data(e: &web_sys::MouseEvent) -> MouseData {
+    MouseData {
+        client_x: e.client_x() as f64,
+        client_y: e.client_y() as f64,
+        screen_x: e.screen_x() as f64,
+        screen_y: e.screen_y() as f64,
+        button: e.button(),
+        buttons: e.buttons(),
+        modifiers: Modifiers {
+            alt: e.alt_key(),
+            ctrl: e.ctrl_key(),
+            meta: e.meta_key(),
+            shift: e.shift_key(),
+        },
+    }
+}
 
-            runtime
-                .borrow_mut()
-                .handle_event(node_id, &name, nexa_event);
+fn string_property(target: &JsValue, name: &str) -> String {
+    js_sys::Reflect::get(target, &name.into())
+        .ok()
+        .and_then(|v| v.as_string())
+        .unwrap_or_default()
+}
 
-            // Trigger updates
-            let mutations = runtime.borrow_mut().drain_mutations();
-            if !mutations.is_empty() {
-                interpreter_handle
-                    .borrow_mut()
-                    .apply_mutations(mutations, interpreter_handle.clone());
-            }
-        }) as Box<dyn FnMut(Event)>);
+fn form_values(form: &web_sys::HtmlFormElement) -> Vec<(String, String)> {
+    let elements = form.elements();
+    (0..elements.length())
+        .filter_map(|i| elements.item(i))
+        .filter_map(|el| {
+            let name = string_property(&el, "name");
+            (!name.is_empty()).then(|| (name, string_property(&el, "value")))
+        })
+        .collect()
+}
 
-        node.add_event_listener_with_callback(event_name, closure.as_ref().unchecked_ref())
-            .unwrap();
+/// Pushes the runtime's pending mutations into the interpreter. The runtime
+/// is released first, since the listeners the mutations install borrow it.
+fn flush(runtime: &RefCell<Runtime<LocalScheduler>>, interpreter: &RefCell<WebInterpreter>) {
+    let batch = runtime.borrow_mut().drain_mutations();
+    if !batch.is_empty() {
+        interpreter.borrow_mut().push_batch(batch);
+    }
+}
 
-        self.event_listeners.entry(id).or_default().push(closure);
+/// Runs an update and applies its mutations in a microtask, at most once per
+/// microtask however many wakes came in.
+fn schedule_flush(
+    runtime: Rc<RefCell<Runtime<LocalScheduler>>>,
+    interpreter: Rc<RefCell<WebInterpreter>>,
+    scheduled: Rc<Cell<bool>>,
+) {
+    if scheduled.replace(true) {
+        return;
     }
+    let closure = Closure::once(move |_: JsValue| {
+        scheduled.set(false);
+        // Skip if an update is already running.
+        let Ok(mut rt) = runtime.try_borrow_mut() else {
+            return;
+        };
+        rt.update();
+        drop(rt);
+        flush(&runtime, &interpreter);
+    });
+    let _ = js_sys::Promise::resolve(&JsValue::UNDEFINED).then(&closure);
+    closure.forget();
 }
 
 #[wasm_bindgen]
@@ -201,7 +699,17 @@ impl WebApp {
 
         let scheduler = LocalScheduler::new();
         let runtime = Rc::new(RefCell::new(Runtime::new(scheduler)));
-        let interpreter = Rc::new(RefCell::new(WebInterpreter::new(document, runtime.clone())));
+        let interpreter = Rc::new_cyclic(|this| {
+            RefCell::new(WebInterpreter::new(document, runtime.clone(), this.clone()))
+        });
+        INTERPRETER.with(|i| *i.borrow_mut() = Rc::downgrade(&interpreter));
+
+        // Spawned and woken tasks (resources behind Suspense) need an update to make progress.
+        let scheduled = Rc::new(Cell::new(false));
+        let (rt, interp) = (runtime.clone(), interpreter.clone());
+        nexa_core::suspense::set_wake_handler(move || {
+            schedule_flush(rt.clone(), interp.clone(), scheduled.clone())
+        });
 
         let app = Self {
             runtime,
@@ -233,13 +741,7 @@ impl WebApp {
         // But we can try to render a fallback if we detect critical state.
 
         self.runtime.borrow_mut().update();
-
-        let mutations = self.runtime.borrow_mut().drain_mutations();
-
-        let interpreter_clone = self.interpreter.clone();
-        self.interpreter
-            .borrow_mut()
-            .apply_mutations(mutations, interpreter_clone);
+        flush(&self.runtime, &self.interpreter);
 
         let end = performance.now();
         let duration = end - start;
@@ -275,30 +777,6 @@ impl WebApp {
         }
     }
 
-    pub fn hydrate(&mut self) -> Result<(), JsValue> {
-        let mut interpreter = self.interpreter.borrow_mut();
-        let window = web_sys::window().unwrap();
-        let document = window.document().unwrap();
-        let root = document.document_element().unwrap();
-
-        // Use query_selector_all to find all elements with data-nexa-id
-        let nodes = root.query_selector_all("[data-nexa-id]")?;
-        for i in 0..nodes.length() {
-            if let Some(node) = nodes.get(i) {
-                if let Some(el) = node.dyn_ref::<Element>() {
-                    if let Some(id_str) = el.get_attribute("data-nexa-id") {
-                        if let Ok(id) = id_str.parse::<u64>() {
-                            interpreter.nodes.insert(id, node);
-                        }
-                    }
-                }
-            }
-        }
-
-        web_sys::console::log_1(&"Hydration complete".into());
-        Ok(())
-    }
-
     pub fn setup_history_api(&self) {
         let window = web_sys::window().unwrap();
         let on_popstate = Closure::wrap(Box::new(|_event: web_sys::PopStateEvent| {
@@ -328,6 +806,54 @@ impl WebApp {
         root_id: &str,
         root_fn: fn() -> nexa_core::NodeId,
     ) -> Result<(), JsValue> {
+        self.set_root(root_id)?;
+        web_sys::console::log_1(&format!("Nexa: Mounting application to {}", root_id).into());
+
+        self.runtime.borrow_mut().mount("Root", root_fn);
+        self.commit_mount()
+    }
+
+    /// Like `mount`, but adopts the markup `nexa-ssr` rendered into the root
+    /// element instead of replacing it. In debug builds, markup that doesn't
+    /// match the app is reported on the console with the node's path.
+    pub fn hydrate(
+        &mut self,
+        root_id: &str,
+        root_fn: fn() -> nexa_core::NodeId,
+    ) -> Result<(), JsValue> {
+        self.set_root(root_id)?;
+        web_sys::console::log_1(&format!("Nexa: Hydrating application in {}", root_id).into());
+
+        self.runtime.borrow_mut().hydrate("Root", root_fn);
+        self.commit_mount()
+    }
+
+    /// Mounts `root_fn` as a separate root in the element `container_id`,
+    /// next to the app and any other widgets on the page. Widgets share
+    /// the app's signals and re-render in the same updates.
+    pub fn mount_at(
+        &mut self,
+        name: &'static str,
+        container_id: &str,
+        root_fn: fn() -> nexa_core::NodeId,
+    ) -> Result<(), JsValue> {
+        let id = container_id.strip_prefix('#').unwrap_or(container_id);
+        web_sys::console::log_1(&format!("Nexa: Mounting {} to #{}", name, id).into());
+
+        self.runtime.borrow_mut().mount_at(name, id, root_fn);
+        self.commit_mount()
+    }
+
+    /// Removes the widget `name` mounted with `mount_at`.
+    pub fn unmount(&mut self, name: &str) -> Result<(), JsValue> {
+        if !self.runtime.borrow_mut().unmount(name) {
+            return Err(JsValue::from_str(&format!("No root named {}", name)));
+        }
+        self.update()
+    }
+
+    /// Makes the element `root_id` the container, id 0.
+    fn set_root(&mut self, root_id: &str) -> Result<(), JsValue> {
         let window = web_sys::window().unwrap();
         let document = window.document().unwrap();
         let id = root_id.strip_prefix('#').unwrap_or(root_id);
@@ -335,16 +861,14 @@ impl WebApp {
             .get_element_by_id(id)
             .ok_or_else(|| JsValue::from_str(&format!("Root element not found: {}", id)))?;
 
-        // Initialize root mapping
-        self.interpreter
-            .borrow_mut()
-            .nodes
-            .insert(0, root_el.into());
-
-        web_sys::console::log_1(&format!("Nexa: Mounting application to {}", root_id).into());
-
-        self.runtime.borrow_mut().mount("Root", root_fn);
+        let root: Node = root_el.into();
+        let mut interpreter = self.interpreter.borrow_mut();
+        interpreter.add_container(&root);
+        interpreter.nodes.insert(0, root);
+        Ok(())
+    }
 
+    fn commit_mount(&mut self) -> Result<(), JsValue> {
         match self.update() {
             Ok(_) => {
                 web_sys::console::log_1(&"Nexa: Mount successful".into());
@@ -50,3 +50,40 @@ fn test_dom_counter() {
     // Check update
     assert_eq!(counter_el.inner_html(), "Count: 1");
 }
+
+fn widget_app() -> nexa_core::NodeId {
+    let count = create_signal(0);
+    rsx! {
+        div {
+            span { id: "widget-counter", "Clicks: {count.get()}" }
+            button {
+                id: "widget-btn",
+                onclick: move |_| count.set(count.get() + 1),
+                "Click"
+            }
+        }
+    }
+    .pop()
+    .unwrap()
+}
+
+#[wasm_bindgen_test]
+fn test_widget_without_app_root_handles_clicks() {
+    let mut app = WebApp::new();
+
+    // No `mount`, only a widget in its own container.
+    let document = web_sys::window().unwrap().document().unwrap();
+    let container = document.create_element("div").unwrap();
+    container.set_id("widget-root");
+    document.body().unwrap().append_child(&container).unwrap();
+
+    app.mount_at("Widget", "#widget-root", widget_app).unwrap();
+
+    let counter_el = document.get_element_by_id("widget-counter").unwrap();
+    assert_eq!(counter_el.inner_html(), "Clicks: 0");
+
+    let btn = document.get_element_by_id("widget-btn").unwrap();
+    btn.dyn_ref::<web_sys::HtmlElement>().unwrap().click();
+
+    assert_eq!(counter_el.inner_html(), "Clicks: 1");
+}